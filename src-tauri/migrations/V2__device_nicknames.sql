@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS device_nicknames (
+  serial_number TEXT PRIMARY KEY,
+  nickname TEXT NOT NULL
+);
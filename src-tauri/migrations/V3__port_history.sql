@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS port_history (
+  id INTEGER PRIMARY KEY,
+  recorded_at TEXT NOT NULL,
+  ports_json TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS port_history_recorded_at_idx
+  ON port_history(recorded_at DESC);
@@ -0,0 +1,209 @@
+//! Read-only handles for monitor-style use.
+//!
+//! `serialport` always opens the device node for reading and writing, so a
+//! read-only handle cannot drop OS write access: it still needs the same
+//! permissions to open, and still holds the port exclusively. What it does
+//! guarantee is that no byte is ever written through it. Modem control lines
+//! (DTR/RTS) are not data and stay available.
+
+use crate::error::SerialError;
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AccessMode {
+    ReadWrite,
+    ReadOnly,
+}
+
+impl AccessMode {
+    pub(crate) fn parse(mode: Option<&str>) -> Result<Self, SerialError> {
+        match mode {
+            None | Some("read-write") => Ok(AccessMode::ReadWrite),
+            Some("read-only") => Ok(AccessMode::ReadOnly),
+            Some(other) => Err(SerialError::InvalidConfig {
+                message: format!("unknown access mode {other:?}; expected read-write or read-only"),
+            }),
+        }
+    }
+
+    pub(crate) fn wrap(self, port: Box<dyn SerialPort>) -> Box<dyn SerialPort> {
+        match self {
+            AccessMode::ReadWrite => port,
+            AccessMode::ReadOnly => Box::new(ReadOnlyPort(port)),
+        }
+    }
+}
+
+/// Delegates everything to the inner port except data writes, which fail.
+pub(crate) struct ReadOnlyPort(pub(crate) Box<dyn SerialPort>);
+
+fn read_only() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        "handle was opened read-only",
+    )
+}
+
+impl Read for ReadOnlyPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Write for ReadOnlyPort {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(read_only())
+    }
+
+    /// Nothing can have been written, so there is nothing to flush.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SerialPort for ReadOnlyPort {
+    fn name(&self) -> Option<String> {
+        self.0.name()
+    }
+
+    fn baud_rate(&self) -> serialport::Result<u32> {
+        self.0.baud_rate()
+    }
+
+    fn data_bits(&self) -> serialport::Result<DataBits> {
+        self.0.data_bits()
+    }
+
+    fn flow_control(&self) -> serialport::Result<FlowControl> {
+        self.0.flow_control()
+    }
+
+    fn parity(&self) -> serialport::Result<Parity> {
+        self.0.parity()
+    }
+
+    fn stop_bits(&self) -> serialport::Result<StopBits> {
+        self.0.stop_bits()
+    }
+
+    fn timeout(&self) -> Duration {
+        self.0.timeout()
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> serialport::Result<()> {
+        self.0.set_baud_rate(baud_rate)
+    }
+
+    fn set_data_bits(&mut self, data_bits: DataBits) -> serialport::Result<()> {
+        self.0.set_data_bits(data_bits)
+    }
+
+    fn set_flow_control(&mut self, flow_control: FlowControl) -> serialport::Result<()> {
+        self.0.set_flow_control(flow_control)
+    }
+
+    fn set_parity(&mut self, parity: Parity) -> serialport::Result<()> {
+        self.0.set_parity(parity)
+    }
+
+    fn set_stop_bits(&mut self, stop_bits: StopBits) -> serialport::Result<()> {
+        self.0.set_stop_bits(stop_bits)
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> serialport::Result<()> {
+        self.0.set_timeout(timeout)
+    }
+
+    fn write_request_to_send(&mut self, level: bool) -> serialport::Result<()> {
+        self.0.write_request_to_send(level)
+    }
+
+    fn write_data_terminal_ready(&mut self, level: bool) -> serialport::Result<()> {
+        self.0.write_data_terminal_ready(level)
+    }
+
+    fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+        self.0.read_clear_to_send()
+    }
+
+    fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+        self.0.read_data_set_ready()
+    }
+
+    fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+        self.0.read_ring_indicator()
+    }
+
+    fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+        self.0.read_carrier_detect()
+    }
+
+    fn bytes_to_read(&self) -> serialport::Result<u32> {
+        self.0.bytes_to_read()
+    }
+
+    fn bytes_to_write(&self) -> serialport::Result<u32> {
+        self.0.bytes_to_write()
+    }
+
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> serialport::Result<()> {
+        self.0.clear(buffer_to_clear)
+    }
+
+    fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+        Ok(Box::new(ReadOnlyPort(self.0.try_clone()?)))
+    }
+
+    fn set_break(&self) -> serialport::Result<()> {
+        self.0.set_break()
+    }
+
+    fn clear_break(&self) -> serialport::Result<()> {
+        self.0.clear_break()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::write_hex;
+    use crate::frame::ProtocolConfig;
+    use crate::mock_port::MockPort;
+    use crate::serial::{read_raw, registry, send_no_reply, send_request, DeviceEntry};
+
+    #[test]
+    fn access_mode_parses_known_names() {
+        assert_eq!(AccessMode::parse(None).unwrap(), AccessMode::ReadWrite);
+        assert_eq!(
+            AccessMode::parse(Some("read-only")).unwrap(),
+            AccessMode::ReadOnly
+        );
+        assert!(matches!(
+            AccessMode::parse(Some("write-only")),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+    }
+
+    #[test]
+    fn read_only_handle_refuses_writes_but_reads() {
+        let port = MockPort::new();
+        let handle_id = registry().insert(DeviceEntry::new(
+            "mock".to_string(),
+            AccessMode::ReadOnly.wrap(Box::new(port.clone())),
+            ProtocolConfig::default(),
+        ));
+
+        assert!(matches!(
+            send_request(handle_id, vec![0x01]),
+            Err(SerialError::IoError { .. })
+        ));
+        assert!(send_no_reply(handle_id, vec![0x01]).is_err());
+        assert!(write_hex(handle_id, "C8".to_string()).is_err());
+        assert!(port.written().is_empty());
+
+        port.push_rx(&[0x11, 0x22]);
+        assert_eq!(read_raw(handle_id, 2).unwrap(), vec![0x11, 0x22]);
+    }
+}
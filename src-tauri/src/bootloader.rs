@@ -0,0 +1,135 @@
+//! Timed control-line and magic-frame sequence that drops a device into its
+//! bootloader, run under one lock hold so nothing can interleave with it.
+
+use crate::error::SerialError;
+use crate::frame::{build_frame_with, read_frame};
+use crate::serial::{check_outgoing, with_entry, DeviceEntry};
+use crate::stream::check_stream_conflict;
+use serde::Deserialize;
+use serialport::ClearBuffer;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BootloaderConfig {
+    /// RTS level driven first and held for the rest of the sequence.
+    pub rts_level: bool,
+    /// DTR pulses after RTS is set: asserted for `pulse_width_ms`, then
+    /// released for `pulse_gap_ms`.
+    pub dtr_pulses: u32,
+    pub pulse_width_ms: u64,
+    pub pulse_gap_ms: u64,
+    /// Wait after the last pulse before the magic frame goes out.
+    pub settle_ms: u64,
+    /// TYPE + PAYLOAD of the magic frame, framed like any request.
+    pub magic_frame: Vec<u8>,
+    /// TYPE of the frame the bootloader sends once it is ready. Other frames
+    /// arriving first are ignored.
+    pub ready_type: u8,
+    pub ready_timeout_ms: u64,
+}
+
+fn run_sequence(entry: &mut DeviceEntry, config: &BootloaderConfig) -> Result<bool, SerialError> {
+    check_outgoing(entry, &config.magic_frame)?;
+    let frame = build_frame_with(&config.magic_frame, &entry.protocol)?;
+    check_stream_conflict(entry)?;
+
+    entry.port.write_request_to_send(config.rts_level)?;
+    for _ in 0..config.dtr_pulses {
+        entry.port.write_data_terminal_ready(true)?;
+        std::thread::sleep(Duration::from_millis(config.pulse_width_ms));
+        entry.port.write_data_terminal_ready(false)?;
+        std::thread::sleep(Duration::from_millis(config.pulse_gap_ms));
+    }
+    std::thread::sleep(Duration::from_millis(config.settle_ms));
+
+    // Whatever the application firmware printed on the way down is not the
+    // ready frame.
+    let _ = entry.port.clear(ClearBuffer::Input);
+    entry.port.write_all(&frame)?;
+    entry.port.flush()?;
+
+    let deadline = Instant::now() + Duration::from_millis(config.ready_timeout_ms);
+    while Instant::now() < deadline {
+        match read_frame(&mut *entry.port, &entry.protocol) {
+            Ok(frame) if frame.first() == Some(&config.ready_type) => return Ok(true),
+            Ok(_) | Err(SerialError::Timeout | SerialError::CrcMismatch) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(false)
+}
+
+/// Runs the bootloader entry sequence: RTS, DTR pulses, settle, magic frame,
+/// then waits for the ready frame. Returns whether it arrived in time;
+/// control-line and write failures are errors.
+#[tauri::command]
+pub fn enter_bootloader(handle_id: u64, config: BootloaderConfig) -> Result<bool, SerialError> {
+    with_entry(handle_id, "enter_bootloader", |entry| {
+        run_sequence(entry, &config)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::build_frame;
+    use crate::mock_port::MockPort;
+    use crate::serial::insert_mock;
+
+    fn config() -> BootloaderConfig {
+        BootloaderConfig {
+            rts_level: true,
+            dtr_pulses: 2,
+            pulse_width_ms: 10,
+            pulse_gap_ms: 5,
+            settle_ms: 5,
+            magic_frame: vec![0x7F, 0xB0, 0x07],
+            ready_type: 0x7E,
+            ready_timeout_ms: 100,
+        }
+    }
+
+    #[test]
+    fn sequence_pulses_lines_then_sends_magic_frame() {
+        let port = MockPort::new();
+        let mut reply = build_frame(&[0x01, 0x00]).unwrap();
+        reply.extend(build_frame(&[0x7E]).unwrap());
+        port.queue_reply(&reply);
+        let handle_id = insert_mock(&port);
+
+        let started = Instant::now();
+        assert!(enter_bootloader(handle_id, config()).unwrap());
+        assert!(started.elapsed() >= Duration::from_millis(35));
+
+        let state = port.state.lock().unwrap();
+        assert_eq!(
+            state.line_changes,
+            vec![
+                ("rts", true),
+                ("dtr", true),
+                ("dtr", false),
+                ("dtr", true),
+                ("dtr", false),
+            ]
+        );
+        assert_eq!(state.written, build_frame(&[0x7F, 0xB0, 0x07]).unwrap());
+    }
+
+    #[test]
+    fn missing_ready_frame_returns_false() {
+        let port = MockPort::new();
+        let handle_id = insert_mock(&port);
+        assert!(!enter_bootloader(handle_id, config()).unwrap());
+        assert_eq!(port.written(), build_frame(&[0x7F, 0xB0, 0x07]).unwrap());
+    }
+
+    #[test]
+    fn control_line_failure_aborts_before_the_magic_frame() {
+        let port = MockPort::new();
+        port.state.lock().unwrap().fail_control_lines = true;
+        let handle_id = insert_mock(&port);
+        assert!(enter_bootloader(handle_id, config()).is_err());
+        assert!(port.written().is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+//! Offline replay of recorded traffic through the frame reader, for
+//! regression-testing protocol settings against real captures.
+
+use crate::crc;
+use crate::echo::EchoPort;
+use crate::error::SerialError;
+use crate::frame::{read_frame_counted, LengthConvention, ProtocolConfig, VSCOPE_SYNC_BYTE};
+use crate::serial::with_entry;
+use crate::stream::check_stream_conflict;
+use serde::Serialize;
+use std::io::{ErrorKind, Read};
+
+/// Sync bytes `detect_framing` tries, VScope's first.
+const CANDIDATE_SYNC_BYTES: [u8; 5] = [VSCOPE_SYNC_BYTE, 0xAA, 0x55, 0xA5, 0x7E];
+const MAX_DETECT_SAMPLE: usize = 64 * 1024;
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParseReport {
+    /// TYPE + PAYLOAD of every frame that parsed, in capture order.
+    pub frames: Vec<Vec<u8>>,
+    /// Bytes discarded while hunting for a frame start, including a footer
+    /// left behind by a frame rejected on its CRC.
+    pub bytes_skipped: usize,
+    pub crc_errors: usize,
+    /// Frames rejected by the footer or inner length check.
+    pub invalid_frames: usize,
+    /// Bytes of a frame cut off by the end of the capture.
+    pub trailing_bytes: usize,
+}
+
+/// Runs `read_frame` over `bytes` until they are used up. A rejected frame
+/// is counted and parsing resumes right after it, as it would on a live
+/// port.
+#[tauri::command]
+pub fn parse_capture(bytes: Vec<u8>, config: ProtocolConfig) -> Result<ParseReport, SerialError> {
+    config.validate()?;
+    let mut port = EchoPort::replay(&bytes);
+    let mut report = ParseReport::default();
+    while port.remaining() > 0 {
+        let before = port.remaining();
+        let mut skipped = 0;
+        let result = read_frame_counted(&mut port, &config, None, &mut skipped);
+        report.bytes_skipped += skipped;
+        match result {
+            Ok(frame) => report.frames.push(frame),
+            Err(SerialError::CrcMismatch) => report.crc_errors += 1,
+            Err(SerialError::InvalidResponse { .. }) => report.invalid_frames += 1,
+            // The capture ran out part way through a frame.
+            Err(SerialError::Timeout) | Err(SerialError::IoError { .. }) => {
+                report.trailing_bytes = before - skipped;
+                break;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(report)
+}
+
+/// Reads up to `sample_bytes` from the handle (stopping early once a read
+/// comes back empty) and returns the framing that parses it best, built on
+/// the handle's current config. `None` when no candidate yields a frame.
+/// The handle's config is left unchanged.
+#[tauri::command]
+pub fn detect_framing(
+    handle_id: u64,
+    sample_bytes: usize,
+) -> Result<Option<ProtocolConfig>, SerialError> {
+    if !(1..=MAX_DETECT_SAMPLE).contains(&sample_bytes) {
+        return Err(SerialError::InvalidConfig {
+            message: format!("sample size must be 1..={MAX_DETECT_SAMPLE} bytes"),
+        });
+    }
+    let (sample, base) = with_entry(handle_id, "detect_framing", |entry| {
+        check_stream_conflict(entry)?;
+        let mut sample = vec![0u8; sample_bytes];
+        let mut filled = 0;
+        while filled < sample_bytes {
+            match entry.port.read(&mut sample[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    break
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+        sample.truncate(filled);
+        Ok((sample, entry.protocol.clone()))
+    })?;
+    detect_framing_in(&sample, &base)
+}
+
+/// Scores every sync byte / checksum / length convention combination over
+/// `sample`: one point per frame, minus one per CRC or validation failure.
+/// Ties go to the earlier candidate, so VScope's defaults win when several
+/// parse equally.
+pub(crate) fn detect_framing_in(
+    sample: &[u8],
+    base: &ProtocolConfig,
+) -> Result<Option<ProtocolConfig>, SerialError> {
+    let mut best: Option<(i64, ProtocolConfig)> = None;
+    for sync_byte in CANDIDATE_SYNC_BYTES {
+        for length_convention in [LengthConvention::BodyAndCrc, LengthConvention::Body] {
+            for checksum in crc::candidates() {
+                let candidate = ProtocolConfig {
+                    sync_byte,
+                    length_convention,
+                    crc_variant: checksum.variant,
+                    crc_coverage: checksum.coverage,
+                    crc_init: checksum.init,
+                    ..base.clone()
+                };
+                if candidate.validate().is_err() {
+                    continue;
+                }
+                let report = parse_capture(sample.to_vec(), candidate.clone())?;
+                if report.frames.is_empty() {
+                    continue;
+                }
+                let score = report.frames.len() as i64
+                    - report.crc_errors as i64
+                    - report.invalid_frames as i64;
+                if best.as_ref().is_none_or(|(top, _)| score > *top) {
+                    best = Some((score, candidate));
+                }
+            }
+        }
+    }
+    Ok(best.map(|(_, config)| config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crc::{CrcCoverage, CrcVariant};
+    use crate::frame::{build_frame, build_frame_with};
+    use crate::mock_port::MockPort;
+    use crate::serial::insert_mock;
+
+    #[test]
+    fn capture_with_noise_bad_crc_and_truncated_tail() {
+        let mut bad = build_frame(&[0x02, 0x10]).unwrap();
+        *bad.last_mut().unwrap() ^= 0xFF;
+        let tail = build_frame(&[0x03, 0x20, 0x21]).unwrap();
+
+        let mut capture = build_frame(&[0x01, 0xAA]).unwrap();
+        capture.extend([0x00, 0x13, 0x37]);
+        capture.extend(&bad);
+        capture.extend(build_frame(&[0x04]).unwrap());
+        capture.extend(&tail[..3]);
+
+        let report = parse_capture(capture, ProtocolConfig::default()).unwrap();
+        assert_eq!(
+            report,
+            ParseReport {
+                frames: vec![vec![0x01, 0xAA], vec![0x04]],
+                bytes_skipped: 3,
+                crc_errors: 1,
+                invalid_frames: 0,
+                trailing_bytes: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn detect_framing_finds_the_only_clean_config() {
+        let device = ProtocolConfig {
+            sync_byte: 0xAA,
+            crc_variant: CrcVariant::Crc8Maxim,
+            crc_coverage: CrcCoverage::LenAndPayload,
+            crc_init: 0xFF,
+            length_convention: LengthConvention::Body,
+            ..ProtocolConfig::default()
+        };
+        let port = MockPort::new();
+        port.push_rx(&[0x00, 0x13]);
+        for payload in [
+            &[0x01, 0x10][..],
+            &[0x02, 0x20, 0x21],
+            &[0x03],
+            &[0x04, 0x40],
+        ] {
+            port.push_rx(&build_frame_with(payload, &device).unwrap());
+        }
+        let handle_id = insert_mock(&port);
+
+        assert_eq!(detect_framing(handle_id, 256).unwrap(), Some(device));
+    }
+
+    #[test]
+    fn detect_framing_on_noise_finds_nothing() {
+        let sample = [0x00, 0x13, 0x37, 0x42];
+        assert_eq!(
+            detect_framing_in(&sample, &ProtocolConfig::default()).unwrap(),
+            None
+        );
+        assert!(matches!(
+            detect_framing(insert_mock(&MockPort::new()), 0),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+    }
+
+    #[test]
+    fn noise_only_capture_is_all_skipped() {
+        let report = parse_capture(vec![0x01, 0x02, 0x03], ProtocolConfig::default()).unwrap();
+        assert!(report.frames.is_empty());
+        assert_eq!(report.bytes_skipped, 3);
+        assert_eq!(report.trailing_bytes, 0);
+    }
+}
@@ -0,0 +1,165 @@
+//! Side-effect-free validation of a `SerialConfig`, for inline errors in the
+//! connect dialog before anything is opened.
+
+use crate::access::AccessMode;
+use crate::error::SerialError;
+use crate::frame::build_frame_with;
+use crate::read_buffer::read_buffer_size;
+use crate::serial::{DuplicatePolicy, SerialConfig};
+use serde::Serialize;
+
+/// Fastest rate accepted; above this no adapter we support keeps up.
+const MAX_BAUD_RATE: u32 = 12_000_000;
+const MAX_READ_TIMEOUT_MS: u64 = 60_000;
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigError {
+    /// camelCase name of the offending `SerialConfig` field, indexed for
+    /// list entries (`initFrames[1]`).
+    pub field: String,
+    pub message: String,
+}
+
+fn message(err: SerialError) -> String {
+    match err {
+        SerialError::InvalidConfig { message } => message,
+        other => other.to_string(),
+    }
+}
+
+/// Checks everything `open_device` would reject about `config`, plus range
+/// checks on rates and timeouts, and reports every problem rather than the
+/// first. Enum fields (parity, data bits, stop bits) are already checked by
+/// deserialization.
+#[tauri::command]
+pub fn validate_config(config: SerialConfig) -> Result<(), Vec<ConfigError>> {
+    let mut errors = Vec::new();
+    let mut fail = |field: &str, message: String| {
+        errors.push(ConfigError {
+            field: field.to_string(),
+            message,
+        })
+    };
+
+    if !(1..=MAX_BAUD_RATE).contains(&config.baud_rate) {
+        fail(
+            "baudRate",
+            format!("baud rate must be within 1..={MAX_BAUD_RATE}"),
+        );
+    }
+    if config.read_timeout_ms > MAX_READ_TIMEOUT_MS {
+        fail(
+            "readTimeoutMs",
+            format!("read timeout must be at most {MAX_READ_TIMEOUT_MS} ms"),
+        );
+    } else if config.read_timeout_ms == 0 && !config.nonblocking {
+        fail(
+            "readTimeoutMs",
+            "read timeout must be at least 1 ms unless nonblocking".to_string(),
+        );
+    }
+    if let Err(err) = AccessMode::parse(config.access_mode.as_deref()) {
+        fail("accessMode", message(err));
+    }
+    if let Err(err) = DuplicatePolicy::parse(config.on_duplicate.as_deref()) {
+        fail("onDuplicate", message(err));
+    }
+    if let Err(err) = read_buffer_size(config.read_buffer_size) {
+        fail("readBufferSize", message(err));
+    }
+    if config.settle_quiet_ms == Some(0) {
+        fail(
+            "settleQuietMs",
+            "settle quiet period must be at least 1 ms".to_string(),
+        );
+    }
+    if config.retry.deadline_ms == Some(0) {
+        fail(
+            "deadlineMs",
+            "request deadline must be at least 1 ms".to_string(),
+        );
+    }
+
+    let protocol_errors = config.protocol.field_errors();
+    let protocol_valid = protocol_errors.is_empty();
+    for (field, message) in protocol_errors {
+        fail(field, message);
+    }
+    // Frame checks are only meaningful against a valid protocol.
+    if protocol_valid {
+        for (index, frame) in config.init_frames.iter().enumerate() {
+            let field = format!("initFrames[{index}]");
+            if frame.is_empty() && !config.protocol.allow_empty_payload {
+                fail(&field, "init frame must include message type".to_string());
+            } else if let Err(err) = build_frame_with(frame, &config.protocol) {
+                fail(&field, message(err));
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::ProtocolConfig;
+    use crate::serial::test_config;
+
+    #[test]
+    fn valid_config_passes() {
+        let config = SerialConfig {
+            init_frames: vec![vec![0x30, 0x01]],
+            settle_quiet_ms: Some(50),
+            ..test_config()
+        };
+        assert_eq!(validate_config(config), Ok(()));
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        let config = SerialConfig {
+            baud_rate: 0,
+            read_timeout_ms: 0,
+            access_mode: Some("write-only".to_string()),
+            read_buffer_size: Some(1),
+            init_frames: vec![vec![0x30], Vec::new()],
+            protocol: ProtocolConfig {
+                preamble_count: 0,
+                max_payload_len: usize::MAX,
+                ..ProtocolConfig::default()
+            },
+            ..test_config()
+        };
+        let fields: Vec<String> = validate_config(config)
+            .unwrap_err()
+            .into_iter()
+            .map(|err| err.field)
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                "baudRate",
+                "readTimeoutMs",
+                "accessMode",
+                "readBufferSize",
+                "maxPayloadLen",
+                "preambleCount",
+            ]
+        );
+
+        let config = SerialConfig {
+            init_frames: vec![vec![0x30], Vec::new(), vec![0; 4096]],
+            ..test_config()
+        };
+        let errors = validate_config(config).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].field, "initFrames[1]");
+        assert_eq!(errors[1].field, "initFrames[2]");
+    }
+}
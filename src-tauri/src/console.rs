@@ -0,0 +1,289 @@
+//! Hex-string I/O for the debug console, so the frontend can display and
+//! send raw bytes without formatting them in JS, plus byte-level burst reads
+//! for devices whose replies end only in silence.
+
+use crate::error::SerialError;
+use crate::serial::{with_entry, write_paced};
+use serialport::SerialPort;
+use std::io::{ErrorKind, Write};
+use std::time::{Duration, Instant};
+
+/// Largest `max_len` a raw read accepts; the buffer is allocated up front.
+pub(crate) const MAX_RAW_READ: usize = 64 * 1024;
+
+pub(crate) fn check_read_len(max_len: usize) -> Result<(), SerialError> {
+    if max_len > MAX_RAW_READ {
+        return Err(SerialError::InvalidConfig {
+            message: format!("maxLen must be at most {MAX_RAW_READ} bytes"),
+        });
+    }
+    Ok(())
+}
+
+/// Formats bytes as space-separated uppercase hex, e.g. `"C8 04 01"`.
+pub(crate) fn format_hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses hex digits into bytes. Whitespace anywhere is ignored, so both
+/// `"C8 04"` and `"c804"` work; an odd digit count is rejected.
+fn parse_hex(hex: &str) -> Result<Vec<u8>, SerialError> {
+    let digits: Vec<u8> = hex
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| {
+            c.to_digit(16)
+                .map(|d| d as u8)
+                .ok_or_else(|| SerialError::InvalidConfig {
+                    message: format!("invalid hex digit {c:?}"),
+                })
+        })
+        .collect::<Result<_, _>>()?;
+    if !digits.len().is_multiple_of(2) {
+        return Err(SerialError::InvalidConfig {
+            message: "hex input has an odd number of digits".to_string(),
+        });
+    }
+    Ok(digits
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair[1])
+        .collect())
+}
+
+/// Reads until `max_len` bytes have arrived or `timeout` elapses, returning
+/// whatever was received. With `idle_gap` the read also ends once data has
+/// started and then paused for that long. The handle's own timeout is
+/// restored afterwards.
+fn read_up_to(
+    port: &mut dyn SerialPort,
+    max_len: usize,
+    timeout: Duration,
+    idle_gap: Option<Duration>,
+) -> Result<Vec<u8>, SerialError> {
+    check_read_len(max_len)?;
+    let original_timeout = port.timeout();
+    let result = read_loop(port, max_len, timeout, idle_gap);
+    let restored = port.set_timeout(original_timeout);
+    let bytes = result?;
+    restored?;
+    Ok(bytes)
+}
+
+fn read_loop(
+    port: &mut dyn SerialPort,
+    max_len: usize,
+    timeout: Duration,
+    idle_gap: Option<Duration>,
+) -> Result<Vec<u8>, SerialError> {
+    let deadline = Instant::now() + timeout;
+    let mut bytes = vec![0u8; max_len];
+    let mut filled = 0;
+    while filled < max_len {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        let wait = match idle_gap {
+            Some(gap) if filled > 0 => gap.min(remaining),
+            _ => remaining,
+        };
+        port.set_timeout(wait)?;
+        match port.read(&mut bytes[filled..]) {
+            Ok(n) => filled += n,
+            Err(err) if matches!(err.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => break,
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+    bytes.truncate(filled);
+    Ok(bytes)
+}
+
+#[tauri::command]
+pub fn read_hex(handle_id: u64, max_len: usize, timeout_ms: u64) -> Result<String, SerialError> {
+    with_entry(handle_id, "read_hex", |entry| {
+        let bytes = read_up_to(
+            &mut *entry.port,
+            max_len,
+            Duration::from_millis(timeout_ms),
+            None,
+        )?;
+        Ok(format_hex(&bytes))
+    })
+}
+
+/// Reads a burst of raw bytes: everything up to `max_len` that arrives before
+/// the line goes quiet for `idle_gap_ms`. The gap only counts once the first
+/// byte is in; `overall_timeout_ms` bounds the whole read, and an empty
+/// result means nothing arrived in that time.
+#[tauri::command]
+pub fn read_burst(
+    handle_id: u64,
+    max_len: usize,
+    idle_gap_ms: u64,
+    overall_timeout_ms: u64,
+) -> Result<Vec<u8>, SerialError> {
+    if idle_gap_ms == 0 {
+        return Err(SerialError::InvalidConfig {
+            message: "idleGapMs must be at least 1".to_string(),
+        });
+    }
+    with_entry(handle_id, "read_burst", |entry| {
+        read_up_to(
+            &mut *entry.port,
+            max_len,
+            Duration::from_millis(overall_timeout_ms),
+            Some(Duration::from_millis(idle_gap_ms)),
+        )
+    })
+}
+
+#[tauri::command]
+pub fn write_hex(handle_id: u64, hex: String) -> Result<(), SerialError> {
+    let bytes = parse_hex(&hex)?;
+    with_entry(handle_id, "write_hex", |entry| {
+        // Arbitrary bytes have no TYPE to check, so a policy blocks them all.
+        if entry.policy.is_restricted() {
+            return Err(SerialError::InvalidConfig {
+                message: "raw writes are refused while a message policy is set".to_string(),
+            });
+        }
+        write_paced(
+            &mut *entry.port,
+            &bytes,
+            entry.inter_byte_delay,
+            entry.write_retries,
+        )?;
+        if entry.auto_flush {
+            entry.port.flush()?;
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_port::MockPort;
+    use crate::serial::insert_mock;
+
+    #[test]
+    fn hex_round_trips() {
+        let bytes = vec![0xC8, 0x04, 0x01, 0x02, 0x03, 0x3F];
+        let hex = format_hex(&bytes);
+        assert_eq!(hex, "C8 04 01 02 03 3F");
+        assert_eq!(parse_hex(&hex).unwrap(), bytes);
+        assert_eq!(format_hex(&[]), "");
+        assert!(parse_hex("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_hex_tolerates_whitespace_and_case() {
+        assert_eq!(parse_hex("c8\t04\n 0 1").unwrap(), vec![0xC8, 0x04, 0x01]);
+        assert_eq!(parse_hex("  abCD  ").unwrap(), vec![0xAB, 0xCD]);
+    }
+
+    #[test]
+    fn parse_hex_rejects_odd_length_and_bad_digits() {
+        assert!(matches!(
+            parse_hex("C8 0"),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+        assert!(matches!(
+            parse_hex("C8 0G"),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+        assert!(matches!(
+            parse_hex("0xC8"),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+    }
+
+    #[test]
+    fn read_hex_returns_what_arrived_within_timeout() {
+        let port = MockPort::with_rx(&[0xC8, 0x02, 0x01]);
+        let handle_id = insert_mock(&port);
+        assert_eq!(read_hex(handle_id, 2, 20).unwrap(), "C8 02");
+        assert_eq!(read_hex(handle_id, 16, 20).unwrap(), "01");
+        assert_eq!(read_hex(handle_id, 16, 10).unwrap(), "");
+    }
+
+    #[test]
+    fn oversized_reads_are_refused() {
+        let port = MockPort::with_rx(&[0x01]);
+        let handle_id = insert_mock(&port);
+        assert!(matches!(
+            read_hex(handle_id, MAX_RAW_READ + 1, 10),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+        assert!(matches!(
+            read_burst(handle_id, usize::MAX, 10, 10),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+        assert_eq!(read_hex(handle_id, MAX_RAW_READ, 10).unwrap(), "01");
+    }
+
+    #[test]
+    fn write_hex_writes_raw_bytes() {
+        let port = MockPort::new();
+        let handle_id = insert_mock(&port);
+        write_hex(handle_id, "C8 04 01 02 03 3F".to_string()).unwrap();
+        assert_eq!(port.written(), vec![0xC8, 0x04, 0x01, 0x02, 0x03, 0x3F]);
+        assert!(write_hex(handle_id, "C8 4".to_string()).is_err());
+        assert_eq!(port.written().len(), 6);
+    }
+
+    #[test]
+    fn write_hex_is_refused_while_a_message_policy_is_set() {
+        let port = MockPort::new();
+        let handle_id = insert_mock(&port);
+        crate::policy::set_message_policy(handle_id, Some(vec![0x01]), None, None).unwrap();
+        assert!(matches!(
+            write_hex(handle_id, "C8 02 01 00".to_string()),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+        assert!(port.written().is_empty());
+
+        crate::policy::set_message_policy(handle_id, None, None, None).unwrap();
+        write_hex(handle_id, "C8 02 01 00".to_string()).unwrap();
+        assert_eq!(port.written(), vec![0xC8, 0x02, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn read_burst_returns_promptly_after_the_line_goes_quiet() {
+        let port = MockPort::new();
+        port.schedule_rx(Duration::from_millis(10), &[0x01, 0x02, 0x03]);
+        port.schedule_rx(Duration::from_millis(20), &[0x04, 0x05]);
+        port.schedule_rx(Duration::from_millis(600), &[0x06]);
+        let handle_id = insert_mock(&port);
+
+        let started = Instant::now();
+        let burst = read_burst(handle_id, 64, 40, 1_000).unwrap();
+        assert_eq!(burst, vec![0x01, 0x02, 0x03, 0x04, 0x05]);
+        assert!(started.elapsed() < Duration::from_millis(300));
+        assert_eq!(port.state.lock().unwrap().scheduled.len(), 1);
+    }
+
+    #[test]
+    fn read_burst_stops_at_max_len_and_overall_timeout() {
+        let port = MockPort::new();
+        port.push_rx(&[0x01, 0x02, 0x03, 0x04]);
+        let handle_id = insert_mock(&port);
+        assert_eq!(
+            read_burst(handle_id, 3, 40, 1_000).unwrap(),
+            vec![0x01, 0x02, 0x03]
+        );
+
+        let started = Instant::now();
+        assert_eq!(read_burst(handle_id, 3, 40, 1_000).unwrap(), vec![0x04]);
+        assert!(started.elapsed() < Duration::from_millis(300));
+
+        let started = Instant::now();
+        assert!(read_burst(handle_id, 3, 40, 60).unwrap().is_empty());
+        assert!(started.elapsed() >= Duration::from_millis(60));
+    }
+}
@@ -0,0 +1,338 @@
+//! Checksum variants understood by the framing layer.
+//!
+//! The VScope default is CRC8 DVB-S2 over TYPE + PAYLOAD; the other variants
+//! exist for bring-up of third-party devices whose trailer is unknown.
+
+use crate::error::SerialError;
+use serde::{Deserialize, Serialize};
+use std::hint::black_box;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CrcVariant {
+    /// CRC8 DVB-S2 (poly `0xD5`), used by onboard/vscope.c.
+    Crc8DvbS2,
+    /// CRC8 SMBus (poly `0x07`).
+    Crc8Smbus,
+    /// CRC8 Maxim/Dallas 1-Wire (reflected poly `0x31`).
+    Crc8Maxim,
+    /// XOR of every covered byte.
+    Xor8,
+    /// Wrapping sum of every covered byte.
+    Sum8,
+}
+
+/// Which frame bytes feed the checksum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CrcCoverage {
+    /// TYPE + PAYLOAD (VScope default).
+    Payload,
+    /// LEN + TYPE + PAYLOAD.
+    LenAndPayload,
+    /// SYNC + LEN + TYPE + PAYLOAD.
+    Frame,
+}
+
+pub const ALL_VARIANTS: [CrcVariant; 5] = [
+    CrcVariant::Crc8DvbS2,
+    CrcVariant::Crc8Smbus,
+    CrcVariant::Crc8Maxim,
+    CrcVariant::Xor8,
+    CrcVariant::Sum8,
+];
+
+pub const ALL_COVERAGES: [CrcCoverage; 3] = [
+    CrcCoverage::Payload,
+    CrcCoverage::LenAndPayload,
+    CrcCoverage::Frame,
+];
+
+/// Init values tried when identifying an unknown checksum.
+const CANDIDATE_INITS: [u8; 2] = [0x00, 0xFF];
+
+impl CrcVariant {
+    pub fn name(self) -> &'static str {
+        match self {
+            CrcVariant::Crc8DvbS2 => "crc8-dvb-s2",
+            CrcVariant::Crc8Smbus => "crc8-smbus",
+            CrcVariant::Crc8Maxim => "crc8-maxim",
+            CrcVariant::Xor8 => "xor8",
+            CrcVariant::Sum8 => "sum8",
+        }
+    }
+
+    /// Inverse of `name`.
+    pub fn parse(name: &str) -> Option<Self> {
+        ALL_VARIANTS
+            .into_iter()
+            .find(|variant| variant.name() == name)
+    }
+
+    /// Feeds `data` into a running checksum. All variants are byte-wise, so
+    /// a checksum over several segments is a chain of `update` calls.
+    pub fn update(self, mut crc: u8, data: &[u8]) -> u8 {
+        match self {
+            CrcVariant::Crc8DvbS2 => {
+                for &byte in data {
+                    crc = CRC8_LUT[(crc ^ byte) as usize];
+                }
+            }
+            CrcVariant::Crc8Smbus => {
+                for &byte in data {
+                    crc ^= byte;
+                    for _ in 0..8 {
+                        crc = if crc & 0x80 != 0 {
+                            (crc << 1) ^ 0x07
+                        } else {
+                            crc << 1
+                        };
+                    }
+                }
+            }
+            CrcVariant::Crc8Maxim => {
+                for &byte in data {
+                    crc ^= byte;
+                    for _ in 0..8 {
+                        crc = if crc & 0x01 != 0 {
+                            (crc >> 1) ^ 0x8C
+                        } else {
+                            crc >> 1
+                        };
+                    }
+                }
+            }
+            CrcVariant::Xor8 => {
+                for &byte in data {
+                    crc ^= byte;
+                }
+            }
+            CrcVariant::Sum8 => {
+                for &byte in data {
+                    crc = crc.wrapping_add(byte);
+                }
+            }
+        }
+        crc
+    }
+}
+
+impl CrcCoverage {
+    pub fn name(self) -> &'static str {
+        match self {
+            CrcCoverage::Payload => "payload",
+            CrcCoverage::LenAndPayload => "len-and-payload",
+            CrcCoverage::Frame => "frame",
+        }
+    }
+
+    /// Inverse of `name`.
+    pub fn parse(name: &str) -> Option<Self> {
+        ALL_COVERAGES
+            .into_iter()
+            .find(|coverage| coverage.name() == name)
+    }
+}
+
+/// A fully specified checksum: algorithm, covered bytes and starting value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checksum {
+    pub variant: CrcVariant,
+    pub coverage: CrcCoverage,
+    pub init: u8,
+}
+
+impl Checksum {
+    /// Computes the trailer for a frame with the given SYNC and LEN bytes and
+    /// TYPE + PAYLOAD `body`.
+    pub fn compute(&self, sync: u8, len: u8, body: &[u8]) -> u8 {
+        let crc = match self.coverage {
+            CrcCoverage::Payload => self.init,
+            CrcCoverage::LenAndPayload => self.variant.update(self.init, &[len]),
+            CrcCoverage::Frame => self.variant.update(self.init, &[sync, len]),
+        };
+        self.variant.update(crc, body)
+    }
+
+    pub fn name(&self) -> String {
+        format!(
+            "{}/{}/0x{:02X}",
+            self.variant.name(),
+            self.coverage.name(),
+            self.init
+        )
+    }
+}
+
+/// Every variant/coverage/init combination `identify_checksum` tries.
+pub fn candidates() -> impl Iterator<Item = Checksum> {
+    ALL_VARIANTS.into_iter().flat_map(|variant| {
+        ALL_COVERAGES.into_iter().flat_map(move |coverage| {
+            CANDIDATE_INITS.into_iter().map(move |init| Checksum {
+                variant,
+                coverage,
+                init,
+            })
+        })
+    })
+}
+
+const CRC8_LUT: [u8; 256] = [
+    0x00, 0xD5, 0x7F, 0xAA, 0xFE, 0x2B, 0x81, 0x54, 0x29, 0xFC, 0x56, 0x83, 0xD7, 0x02, 0xA8, 0x7D,
+    0x52, 0x87, 0x2D, 0xF8, 0xAC, 0x79, 0xD3, 0x06, 0x7B, 0xAE, 0x04, 0xD1, 0x85, 0x50, 0xFA, 0x2F,
+    0xA4, 0x71, 0xDB, 0x0E, 0x5A, 0x8F, 0x25, 0xF0, 0x8D, 0x58, 0xF2, 0x27, 0x73, 0xA6, 0x0C, 0xD9,
+    0xF6, 0x23, 0x89, 0x5C, 0x08, 0xDD, 0x77, 0xA2, 0xDF, 0x0A, 0xA0, 0x75, 0x21, 0xF4, 0x5E, 0x8B,
+    0x9D, 0x48, 0xE2, 0x37, 0x63, 0xB6, 0x1C, 0xC9, 0xB4, 0x61, 0xCB, 0x1E, 0x4A, 0x9F, 0x35, 0xE0,
+    0xCF, 0x1A, 0xB0, 0x65, 0x31, 0xE4, 0x4E, 0x9B, 0xE6, 0x33, 0x99, 0x4C, 0x18, 0xCD, 0x67, 0xB2,
+    0x39, 0xEC, 0x46, 0x93, 0xC7, 0x12, 0xB8, 0x6D, 0x10, 0xC5, 0x6F, 0xBA, 0xEE, 0x3B, 0x91, 0x44,
+    0x6B, 0xBE, 0x14, 0xC1, 0x95, 0x40, 0xEA, 0x3F, 0x42, 0x97, 0x3D, 0xE8, 0xBC, 0x69, 0xC3, 0x16,
+    0xEF, 0x3A, 0x90, 0x45, 0x11, 0xC4, 0x6E, 0xBB, 0xC6, 0x13, 0xB9, 0x6C, 0x38, 0xED, 0x47, 0x92,
+    0xBD, 0x68, 0xC2, 0x17, 0x43, 0x96, 0x3C, 0xE9, 0x94, 0x41, 0xEB, 0x3E, 0x6A, 0xBF, 0x15, 0xC0,
+    0x4B, 0x9E, 0x34, 0xE1, 0xB5, 0x60, 0xCA, 0x1F, 0x62, 0xB7, 0x1D, 0xC8, 0x9C, 0x49, 0xE3, 0x36,
+    0x19, 0xCC, 0x66, 0xB3, 0xE7, 0x32, 0x98, 0x4D, 0x30, 0xE5, 0x4F, 0x9A, 0xCE, 0x1B, 0xB1, 0x64,
+    0x72, 0xA7, 0x0D, 0xD8, 0x8C, 0x59, 0xF3, 0x26, 0x5B, 0x8E, 0x24, 0xF1, 0xA5, 0x70, 0xDA, 0x0F,
+    0x20, 0xF5, 0x5F, 0x8A, 0xDE, 0x0B, 0xA1, 0x74, 0x09, 0xDC, 0x76, 0xA3, 0xF7, 0x22, 0x88, 0x5D,
+    0xD6, 0x03, 0xA9, 0x7C, 0x28, 0xFD, 0x57, 0x82, 0xFF, 0x2A, 0x80, 0x55, 0x01, 0xD4, 0x7E, 0xAB,
+    0x84, 0x51, 0xFB, 0x2E, 0x7A, 0xAF, 0x05, 0xD0, 0xAD, 0x78, 0xD2, 0x07, 0x53, 0x86, 0x2C, 0xF9,
+];
+
+/// Most bytes `benchmark_crc` will checksum in one call, so a typo in the
+/// arguments cannot stall the command thread for minutes.
+const MAX_BENCHMARK_BYTES: u64 = 1 << 30;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrcBenchmark {
+    pub variant: CrcVariant,
+    pub bytes_processed: u64,
+    pub elapsed_ns: u64,
+    /// Decimal megabytes (10^6 bytes) per second.
+    pub mb_per_s: f64,
+    pub ns_per_byte: f64,
+}
+
+/// Times `iterations` passes of `variant` over a `bytes`-long buffer. The
+/// buffer is filled before the clock starts.
+#[tauri::command]
+pub fn benchmark_crc(
+    variant: String,
+    bytes: usize,
+    iterations: usize,
+) -> Result<CrcBenchmark, SerialError> {
+    let variant = CrcVariant::parse(&variant).ok_or_else(|| SerialError::InvalidConfig {
+        message: format!("unknown crc variant {variant:?}"),
+    })?;
+    let total = (bytes as u64).saturating_mul(iterations as u64);
+    if total == 0 || total > MAX_BENCHMARK_BYTES {
+        return Err(SerialError::InvalidConfig {
+            message: format!("bytes * iterations must be 1..={MAX_BENCHMARK_BYTES}"),
+        });
+    }
+    let buffer: Vec<u8> = (0..bytes).map(|i| i as u8).collect();
+
+    let started = Instant::now();
+    let mut crc = 0;
+    for _ in 0..iterations {
+        crc = variant.update(crc, black_box(&buffer));
+    }
+    black_box(crc);
+    // A zero reading on a coarse clock would make the rates infinite.
+    let elapsed_ns = (started.elapsed().as_nanos() as u64).max(1);
+
+    Ok(CrcBenchmark {
+        variant,
+        bytes_processed: total,
+        elapsed_ns,
+        mb_per_s: total as f64 / elapsed_ns as f64 * 1e3,
+        ns_per_byte: elapsed_ns as f64 / total as f64,
+    })
+}
+
+/// CRC8 DVB-S2 with zero init: the VScope default trailer.
+#[cfg(test)]
+pub fn crc8(data: &[u8]) -> u8 {
+    CrcVariant::Crc8DvbS2.update(0, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHECK_INPUT: &[u8] = b"123456789";
+
+    #[test]
+    fn variants_match_reference_check_values() {
+        assert_eq!(CrcVariant::Crc8DvbS2.update(0, CHECK_INPUT), 0xBC);
+        assert_eq!(CrcVariant::Crc8Smbus.update(0, CHECK_INPUT), 0xF4);
+        assert_eq!(CrcVariant::Crc8Maxim.update(0, CHECK_INPUT), 0xA1);
+        assert_eq!(CrcVariant::Xor8.update(0, &[0x0F, 0xF0, 0x01]), 0xFE);
+        assert_eq!(CrcVariant::Sum8.update(0, &[0xFF, 0x02]), 0x01);
+    }
+
+    #[test]
+    fn coverage_includes_header_bytes() {
+        let body = [0x01, 0x02];
+        let payload_only = Checksum {
+            variant: CrcVariant::Xor8,
+            coverage: CrcCoverage::Payload,
+            init: 0,
+        };
+        let with_len = Checksum {
+            coverage: CrcCoverage::LenAndPayload,
+            ..payload_only
+        };
+        let whole = Checksum {
+            coverage: CrcCoverage::Frame,
+            ..payload_only
+        };
+        assert_eq!(payload_only.compute(0xC8, 0x03, &body), 0x03);
+        assert_eq!(with_len.compute(0xC8, 0x03, &body), 0x00);
+        assert_eq!(whole.compute(0xC8, 0x03, &body), 0xC8);
+    }
+
+    #[test]
+    fn crc8_empty() {
+        assert_eq!(crc8(&[]), 0x00);
+    }
+
+    #[test]
+    fn crc8_single_byte() {
+        assert_eq!(crc8(&[0x00]), 0x00);
+        assert_eq!(crc8(&[0x01]), 0xD5);
+        assert_eq!(crc8(&[0xFF]), 0xF9);
+    }
+
+    #[test]
+    fn crc8_multi_byte() {
+        // Verify deterministic output for multi-byte input
+        assert_eq!(crc8(&[0x01, 0x02, 0x03]), 0x3F);
+        // Different input produces different CRC
+        assert_ne!(crc8(&[0x01, 0x02, 0x03]), crc8(&[0x03, 0x02, 0x01]));
+    }
+
+    #[test]
+    fn benchmark_reports_nonzero_throughput() {
+        let report = benchmark_crc("crc8-dvb-s2".to_string(), 4096, 64).unwrap();
+        assert_eq!(report.variant, CrcVariant::Crc8DvbS2);
+        assert_eq!(report.bytes_processed, 4096 * 64);
+        assert!(report.mb_per_s > 0.0 && report.mb_per_s.is_finite());
+        assert!(report.ns_per_byte > 0.0);
+        assert!(matches!(
+            benchmark_crc("crc16".to_string(), 16, 1),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+        assert!(benchmark_crc("xor8".to_string(), 0, 1).is_err());
+    }
+
+    #[test]
+    fn names_parse_back() {
+        for variant in ALL_VARIANTS {
+            assert_eq!(CrcVariant::parse(variant.name()), Some(variant));
+        }
+        for coverage in ALL_COVERAGES {
+            assert_eq!(CrcCoverage::parse(coverage.name()), Some(coverage));
+        }
+        assert_eq!(CrcVariant::parse("crc16"), None);
+    }
+}
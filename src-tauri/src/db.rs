@@ -1,26 +1,146 @@
-use refinery::embed_migrations;
+use crate::events::{self, EventSink};
+use refinery::{embed_migrations, Target};
 use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tauri::Manager;
 
 const DB_FILENAME: &str = "vscope.db";
 const STARTUP_NOTICE_FILENAME: &str = "startup_notice.txt";
+/// Pending notices kept when the frontend has not consumed earlier ones.
+const MAX_PENDING_NOTICES: usize = 5;
+const NOTICE_LOG_FILENAME: &str = "notice_log.json";
+/// Entries kept in the notice log; older ones are dropped as new ones land.
+const MAX_NOTICE_LOG_ENTRIES: usize = 50;
+
+pub const MIGRATION_PROGRESS_EVENT: &str = "app://migration-progress";
+
+/// How long a Rust-side connection waits on another connection's lock (the
+/// SQL plugin's pool included) before failing with "database is locked".
+pub const DEFAULT_BUSY_TIMEOUT_MS: u64 = 5_000;
+
+static BUSY_TIMEOUT_MS: AtomicU64 = AtomicU64::new(DEFAULT_BUSY_TIMEOUT_MS);
+
+/// How long startup waits for migrations before giving up on them; override
+/// with the `VSCOPE_MIGRATION_TIMEOUT_MS` environment variable.
+pub const DEFAULT_MIGRATION_TIMEOUT_MS: u64 = 30_000;
+const MIGRATION_TIMEOUT_ENV: &str = "VSCOPE_MIGRATION_TIMEOUT_MS";
 
 embed_migrations!();
 
+/// Runs the startup migrations on a worker thread so a migration stuck on a
+/// lock (say, one left by a crashed instance) cannot stall the setup hook.
+/// Past the timeout, startup carries on and queues a notice; the worker keeps
+/// going and may still finish later, but no longer resets the database if
+/// its migration fails (see `migrate_or_recreate`).
 pub fn run_migrations(app: &tauri::AppHandle) -> Result<(), Box<dyn Error>> {
+    let timeout = match migration_timeout(std::env::var(MIGRATION_TIMEOUT_ENV).ok()) {
+        Ok(timeout) => timeout,
+        Err(warning) => {
+            let _ = write_startup_notice(app, warning);
+            Duration::from_millis(DEFAULT_MIGRATION_TIMEOUT_MS)
+        }
+    };
+    let worker_app = app.clone();
+    let outcome = migrate_within(timeout, move |abandoned| {
+        migrate_or_recreate(&worker_app, abandoned).map_err(|err| err.to_string())
+    })?;
+    if outcome == MigrationOutcome::TimedOut {
+        write_startup_notice(
+            app,
+            format!(
+                "Database migrations were still running after {} s, so startup went ahead without them. Saved data may be unavailable or out of date until they finish.",
+                timeout.as_secs_f64()
+            ),
+        )?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum MigrationOutcome {
+    Done,
+    /// The worker was still running at the deadline and has been left to it.
+    TimedOut,
+}
+
+/// Parses the `VSCOPE_MIGRATION_TIMEOUT_MS` override; an unusable value is
+/// reported as notice text, for the caller to fall back to the default.
+fn migration_timeout(value: Option<String>) -> Result<Duration, String> {
+    let Some(value) = value else {
+        return Ok(Duration::from_millis(DEFAULT_MIGRATION_TIMEOUT_MS));
+    };
+    value.trim().parse().map(Duration::from_millis).map_err(|_| {
+        format!(
+            "Ignored {MIGRATION_TIMEOUT_ENV}={value:?}; expected milliseconds. Using {DEFAULT_MIGRATION_TIMEOUT_MS} ms."
+        )
+    })
+}
+
+/// Runs `migrate` on its own thread and waits at most `timeout` for it. A
+/// migration error is returned as is; a panic is reported as an error.
+///
+/// `migrate` gets the "abandoned" flag, set once setup stops waiting. Setup
+/// takes the lock to set it, so a worker holding the lock across a
+/// destructive step either finishes that step first or sees the flag.
+fn migrate_within(
+    timeout: Duration,
+    migrate: impl FnOnce(&Mutex<bool>) -> Result<(), String> + Send + 'static,
+) -> Result<MigrationOutcome, Box<dyn Error>> {
+    let abandoned = Arc::new(Mutex::new(false));
+    let worker_abandoned = Arc::clone(&abandoned);
+    let (done_tx, done_rx) = mpsc::channel();
+    thread::Builder::new()
+        .name("db-migrations".to_string())
+        .spawn(move || {
+            let _ = done_tx.send(migrate(&worker_abandoned));
+        })?;
+    let result = match done_rx.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => {
+            *abandoned.lock().unwrap_or_else(PoisonError::into_inner) = true;
+            return Ok(MigrationOutcome::TimedOut);
+        }
+        Err(RecvTimeoutError::Disconnected) => return Err("migration thread panicked".into()),
+    };
+    result.map(|()| MigrationOutcome::Done).map_err(Into::into)
+}
+
+/// Migrates the app database, recreating it when the existing one will not
+/// load. Once `abandoned` is set the app and the SQL plugin pool may already
+/// be using the file, so a failure then leaves it in place instead.
+fn migrate_or_recreate(
+    app: &tauri::AppHandle,
+    abandoned: &Mutex<bool>,
+) -> Result<(), Box<dyn Error>> {
     let db_path = resolve_db_path(app)?;
     if let Some(parent) = db_path.parent() {
         fs::create_dir_all(parent)?;
     }
 
-    match run_migrations_once(&db_path) {
+    let progress = events::app_sink(app.clone());
+    match run_migrations_at(&db_path, Some(&progress)) {
         Ok(()) => Ok(()),
         Err(first_error) => {
-            reset_db_files(&db_path)?;
-            run_migrations_once(&db_path)?;
+            // Held across the reset so setup cannot move on halfway through.
+            let abandoned = abandoned.lock().unwrap_or_else(PoisonError::into_inner);
+            if *abandoned {
+                let _ = write_startup_notice(
+                    app,
+                    format!(
+                        "Database migrations failed after startup had gone ahead without them. The database was left as it was. ({first_error})"
+                    ),
+                );
+                return Err(first_error);
+            }
+            recreate_db(&db_path, Some(&progress))?;
             let _ = write_startup_notice(
                 app,
                 format!(
@@ -32,21 +152,147 @@ pub fn run_migrations(app: &tauri::AppHandle) -> Result<(), Box<dyn Error>> {
     }
 }
 
+/// Deliberate "factory reset": deletes the database and migrates a fresh one.
+///
+/// The SQL plugin pool held by the frontend must be closed first (see
+/// `resetDatabase` in `src/lib/db`), otherwise Windows refuses to delete the
+/// open files. Rust-side connections are per-call and hold nothing open.
+#[tauri::command]
+pub fn reset_database(app: tauri::AppHandle) -> Result<(), String> {
+    let db_path = resolve_db_path(&app).map_err(|err| err.to_string())?;
+    let progress = events::app_sink(app.clone());
+    recreate_db(&db_path, Some(&progress)).map_err(|err| err.to_string())?;
+    write_startup_notice(
+        &app,
+        "Database was reset on request. Local snapshots were removed.".to_string(),
+    )
+    .map_err(|err| err.to_string())
+}
+
+/// Folds the WAL into the main file and truncates it, so a copy of the main
+/// file alone is a complete backup. The SQL plugin pool can stay open, but a
+/// read transaction still running on it keeps the checkpoint from finishing
+/// and this fails instead of leaving a partial one. Copy straight after:
+/// later writes start a new WAL.
+#[tauri::command]
+pub fn prepare_for_backup(app: tauri::AppHandle) -> Result<(), String> {
+    let db_path = resolve_db_path(&app).map_err(|err| err.to_string())?;
+    checkpoint_truncate(&db_path).map_err(|err| err.to_string())
+}
+
+fn checkpoint_truncate(db_path: &Path) -> Result<(), Box<dyn Error>> {
+    let conn = Connection::open(db_path)?;
+    apply_busy_timeout(&conn)?;
+    let busy: i64 = conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))?;
+    if busy != 0 {
+        return Err("database is busy; WAL checkpoint did not complete".into());
+    }
+    Ok(())
+}
+
+fn recreate_db(db_path: &Path, progress: Option<&EventSink>) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = db_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    reset_db_files(db_path)?;
+    run_migrations_at(db_path, progress)
+}
+
 fn resolve_db_path(app: &tauri::AppHandle) -> Result<PathBuf, Box<dyn Error>> {
     let app_dir = app.path().app_data_dir()?;
     Ok(app_dir.join(DB_FILENAME))
 }
 
+pub(crate) fn open_connection(app: &tauri::AppHandle) -> Result<Connection, Box<dyn Error>> {
+    let conn = Connection::open(resolve_db_path(app)?)?;
+    apply_busy_timeout(&conn)?;
+    conn.pragma_update(None, "foreign_keys", "ON")?;
+    Ok(conn)
+}
+
+fn apply_busy_timeout(conn: &Connection) -> rusqlite::Result<()> {
+    conn.busy_timeout(Duration::from_millis(
+        BUSY_TIMEOUT_MS.load(Ordering::Relaxed),
+    ))
+}
+
+#[tauri::command]
+pub fn db_busy_timeout() -> u64 {
+    BUSY_TIMEOUT_MS.load(Ordering::Relaxed)
+}
+
+/// Sets the busy timeout for connections opened from now on; connections
+/// already open keep theirs. 0 fails on the first lock conflict.
+#[tauri::command]
+pub fn set_db_busy_timeout(ms: u64) {
+    BUSY_TIMEOUT_MS.store(ms, Ordering::Relaxed);
+}
+
 fn resolve_startup_notice_path(app: &tauri::AppHandle) -> Result<PathBuf, Box<dyn Error>> {
     let app_dir = app.path().app_data_dir()?;
     Ok(app_dir.join(STARTUP_NOTICE_FILENAME))
 }
 
-fn run_migrations_once(db_path: &Path) -> Result<(), Box<dyn Error>> {
+fn run_migrations_at(db_path: &Path, progress: Option<&EventSink>) -> Result<(), Box<dyn Error>> {
     let mut conn = Connection::open(db_path)?;
+    apply_busy_timeout(&conn)?;
     conn.pragma_update(None, "journal_mode", "WAL")?;
+    run_migrations_once(&mut conn, progress)
+}
+
+/// Emitted after each migration is applied, so a splash screen can show
+/// that a long upgrade is still making progress.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MigrationProgress {
+    version: u32,
+    name: String,
+    /// Since the migration run started.
+    elapsed_ms: u64,
+    /// Embedded migrations in total, applied or not.
+    total: usize,
+}
+
+/// Migrates an already-open connection; callers apply any other connection
+/// setup (journal mode) first. With `progress`, the runner is stepped one
+/// target version at a time so an event can go out between migrations;
+/// refinery otherwise applies the whole batch without reporting.
+pub(crate) fn run_migrations_once(
+    conn: &mut Connection,
+    progress: Option<&EventSink>,
+) -> Result<(), Box<dyn Error>> {
+    apply_busy_timeout(conn)?;
     conn.pragma_update(None, "foreign_keys", "ON")?;
-    migrations::runner().run(&mut conn)?;
+    let Some(sink) = progress else {
+        migrations::runner().run(conn)?;
+        return Ok(());
+    };
+
+    let started = Instant::now();
+    let mut versions: Vec<u32> = migrations::runner()
+        .get_migrations()
+        .iter()
+        .map(|migration| migration.version())
+        .collect();
+    versions.sort_unstable();
+    let total = versions.len();
+    for version in versions {
+        let report = migrations::runner()
+            .set_target(Target::Version(version))
+            .run(conn)?;
+        for migration in report.applied_migrations() {
+            events::emit(
+                sink,
+                MIGRATION_PROGRESS_EVENT,
+                &MigrationProgress {
+                    version: migration.version(),
+                    name: migration.name().to_string(),
+                    elapsed_ms: started.elapsed().as_millis() as u64,
+                    total,
+                },
+            );
+        }
+    }
     Ok(())
 }
 
@@ -62,23 +308,434 @@ fn reset_db_files(db_path: &Path) -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// Queues `notice` for the frontend and records it in the notice log. The
+/// log is best-effort: failing to write it does not fail the notice.
 fn write_startup_notice(app: &tauri::AppHandle, notice: String) -> Result<(), Box<dyn Error>> {
     let path = resolve_startup_notice_path(app)?;
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?;
     }
-    fs::write(path, notice)?;
+    if let Ok(log_path) = resolve_notice_log_path(app) {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+        let _ = append_notice_log(&log_path, &notice, timestamp_ms);
+    }
+    append_notice(&path, notice)
+}
+
+fn resolve_notice_log_path(app: &tauri::AppHandle) -> Result<PathBuf, Box<dyn Error>> {
+    let app_dir = app.path().app_data_dir()?;
+    Ok(app_dir.join(NOTICE_LOG_FILENAME))
+}
+
+/// One entry of the notice log, which unlike the pending notices survives
+/// being read and spans launches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimedNotice {
+    /// Milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    pub notice: String,
+}
+
+/// Reads the notice log, oldest first. A log that does not parse is an
+/// error, so appending never replaces it with a fresh one.
+fn read_notice_log_at(path: &Path) -> Result<Vec<TimedNotice>, Box<dyn Error>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path)?;
+    if content.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(serde_json::from_str(&content)?)
+}
+
+fn append_notice_log(path: &Path, notice: &str, timestamp_ms: u64) -> Result<(), Box<dyn Error>> {
+    let mut entries = read_notice_log_at(path)?;
+    entries.push(TimedNotice {
+        timestamp_ms,
+        notice: notice.to_string(),
+    });
+    let excess = entries.len().saturating_sub(MAX_NOTICE_LOG_ENTRIES);
+    entries.drain(..excess);
+    fs::write(path, serde_json::to_string(&entries)?)?;
     Ok(())
 }
 
+/// Every notice from the last `MAX_NOTICE_LOG_ENTRIES`, across launches, for
+/// diagnosing repeated resets. Reading does not consume them.
 #[tauri::command]
-pub fn take_startup_notice(app: tauri::AppHandle) -> Result<Option<String>, String> {
-    let path = resolve_startup_notice_path(&app).map_err(|err| err.to_string())?;
+pub fn read_notice_log(app: tauri::AppHandle) -> Result<Vec<TimedNotice>, String> {
+    let path = resolve_notice_log_path(&app).map_err(|err| err.to_string())?;
+    read_notice_log_at(&path).map_err(|err| err.to_string())
+}
+
+/// Reads pending notices. The file holds a JSON array of strings; anything
+/// that does not parse as one is a legacy single plain-text notice.
+fn read_notices(path: &Path) -> Result<Vec<String>, Box<dyn Error>> {
     if !path.exists() {
-        return Ok(None);
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path)?;
+    if let Ok(notices) = serde_json::from_str::<Vec<String>>(&content) {
+        return Ok(notices);
+    }
+    if content.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(vec![content])
+}
+
+fn append_notice(path: &Path, notice: String) -> Result<(), Box<dyn Error>> {
+    let mut notices = read_notices(path)?;
+    notices.push(notice);
+    let excess = notices.len().saturating_sub(MAX_PENDING_NOTICES);
+    notices.drain(..excess);
+    fs::write(path, serde_json::to_string(&notices)?)?;
+    Ok(())
+}
+
+fn take_notices(path: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    let notices = read_notices(path)?;
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(notices)
+}
+
+/// A migration compiled into this binary.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationDesc {
+    pub version: u32,
+    pub name: String,
+    /// refinery's checksum as 16 hex digits (a u64 does not survive JSON).
+    pub checksum: String,
+}
+
+/// Describes the embedded migrations without touching any database.
+#[tauri::command]
+pub fn list_embedded_migrations() -> Vec<MigrationDesc> {
+    let mut descs: Vec<MigrationDesc> = migrations::runner()
+        .get_migrations()
+        .iter()
+        .map(|migration| MigrationDesc {
+            version: migration.version(),
+            name: migration.name().to_string(),
+            checksum: format!("{:016x}", migration.checksum()),
+        })
+        .collect();
+    descs.sort_by_key(|desc| desc.version);
+    descs
+}
+
+#[tauri::command]
+pub fn take_startup_notice(app: tauri::AppHandle) -> Result<Vec<String>, String> {
+    let path = resolve_startup_notice_path(&app).map_err(|err| err.to_string())?;
+    take_notices(&path).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::testing;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("vscope-db-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn notices_accumulate_until_taken() {
+        let path = temp_path("accumulate.json");
+        append_notice(&path, "first reset".to_string()).unwrap();
+        append_notice(&path, "second reset".to_string()).unwrap();
+
+        assert_eq!(
+            take_notices(&path).unwrap(),
+            vec!["first reset".to_string(), "second reset".to_string()]
+        );
+        assert!(!path.exists());
+        assert!(take_notices(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn notices_are_bounded_to_most_recent() {
+        let path = temp_path("bounded.json");
+        for i in 0..MAX_PENDING_NOTICES + 2 {
+            append_notice(&path, format!("notice {i}")).unwrap();
+        }
+        let notices = take_notices(&path).unwrap();
+        assert_eq!(notices.len(), MAX_PENDING_NOTICES);
+        assert_eq!(notices[0], "notice 2");
+    }
+
+    #[test]
+    fn notice_log_appends_and_reads_back() {
+        let path = temp_path("log.json");
+        assert!(read_notice_log_at(&path).unwrap().is_empty());
+        append_notice_log(&path, "reset", 1_000).unwrap();
+        append_notice_log(&path, "reset again", 2_000).unwrap();
+
+        let expected = vec![
+            TimedNotice {
+                timestamp_ms: 1_000,
+                notice: "reset".to_string(),
+            },
+            TimedNotice {
+                timestamp_ms: 2_000,
+                notice: "reset again".to_string(),
+            },
+        ];
+        assert_eq!(read_notice_log_at(&path).unwrap(), expected);
+        // Reading is not consuming, unlike the pending notices.
+        assert_eq!(read_notice_log_at(&path).unwrap(), expected);
+    }
+
+    #[test]
+    fn notice_log_rotates_at_cap() {
+        let path = temp_path("log_cap.json");
+        for i in 0..MAX_NOTICE_LOG_ENTRIES + 3 {
+            append_notice_log(&path, &format!("notice {i}"), i as u64).unwrap();
+        }
+        let entries = read_notice_log_at(&path).unwrap();
+        assert_eq!(entries.len(), MAX_NOTICE_LOG_ENTRIES);
+        assert_eq!(entries[0].notice, "notice 3");
+        assert_eq!(
+            entries.last().unwrap().notice,
+            format!("notice {}", MAX_NOTICE_LOG_ENTRIES + 2)
+        );
+    }
+
+    #[test]
+    fn corrupt_notice_log_is_left_alone() {
+        let path = temp_path("log_corrupt.json");
+        fs::write(&path, "[{\"timestampMs\": 1, \"noti").unwrap();
+        assert!(read_notice_log_at(&path).is_err());
+        assert!(append_notice_log(&path, "reset", 2_000).is_err());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[{\"timestampMs\": 1, \"noti"
+        );
     }
 
-    let content = fs::read_to_string(&path).map_err(|err| err.to_string())?;
-    fs::remove_file(&path).map_err(|err| err.to_string())?;
-    Ok(Some(content))
+    #[test]
+    fn legacy_plain_text_notice_is_preserved() {
+        let path = temp_path("legacy.txt");
+        fs::write(&path, "Database was reset.").unwrap();
+        append_notice(&path, "newer".to_string()).unwrap();
+        assert_eq!(
+            take_notices(&path).unwrap(),
+            vec!["Database was reset.".to_string(), "newer".to_string()]
+        );
+    }
+
+    #[test]
+    fn recreate_db_leaves_empty_current_schema() {
+        let path = temp_path("recreate.db");
+        run_migrations_at(&path, None).unwrap();
+        let conn = Connection::open(&path).unwrap();
+        conn.execute(
+            "INSERT INTO snapshot_meta (name, device_names_json, channel_count, sample_count, divider, pre_trig, channel_map_json, trigger_threshold, trigger_channel, trigger_mode, rt_values_json, created_at) VALUES ('old', '[]', 1, 1, 1, 0, '[0]', 0.0, 0, 0, '[]', '2026-01-01T00:00:00Z')",
+            [],
+        )
+        .unwrap();
+        drop(conn);
+
+        recreate_db(&path, None).unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        let rows: i64 = conn
+            .query_row("SELECT COUNT(*) FROM snapshot_meta", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(rows, 0);
+        let applied: usize = conn
+            .query_row("SELECT COUNT(*) FROM refinery_schema_history", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(applied, migrations::runner().get_migrations().len());
+    }
+
+    #[test]
+    fn checkpoint_then_copy_gives_a_complete_backup() {
+        let path = temp_path("live.db");
+        let backup = temp_path("backup.db");
+        let wal = PathBuf::from(format!("{}-wal", path.display()));
+        run_migrations_at(&path, None).unwrap();
+        // Stands in for the frontend pool, which stays open across the backup.
+        let app_conn = Connection::open(&path).unwrap();
+        for serial in ["A1", "B2"] {
+            app_conn
+                .execute(
+                    "INSERT INTO device_nicknames (serial_number, nickname) VALUES (?1, 'x')",
+                    [serial],
+                )
+                .unwrap();
+        }
+        assert!(fs::metadata(&wal).unwrap().len() > 0);
+
+        checkpoint_truncate(&path).unwrap();
+        assert_eq!(fs::metadata(&wal).unwrap().len(), 0);
+        fs::copy(&path, &backup).unwrap();
+
+        let conn = Connection::open(&backup).unwrap();
+        let integrity: String = conn
+            .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(integrity, "ok");
+        let rows: i64 = conn
+            .query_row("SELECT COUNT(*) FROM device_nicknames", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(rows, 2);
+        drop(app_conn);
+    }
+
+    #[test]
+    fn busy_timeout_waits_out_a_short_lock() {
+        let path = temp_path("busy.db");
+        run_migrations_at(&path, None).unwrap();
+        let insert = "INSERT INTO device_nicknames (serial_number, nickname) VALUES (?1, 'x')";
+
+        let hold_lock = |ms: u64| {
+            let holder = Connection::open(&path).unwrap();
+            holder.execute_batch("BEGIN IMMEDIATE").unwrap();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(ms));
+                holder.execute_batch("COMMIT").unwrap();
+            })
+        };
+
+        set_db_busy_timeout(2_000);
+        let released = hold_lock(100);
+        std::thread::sleep(Duration::from_millis(10));
+        let conn = Connection::open(&path).unwrap();
+        apply_busy_timeout(&conn).unwrap();
+        let started = Instant::now();
+        conn.execute(insert, ["waited"]).unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        released.join().unwrap();
+
+        set_db_busy_timeout(0);
+        let released = hold_lock(100);
+        std::thread::sleep(Duration::from_millis(10));
+        let conn = Connection::open(&path).unwrap();
+        apply_busy_timeout(&conn).unwrap();
+        assert!(conn.execute(insert, ["failed"]).is_err());
+        released.join().unwrap();
+
+        set_db_busy_timeout(DEFAULT_BUSY_TIMEOUT_MS);
+        assert_eq!(db_busy_timeout(), DEFAULT_BUSY_TIMEOUT_MS);
+    }
+
+    #[test]
+    fn migrations_run_on_in_memory_connection() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations_once(&mut conn, None).unwrap();
+        let version: u32 = conn
+            .query_row(
+                "SELECT MAX(version) FROM refinery_schema_history",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        let latest = migrations::runner()
+            .get_migrations()
+            .iter()
+            .map(|migration| migration.version())
+            .max()
+            .unwrap();
+        assert_eq!(version, latest);
+        // Re-running against an up-to-date schema is a no-op.
+        run_migrations_once(&mut conn, None).unwrap();
+    }
+
+    #[test]
+    fn progress_event_fires_per_applied_migration() {
+        let (sink, captured) = testing::capture();
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations_once(&mut conn, Some(&sink)).unwrap();
+
+        let events = testing::wait_for(&captured, MIGRATION_PROGRESS_EVENT, 0, Duration::ZERO);
+        let total = migrations::runner().get_migrations().len();
+        assert!(total > 1);
+        assert_eq!(events.len(), total);
+        let versions: Vec<u64> = events
+            .iter()
+            .map(|event| event["version"].as_u64().unwrap())
+            .collect();
+        assert_eq!(versions, (1..=total as u64).collect::<Vec<_>>());
+        assert_eq!(events[0]["name"], "init");
+        assert!(events.iter().all(|event| event["total"] == total));
+
+        // An up-to-date schema applies nothing and reports nothing.
+        captured.lock().unwrap().clear();
+        run_migrations_once(&mut conn, Some(&sink)).unwrap();
+        assert!(captured.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn blocked_migration_times_out_instead_of_hanging() {
+        let started = Instant::now();
+        let outcome = migrate_within(Duration::from_millis(50), |_| {
+            thread::sleep(Duration::from_secs(10));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(outcome, MigrationOutcome::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        assert_eq!(
+            migrate_within(Duration::from_secs(5), |_| Ok(())).unwrap(),
+            MigrationOutcome::Done
+        );
+        let err =
+            migrate_within(Duration::from_secs(5), |_| Err("bad schema".to_string())).unwrap_err();
+        assert_eq!(err.to_string(), "bad schema");
+    }
+
+    #[test]
+    fn migration_timeout_override_is_parsed_or_reported() {
+        assert_eq!(
+            migration_timeout(None).unwrap(),
+            Duration::from_millis(DEFAULT_MIGRATION_TIMEOUT_MS)
+        );
+        assert_eq!(
+            migration_timeout(Some(" 1500 ".to_string())).unwrap(),
+            Duration::from_millis(1_500)
+        );
+        let warning = migration_timeout(Some("30s".to_string())).unwrap_err();
+        assert!(warning.contains(MIGRATION_TIMEOUT_ENV), "{warning}");
+    }
+
+    #[test]
+    fn timed_out_worker_sees_it_was_abandoned() {
+        let (seen_tx, seen_rx) = mpsc::channel();
+        let outcome = migrate_within(Duration::from_millis(20), move |abandoned| {
+            assert!(!*abandoned.lock().unwrap());
+            thread::sleep(Duration::from_millis(200));
+            let _ = seen_tx.send(*abandoned.lock().unwrap());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(outcome, MigrationOutcome::TimedOut);
+        assert!(seen_rx.recv_timeout(Duration::from_secs(5)).unwrap());
+    }
+
+    #[test]
+    fn embedded_migrations_are_listed_in_order() {
+        let descs = list_embedded_migrations();
+        assert_eq!(descs.len(), 3);
+        assert_eq!((descs[0].version, descs[0].name.as_str()), (1, "init"));
+        let last = descs.last().unwrap();
+        assert_eq!((last.version, last.name.as_str()), (3, "port_history"));
+        assert!(descs.iter().all(|desc| desc.checksum.len() == 16));
+    }
 }
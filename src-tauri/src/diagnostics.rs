@@ -0,0 +1,236 @@
+//! Per-handle counters and recent history, bundled into one report for the
+//! "copy diagnostics" button.
+
+use crate::error::SerialError;
+use crate::frame::ProtocolConfig;
+use crate::serial::{with_entry, RetryConfig};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// Errors and frames kept for the report; older ones are dropped.
+const HISTORY_LEN: usize = 16;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkCounters {
+    pub requests: u64,
+    pub responses: u64,
+    pub retries: u64,
+    pub timeouts: u64,
+    pub crc_errors: u64,
+    pub payload_bytes_sent: u64,
+    pub payload_bytes_received: u64,
+    /// Framed requests made while the stream reader ran, holding it off for
+    /// the exchange.
+    pub stream_pauses: u64,
+    /// Times the port was swapped by `reopen_handle`; the counters above
+    /// carry on across it.
+    pub reopens: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Direction {
+    Tx,
+    Rx,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturedFrame {
+    pub direction: Direction,
+    /// TYPE + PAYLOAD, without framing.
+    pub payload: Vec<u8>,
+}
+
+/// Request history for one handle, updated by `exchange`.
+pub(crate) struct HandleStats {
+    opened_at: Instant,
+    counters: LinkCounters,
+    recent_errors: VecDeque<String>,
+    recent_frames: VecDeque<CapturedFrame>,
+}
+
+fn push_bounded<T>(queue: &mut VecDeque<T>, item: T) {
+    if queue.len() == HISTORY_LEN {
+        queue.pop_front();
+    }
+    queue.push_back(item);
+}
+
+impl HandleStats {
+    pub(crate) fn new() -> Self {
+        Self {
+            opened_at: Instant::now(),
+            counters: LinkCounters::default(),
+            recent_errors: VecDeque::new(),
+            recent_frames: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn record_request(&mut self, payload: &[u8]) {
+        self.counters.requests += 1;
+        self.counters.payload_bytes_sent += payload.len() as u64;
+        self.capture(Direction::Tx, payload);
+    }
+
+    pub(crate) fn record_stream_pause(&mut self) {
+        self.counters.stream_pauses += 1;
+    }
+
+    pub(crate) fn record_reopen(&mut self) {
+        self.counters.reopens += 1;
+    }
+
+    pub(crate) fn record_retry(&mut self) {
+        self.counters.retries += 1;
+    }
+
+    pub(crate) fn record_response(&mut self, payload: &[u8]) {
+        self.counters.responses += 1;
+        self.counters.payload_bytes_received += payload.len() as u64;
+        self.capture(Direction::Rx, payload);
+    }
+
+    /// Counts a failed attempt; every attempt is recorded, not only the last.
+    pub(crate) fn record_error(&mut self, err: &SerialError) {
+        match err {
+            SerialError::Timeout => self.counters.timeouts += 1,
+            SerialError::CrcMismatch => self.counters.crc_errors += 1,
+            _ => {}
+        }
+        push_bounded(&mut self.recent_errors, err.to_string());
+    }
+
+    fn capture(&mut self, direction: Direction, payload: &[u8]) {
+        push_bounded(
+            &mut self.recent_frames,
+            CapturedFrame {
+                direction,
+                payload: payload.to_vec(),
+            },
+        );
+    }
+
+    /// Share of attempts that produced a valid response; 1.0 before any.
+    fn link_quality(&self) -> f64 {
+        let LinkCounters {
+            responses,
+            timeouts,
+            crc_errors,
+            ..
+        } = self.counters;
+        let attempts = responses + timeouts + crc_errors;
+        if attempts == 0 {
+            1.0
+        } else {
+            responses as f64 / attempts as f64
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsReport {
+    pub handle_id: u64,
+    pub path: String,
+    /// Device node behind `path` when the handle resolves symlinks.
+    pub resolved_path: Option<String>,
+    pub uptime_ms: u64,
+    pub protocol: ProtocolConfig,
+    pub retry: RetryConfig,
+    pub counters: LinkCounters,
+    pub link_quality: f64,
+    /// Oldest first.
+    pub recent_errors: Vec<String>,
+    /// Oldest first.
+    pub recent_frames: Vec<CapturedFrame>,
+    pub pending_unsolicited: usize,
+    pub streaming: bool,
+}
+
+#[tauri::command]
+pub fn diagnostics_report(handle_id: u64) -> Result<DiagnosticsReport, SerialError> {
+    with_entry(handle_id, "diagnostics_report", |entry| {
+        let stats = &entry.stats;
+        Ok(DiagnosticsReport {
+            handle_id,
+            path: entry.path.clone(),
+            resolved_path: entry.resolved_path.clone(),
+            uptime_ms: stats.opened_at.elapsed().as_millis() as u64,
+            protocol: entry.protocol.clone(),
+            retry: entry.retry.clone(),
+            counters: stats.counters.clone(),
+            link_quality: stats.link_quality(),
+            recent_errors: stats.recent_errors.iter().cloned().collect(),
+            recent_frames: stats.recent_frames.iter().cloned().collect(),
+            pending_unsolicited: entry.unsolicited.len(),
+            streaming: entry.stream.is_some(),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::build_frame;
+    use crate::mock_port::MockPort;
+    use crate::serial::{insert_mock, send_request};
+
+    #[test]
+    fn report_reflects_recent_exchanges() {
+        let port = MockPort::new();
+        port.queue_reply(&build_frame(&[0x01, 0x2A]).unwrap());
+        let handle_id = insert_mock(&port);
+
+        send_request(handle_id, vec![0x01]).unwrap();
+        // No reply queued: the second request times out.
+        assert!(matches!(
+            send_request(handle_id, vec![0x02]),
+            Err(SerialError::Timeout)
+        ));
+
+        let report = diagnostics_report(handle_id).unwrap();
+        assert_eq!(report.path, "mock");
+        assert_eq!(
+            report.counters,
+            LinkCounters {
+                requests: 2,
+                responses: 1,
+                timeouts: 1,
+                payload_bytes_sent: 2,
+                payload_bytes_received: 2,
+                ..LinkCounters::default()
+            }
+        );
+        assert_eq!(report.link_quality, 0.5);
+        assert_eq!(report.recent_errors, vec!["timeout".to_string()]);
+        assert_eq!(
+            report.recent_frames,
+            vec![
+                CapturedFrame {
+                    direction: Direction::Tx,
+                    payload: vec![0x01],
+                },
+                CapturedFrame {
+                    direction: Direction::Rx,
+                    payload: vec![0x01, 0x2A],
+                },
+                CapturedFrame {
+                    direction: Direction::Tx,
+                    payload: vec![0x02],
+                },
+            ]
+        );
+        assert!(!report.streaming);
+    }
+
+    #[test]
+    fn report_rejects_unknown_handle() {
+        assert!(matches!(
+            diagnostics_report(u64::MAX),
+            Err(SerialError::InvalidHandle { .. })
+        ));
+    }
+}
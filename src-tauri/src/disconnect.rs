@@ -0,0 +1,79 @@
+//! Which port I/O failures mean the device is gone. Adapters disagree on how
+//! they report an unplug, so the set is configurable at runtime.
+
+use crate::error::SerialError;
+use serde::Serialize;
+use std::io::ErrorKind;
+use std::sync::{OnceLock, PoisonError, RwLock};
+
+pub const DISCONNECTED_EVENT: &str = "serial://disconnected";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DisconnectKind {
+    Io(ErrorKind),
+    /// `serialport`'s own no-device error.
+    NoDevice,
+}
+
+const DEFAULT_KINDS: [DisconnectKind; 3] = [
+    DisconnectKind::Io(ErrorKind::NotConnected),
+    DisconnectKind::Io(ErrorKind::BrokenPipe),
+    DisconnectKind::NoDevice,
+];
+
+impl DisconnectKind {
+    fn parse(name: &str) -> Result<Self, SerialError> {
+        let kind = match name {
+            "NoDevice" => return Ok(DisconnectKind::NoDevice),
+            "NotConnected" => ErrorKind::NotConnected,
+            "BrokenPipe" => ErrorKind::BrokenPipe,
+            "ConnectionReset" => ErrorKind::ConnectionReset,
+            "ConnectionAborted" => ErrorKind::ConnectionAborted,
+            "UnexpectedEof" => ErrorKind::UnexpectedEof,
+            "NotFound" => ErrorKind::NotFound,
+            "PermissionDenied" => ErrorKind::PermissionDenied,
+            "Other" => ErrorKind::Other,
+            other => {
+                return Err(SerialError::InvalidConfig {
+                    message: format!("unknown disconnect error kind {other:?}"),
+                })
+            }
+        };
+        Ok(DisconnectKind::Io(kind))
+    }
+}
+
+fn configured() -> &'static RwLock<Vec<DisconnectKind>> {
+    static KINDS: OnceLock<RwLock<Vec<DisconnectKind>>> = OnceLock::new();
+    KINDS.get_or_init(|| RwLock::new(DEFAULT_KINDS.to_vec()))
+}
+
+/// Consulted by the `SerialError` conversions for every port I/O failure.
+pub(crate) fn is_disconnect(kind: DisconnectKind) -> bool {
+    configured()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .contains(&kind)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Disconnected<'a> {
+    pub(crate) handle_id: u64,
+    pub(crate) label: Option<String>,
+    pub(crate) message: &'a str,
+}
+
+/// Replaces the set of error kinds reported as `Disconnected` (and that end a
+/// stream with a `serial://disconnected` event). Names are `std::io`
+/// `ErrorKind` variants (`NotConnected`, `BrokenPipe`, `ConnectionReset`,
+/// ...) or `NoDevice`. The default is NotConnected, BrokenPipe and NoDevice.
+#[tauri::command]
+pub fn set_disconnect_error_kinds(kinds: Vec<String>) -> Result<(), SerialError> {
+    let parsed = kinds
+        .iter()
+        .map(|name| DisconnectKind::parse(name))
+        .collect::<Result<Vec<_>, _>>()?;
+    *configured().write().unwrap_or_else(PoisonError::into_inner) = parsed;
+    Ok(())
+}
@@ -0,0 +1,127 @@
+//! Free space on the volume holding the app data dir, so a large snapshot can
+//! be refused up front. A write that hits a full disk mid-transaction can
+//! leave the database failing its next startup load, which resets it.
+
+use serde::Serialize;
+use std::io;
+use std::path::Path;
+use tauri::Manager;
+use thiserror::Error;
+
+/// Headroom required on top of the snapshot itself, for the WAL and the
+/// index and overflow pages SQLite writes alongside the blob.
+pub const SNAPSHOT_SPACE_MARGIN_BYTES: u64 = 16 * 1024 * 1024;
+
+#[derive(Error, Debug, Serialize)]
+#[serde(tag = "type", content = "data")]
+pub enum SpaceError {
+    /// `needed` includes `SNAPSHOT_SPACE_MARGIN_BYTES`.
+    #[error("insufficient disk space: {needed} bytes needed, {available} bytes free")]
+    InsufficientSpace { needed: u64, available: u64 },
+
+    #[error("cannot check disk space: {message}")]
+    SpaceUnknown { message: String },
+}
+
+/// Bytes free to this user on the volume hosting the app data dir.
+#[tauri::command]
+pub fn disk_space_available(app: tauri::AppHandle) -> Result<u64, String> {
+    let dir = app.path().app_data_dir().map_err(|err| err.to_string())?;
+    // The data dir may not exist before the first launch has finished.
+    let existing = dir
+        .ancestors()
+        .find(|path| path.exists())
+        .ok_or_else(|| format!("no existing directory above {}", dir.display()))?;
+    available_bytes(existing).map_err(|err| err.to_string())
+}
+
+/// Fails with `InsufficientSpace` unless `byte_len` more bytes plus
+/// `SNAPSHOT_SPACE_MARGIN_BYTES` fit on the data volume, or `SpaceUnknown`
+/// if the free space cannot be read.
+#[tauri::command]
+pub fn check_snapshot_space(app: tauri::AppHandle, byte_len: u64) -> Result<(), SpaceError> {
+    let available =
+        disk_space_available(app).map_err(|message| SpaceError::SpaceUnknown { message })?;
+    check_space(byte_len, available)
+}
+
+fn check_space(byte_len: u64, available: u64) -> Result<(), SpaceError> {
+    match byte_len.checked_add(SNAPSHOT_SPACE_MARGIN_BYTES) {
+        Some(needed) if needed <= available => Ok(()),
+        needed => Err(SpaceError::InsufficientSpace {
+            needed: needed.unwrap_or(u64::MAX),
+            available,
+        }),
+    }
+}
+
+#[cfg(unix)]
+// Field widths differ by platform (`u32` blocks on macOS, `u64` on Linux).
+#[allow(clippy::unnecessary_cast)]
+fn available_bytes(path: &Path) -> io::Result<u64> {
+    use std::ffi::CString;
+    use std::mem::MaybeUninit;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let mut stats = MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `c_path` is NUL-terminated and `stats` is valid for writes.
+    if unsafe { libc::statvfs(c_path.as_ptr(), stats.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: statvfs filled `stats` in on success.
+    let stats = unsafe { stats.assume_init() };
+    Ok(stats.f_bavail as u64 * stats.f_frsize as u64)
+}
+
+#[cfg(windows)]
+fn available_bytes(path: &Path) -> io::Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0u64;
+    // SAFETY: `wide` is NUL-terminated; the total and free-total outputs are
+    // optional and may be null.
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(available)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn space_check_requires_payload_plus_margin() {
+        let payload = 4 * 1024 * 1024;
+        let exact = payload + SNAPSHOT_SPACE_MARGIN_BYTES;
+        assert!(check_space(payload, exact).is_ok());
+        assert!(check_space(payload, u64::MAX).is_ok());
+
+        assert!(matches!(
+            check_space(payload, exact - 1),
+            Err(SpaceError::InsufficientSpace { needed, available })
+                if needed == exact && available == exact - 1
+        ));
+        assert!(matches!(
+            check_space(u64::MAX, u64::MAX),
+            Err(SpaceError::InsufficientSpace { .. })
+        ));
+    }
+
+    #[test]
+    fn temp_dir_reports_free_space() {
+        assert!(available_bytes(&std::env::temp_dir()).unwrap() > 0);
+    }
+}
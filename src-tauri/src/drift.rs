@@ -0,0 +1,397 @@
+//! Device-vs-host clock drift estimation over unsolicited stream frames.
+//!
+//! Each frame carries a device timestamp at a configurable payload offset.
+//! The device clock (seconds) is fitted against host receive time by least
+//! squares; a slope of exactly 1 means no drift.
+//!
+//! `sync_device_clock` takes a single reading instead, pinning one device
+//! tick to host wall-clock time so later timestamps can be converted.
+
+use crate::error::SerialError;
+use crate::frame::{read_frame, Endianness};
+use crate::serial::{exchange, with_entry};
+use serde::{Deserialize, Serialize};
+use serialport::ClearBuffer;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Host-side extension in the style of READ_REGISTER: an empty request,
+/// answered with the device's free-running tick counter.
+pub const MSG_GET_TICKS: u8 = 0x22;
+
+/// Most frames one `clock_drift` call reads; the device stays locked until
+/// they have all arrived.
+const MAX_DRIFT_SAMPLES: usize = 4096;
+
+/// Location and unit of the timestamp inside a stream frame's payload.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimestampField {
+    /// Byte offset into the payload, i.e. not counting the TYPE byte.
+    pub offset: usize,
+    /// Field width in bytes, 1..=8.
+    pub width: usize,
+    pub ticks_per_second: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DriftResult {
+    /// Device clock rate relative to the host, in parts per million; positive
+    /// means the device runs fast.
+    pub drift_ppm: f64,
+    /// RMS distance of the device timestamps from the fitted line, in microseconds.
+    pub residual_us: f64,
+    pub samples: usize,
+}
+
+impl TimestampField {
+    fn validate(&self) -> Result<(), SerialError> {
+        if !(1..=8).contains(&self.width) {
+            return Err(SerialError::InvalidConfig {
+                message: format!("timestamp width must be within 1..=8, got {}", self.width),
+            });
+        }
+        if !(self.ticks_per_second.is_finite() && self.ticks_per_second > 0.0) {
+            return Err(SerialError::InvalidConfig {
+                message: "ticks per second must be positive".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn extract(&self, body: &[u8], endianness: Endianness) -> Result<u64, SerialError> {
+        self.offset
+            .checked_add(1)
+            .and_then(|start| Some(start..start.checked_add(self.width)?))
+            .and_then(|range| body.get(range))
+            .map(|bytes| endianness.decode_uint(bytes))
+            .ok_or_else(|| SerialError::InvalidResponse {
+                message: format!(
+                    "stream frame too short for timestamp at offset {} ({} bytes)",
+                    self.offset,
+                    body.len().saturating_sub(1)
+                ),
+            })
+    }
+}
+
+/// Undoes counter wraparounds so the device timeline is monotonic.
+fn unwrap_ticks(raw: &[u64], width: usize) -> Vec<u64> {
+    let span = if width >= 8 { 0 } else { 1u64 << (8 * width) };
+    let mut offset = 0u64;
+    let mut prev = None;
+    raw.iter()
+        .map(|&ticks| {
+            if matches!(prev, Some(p) if ticks < p) {
+                offset = offset.wrapping_add(span);
+            }
+            prev = Some(ticks);
+            ticks.wrapping_add(offset)
+        })
+        .collect()
+}
+
+/// Fits device seconds against host seconds, both relative to the first sample.
+fn fit_drift(host: &[f64], device: &[f64]) -> Result<DriftResult, SerialError> {
+    let n = host.len();
+    if n < 2 || device.len() != n {
+        return Err(SerialError::InvalidConfig {
+            message: "drift estimate needs at least 2 samples".to_string(),
+        });
+    }
+    let count = n as f64;
+    let mean_x = host.iter().sum::<f64>() / count;
+    let mean_y = device.iter().sum::<f64>() / count;
+    let (mut sxx, mut sxy) = (0.0, 0.0);
+    for (x, y) in host.iter().zip(device) {
+        sxx += (x - mean_x) * (x - mean_x);
+        sxy += (x - mean_x) * (y - mean_y);
+    }
+    if sxx == 0.0 {
+        return Err(SerialError::InvalidResponse {
+            message: "stream frames arrived without measurable spacing".to_string(),
+        });
+    }
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+    let sum_sq: f64 = host
+        .iter()
+        .zip(device)
+        .map(|(x, y)| (y - (intercept + slope * x)).powi(2))
+        .sum();
+
+    Ok(DriftResult {
+        drift_ppm: (slope - 1.0) * 1e6,
+        residual_us: (sum_sq / count).sqrt() * 1e6,
+        samples: n,
+    })
+}
+
+#[tauri::command]
+pub fn clock_drift(
+    handle_id: u64,
+    sample_count: usize,
+    field: TimestampField,
+) -> Result<DriftResult, SerialError> {
+    field.validate()?;
+    if !(2..=MAX_DRIFT_SAMPLES).contains(&sample_count) {
+        return Err(SerialError::InvalidConfig {
+            message: format!("sample count must be within 2..={MAX_DRIFT_SAMPLES}"),
+        });
+    }
+
+    let (received, raw) = with_entry(handle_id, "clock_drift", |entry| {
+        // Anything already buffered has an unknown arrival time.
+        entry.port.clear(ClearBuffer::Input)?;
+        let mut received = Vec::with_capacity(sample_count);
+        let mut raw = Vec::with_capacity(sample_count);
+        for _ in 0..sample_count {
+            let body = read_frame(&mut *entry.port, &entry.protocol)?;
+            received.push(Instant::now());
+            raw.push(field.extract(&body, entry.protocol.endianness)?);
+        }
+        Ok((received, raw))
+    })?;
+
+    let ticks = unwrap_ticks(&raw, field.width);
+    let host: Vec<f64> = received
+        .iter()
+        .map(|at| at.duration_since(received[0]).as_secs_f64())
+        .collect();
+    let device: Vec<f64> = ticks
+        .iter()
+        .map(|&t| (t - ticks[0]) as f64 / field.ticks_per_second)
+        .collect();
+    fit_drift(&host, &device)
+}
+
+/// One device tick pinned to host wall-clock time.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClockSync {
+    /// Host time, in microseconds since the Unix epoch, at which the device
+    /// read `device_tick`. Taken as the midpoint of the round trip.
+    pub host_epoch_us: u64,
+    pub device_tick: u64,
+    pub tick_hz: f64,
+    /// Request-to-response time; half of it bounds the mapping error.
+    pub round_trip_us: u64,
+}
+
+/// Queries the tick counter with `MSG_GET_TICKS` and maps it to host time.
+/// `tick_offset` and `tick_width` locate the counter in the response payload
+/// as for `clock_drift`; `tick_hz` is its rate.
+#[tauri::command]
+pub fn sync_device_clock(
+    handle_id: u64,
+    tick_offset: usize,
+    tick_width: usize,
+    tick_hz: f64,
+) -> Result<ClockSync, SerialError> {
+    let field = TimestampField {
+        offset: tick_offset,
+        width: tick_width,
+        ticks_per_second: tick_hz,
+    };
+    field.validate()?;
+
+    let (sent, received, device_tick) = with_entry(handle_id, "sync_device_clock", |entry| {
+        let sent = Instant::now();
+        let response = exchange(entry, &[MSG_GET_TICKS])?;
+        let received = Instant::now();
+        match response.first() {
+            Some(&MSG_GET_TICKS) => {}
+            Some(other) => {
+                return Err(SerialError::InvalidResponse {
+                    message: format!("expected tick response, got type 0x{other:02X}"),
+                })
+            }
+            None => {
+                return Err(SerialError::InvalidResponse {
+                    message: "empty tick response".to_string(),
+                })
+            }
+        }
+        let tick = field.extract(&response, entry.protocol.endianness)?;
+        Ok((sent, received, tick))
+    })?;
+
+    let round_trip = received.duration_since(sent);
+    let now_us = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|err| SerialError::IoError {
+            message: format!("host clock before Unix epoch: {err}"),
+        })?
+        .as_micros() as u64;
+    let since_midpoint = received.elapsed() + round_trip / 2;
+    Ok(ClockSync {
+        host_epoch_us: now_us - since_midpoint.as_micros() as u64,
+        device_tick,
+        tick_hz,
+        round_trip_us: round_trip.as_micros() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::build_frame;
+    use crate::mock_port::MockPort;
+    use crate::serial::insert_mock;
+    use std::time::Duration;
+
+    const STREAM_TYPE: u8 = 0x40;
+
+    fn stream_frame(ticks: u32) -> Vec<u8> {
+        let mut body = vec![STREAM_TYPE, 0xAA];
+        body.extend_from_slice(&ticks.to_le_bytes());
+        build_frame(&body).unwrap()
+    }
+
+    fn field() -> TimestampField {
+        TimestampField {
+            offset: 1,
+            width: 4,
+            ticks_per_second: 1_000_000.0,
+        }
+    }
+
+    #[test]
+    fn fit_recovers_exact_drift() {
+        let host: Vec<f64> = (0..10).map(|i| i as f64 * 0.1).collect();
+        let device: Vec<f64> = host.iter().map(|x| x * (1.0 + 250e-6)).collect();
+        let result = fit_drift(&host, &device).unwrap();
+        assert!((result.drift_ppm - 250.0).abs() < 1e-6);
+        assert!(result.residual_us < 1e-6);
+    }
+
+    #[test]
+    fn unwrap_handles_counter_rollover() {
+        assert_eq!(unwrap_ticks(&[250, 254, 2, 6], 1), vec![250, 254, 258, 262]);
+    }
+
+    #[test]
+    fn extract_reads_field_after_type_byte() {
+        let body = [STREAM_TYPE, 0xAA, 0x01, 0x02, 0x03, 0x04];
+        assert_eq!(
+            field().extract(&body, Endianness::Little).unwrap(),
+            0x0403_0201
+        );
+        assert_eq!(
+            field().extract(&body, Endianness::Big).unwrap(),
+            0x0102_0304
+        );
+        assert!(matches!(
+            field().extract(&body[..4], Endianness::Little),
+            Err(SerialError::InvalidResponse { .. })
+        ));
+        let past_end = TimestampField {
+            offset: usize::MAX,
+            ..field()
+        };
+        assert!(matches!(
+            past_end.extract(&body, Endianness::Little),
+            Err(SerialError::InvalidResponse { .. })
+        ));
+    }
+
+    #[test]
+    fn clock_drift_estimates_mock_stream_drift() {
+        // Device clock runs 2% fast; large enough to dominate scheduler jitter.
+        let port = MockPort::new();
+        let interval = Duration::from_millis(15);
+        for i in 0..12u32 {
+            let host_us = i * interval.as_micros() as u32;
+            let device_ticks = 5_000 + host_us + host_us / 50;
+            port.schedule_rx(interval * (i + 1), &stream_frame(device_ticks));
+        }
+        let handle_id = insert_mock(&port);
+
+        let result = clock_drift(handle_id, 12, field()).unwrap();
+        assert_eq!(result.samples, 12);
+        assert!(
+            (result.drift_ppm - 20_000.0).abs() < 5_000.0,
+            "drift {} ppm",
+            result.drift_ppm
+        );
+    }
+
+    #[test]
+    fn sync_device_clock_maps_canned_tick_to_host_time() {
+        let port = MockPort::new();
+        let mut body = vec![MSG_GET_TICKS];
+        body.extend_from_slice(&1_500_000u32.to_le_bytes());
+        port.queue_reply(&build_frame(&body).unwrap());
+        let handle_id = insert_mock(&port);
+
+        let before_us = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_micros() as u64;
+        let sync = sync_device_clock(handle_id, 0, 4, 1_000_000.0).unwrap();
+        let after_us = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_micros() as u64;
+
+        assert_eq!(port.written(), build_frame(&[MSG_GET_TICKS]).unwrap());
+        assert_eq!(sync.device_tick, 1_500_000);
+        assert!((before_us..=after_us).contains(&sync.host_epoch_us));
+        assert!(sync.round_trip_us <= after_us - before_us);
+        assert_eq!(sync.tick_hz, 1_000_000.0);
+    }
+
+    #[test]
+    fn sync_device_clock_rejects_foreign_response() {
+        let port = MockPort::new();
+        port.queue_reply(&build_frame(&[0x21, 0, 0, 0, 0]).unwrap());
+        let handle_id = insert_mock(&port);
+        assert!(matches!(
+            sync_device_clock(handle_id, 0, 4, 1_000.0),
+            Err(SerialError::InvalidResponse { .. })
+        ));
+        assert!(matches!(
+            sync_device_clock(handle_id, 0, 4, 0.0),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+
+        let tick_reply = build_frame(&[MSG_GET_TICKS, 0x07, 0, 0, 0]).unwrap();
+        port.queue_reply(&tick_reply);
+        port.queue_reply(&tick_reply);
+        assert!(matches!(
+            sync_device_clock(handle_id, usize::MAX, 4, 1_000.0),
+            Err(SerialError::InvalidResponse { .. })
+        ));
+        assert_eq!(
+            sync_device_clock(handle_id, 0, 4, 1_000.0)
+                .unwrap()
+                .device_tick,
+            7
+        );
+    }
+
+    #[test]
+    fn clock_drift_rejects_bad_field_and_count() {
+        let handle_id = insert_mock(&MockPort::new());
+        let bad_width = TimestampField {
+            width: 9,
+            ..field()
+        };
+        assert!(matches!(
+            clock_drift(handle_id, 4, bad_width),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+        assert!(matches!(
+            clock_drift(handle_id, 1, field()),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+        assert!(matches!(
+            clock_drift(handle_id, MAX_DRIFT_SAMPLES + 1, field()),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+        assert!(matches!(
+            clock_drift(handle_id, usize::MAX, field()),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+    }
+}
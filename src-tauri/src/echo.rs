@@ -0,0 +1,222 @@
+//! In-memory loopback device for UI work and CI, where no serial port (real
+//! or pty) is available.
+
+use crate::error::SerialError;
+use crate::frame::ProtocolConfig;
+use crate::serial::{registry, DeviceEntry};
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// Path reported for echo handles.
+pub const ECHO_DEVICE_PATH: &str = "echo://loopback";
+
+/// Port whose input is whatever was last written to it. A framed request is
+/// read back as a response with the same payload, and a malformed frame
+/// fails to parse exactly as it would on a looped-back cable.
+pub(crate) struct EchoPort {
+    /// Behind a lock only because `SerialPort::clear` takes `&self`.
+    rx: Mutex<VecDeque<u8>>,
+    timeout: Duration,
+    /// Sleep out the timeout before reporting an empty read, like a real
+    /// port. Off for replays, where the input will never grow.
+    wait_when_empty: bool,
+}
+
+impl EchoPort {
+    /// Port that reads back `bytes` and then times out at once, so the frame
+    /// reader can be driven over a capture without waiting on the clock.
+    pub(crate) fn replay(bytes: &[u8]) -> Self {
+        Self {
+            rx: Mutex::new(bytes.iter().copied().collect()),
+            timeout: Duration::from_secs(1),
+            wait_when_empty: false,
+        }
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.rx().len()
+    }
+
+    fn rx(&self) -> std::sync::MutexGuard<'_, VecDeque<u8>> {
+        self.rx.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Read for EchoPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut rx = self.rx();
+        if rx.is_empty() {
+            // Nothing else can write while the reader holds the entry, so
+            // behave like a blocking port waiting out its timeout.
+            if self.wait_when_empty {
+                std::thread::sleep(self.timeout);
+            }
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "echo device has no data",
+            ));
+        }
+        let n = buf.len().min(rx.len());
+        for (slot, byte) in buf.iter_mut().zip(rx.drain(..n)) {
+            *slot = byte;
+        }
+        Ok(n)
+    }
+}
+
+impl Write for EchoPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.rx().extend(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SerialPort for EchoPort {
+    fn name(&self) -> Option<String> {
+        Some(ECHO_DEVICE_PATH.to_string())
+    }
+
+    fn baud_rate(&self) -> serialport::Result<u32> {
+        Ok(115_200)
+    }
+
+    fn data_bits(&self) -> serialport::Result<DataBits> {
+        Ok(DataBits::Eight)
+    }
+
+    fn flow_control(&self) -> serialport::Result<FlowControl> {
+        Ok(FlowControl::None)
+    }
+
+    fn parity(&self) -> serialport::Result<Parity> {
+        Ok(Parity::None)
+    }
+
+    fn stop_bits(&self) -> serialport::Result<StopBits> {
+        Ok(StopBits::One)
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn set_baud_rate(&mut self, _baud_rate: u32) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn set_data_bits(&mut self, _data_bits: DataBits) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn set_flow_control(&mut self, _flow_control: FlowControl) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn set_parity(&mut self, _parity: Parity) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn set_stop_bits(&mut self, _stop_bits: StopBits) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> serialport::Result<()> {
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    fn write_request_to_send(&mut self, _level: bool) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn write_data_terminal_ready(&mut self, _level: bool) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+        Ok(true)
+    }
+
+    fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+        Ok(true)
+    }
+
+    fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+
+    fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+        Ok(true)
+    }
+
+    fn bytes_to_read(&self) -> serialport::Result<u32> {
+        Ok(self.rx().len() as u32)
+    }
+
+    fn bytes_to_write(&self) -> serialport::Result<u32> {
+        Ok(0)
+    }
+
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> serialport::Result<()> {
+        if !matches!(buffer_to_clear, ClearBuffer::Output) {
+            self.rx().clear();
+        }
+        Ok(())
+    }
+
+    fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+        Err(serialport::Error::new(
+            serialport::ErrorKind::Unknown,
+            "echo device cannot be cloned",
+        ))
+    }
+
+    fn set_break(&self) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn clear_break(&self) -> serialport::Result<()> {
+        Ok(())
+    }
+}
+
+/// Registers an in-memory handle that answers every framed request with its
+/// own payload. It works with every command that takes a handle id and is
+/// closed with `close_device`; it cannot be reopened.
+#[tauri::command]
+pub fn open_echo_device() -> Result<u64, SerialError> {
+    let port = EchoPort {
+        rx: Mutex::new(VecDeque::new()),
+        timeout: Duration::from_millis(100),
+        wait_when_empty: true,
+    };
+    Ok(registry().insert(DeviceEntry::new(
+        ECHO_DEVICE_PATH.to_string(),
+        Box::new(port),
+        ProtocolConfig::default(),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serial::{close_device, send_request};
+
+    #[test]
+    fn echo_device_returns_request_payload() {
+        let handle_id = open_echo_device().unwrap();
+        assert_eq!(
+            send_request(handle_id, vec![0x01, 0xAA, 0x55]).unwrap(),
+            vec![0x01, 0xAA, 0x55]
+        );
+        assert_eq!(send_request(handle_id, vec![0x04]).unwrap(), vec![0x04]);
+        close_device(handle_id, None, None, None).unwrap();
+    }
+}
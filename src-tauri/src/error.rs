@@ -1,3 +1,4 @@
+use crate::disconnect::{is_disconnect, DisconnectKind};
 use serde::Serialize;
 use thiserror::Error;
 
@@ -10,12 +11,18 @@ pub enum SerialError {
     #[error("port busy: {path}")]
     PortBusy { path: String },
 
+    #[error("permission denied: {path}")]
+    PermissionDenied { path: String },
+
     #[error("invalid handle: {handle_id}")]
     InvalidHandle { handle_id: u64 },
 
     #[error("timeout")]
     Timeout,
 
+    #[error("no data available")]
+    WouldBlock,
+
     #[error("crc mismatch")]
     CrcMismatch,
 
@@ -27,12 +34,30 @@ pub enum SerialError {
 
     #[error("payload too large")]
     PayloadTooLarge,
+
+    #[error("invalid response: {message}")]
+    InvalidResponse { message: String },
+
+    #[error("message type 0x{message_type:02X} blocked by policy")]
+    PolicyDenied { message_type: u8 },
+
+    #[error("device disconnected: {message}")]
+    Disconnected { message: String },
+
+    #[error("rate limit exceeded; retry in {retry_after_ms} ms")]
+    RateLimited { retry_after_ms: u64 },
+
+    #[error("stream reader is active on this handle")]
+    StreamActive,
 }
 
 impl From<std::io::Error> for SerialError {
     fn from(err: std::io::Error) -> Self {
         match err.kind() {
             std::io::ErrorKind::TimedOut => SerialError::Timeout,
+            kind if is_disconnect(DisconnectKind::Io(kind)) => SerialError::Disconnected {
+                message: err.to_string(),
+            },
             _ => SerialError::IoError {
                 message: err.to_string(),
             },
@@ -43,12 +68,20 @@ impl From<std::io::Error> for SerialError {
 impl From<serialport::Error> for SerialError {
     fn from(err: serialport::Error) -> Self {
         match err.kind {
+            serialport::ErrorKind::NoDevice if is_disconnect(DisconnectKind::NoDevice) => {
+                SerialError::Disconnected {
+                    message: err.to_string(),
+                }
+            }
             serialport::ErrorKind::NoDevice => SerialError::PortNotFound {
                 path: err.to_string(),
             },
             serialport::ErrorKind::Io(io_kind) => match io_kind {
                 std::io::ErrorKind::TimedOut => SerialError::Timeout,
-                std::io::ErrorKind::PermissionDenied => SerialError::PortBusy {
+                kind if is_disconnect(DisconnectKind::Io(kind)) => SerialError::Disconnected {
+                    message: err.to_string(),
+                },
+                std::io::ErrorKind::PermissionDenied => SerialError::PermissionDenied {
                     path: err.to_string(),
                 },
                 _ => SerialError::IoError {
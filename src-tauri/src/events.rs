@@ -0,0 +1,64 @@
+//! Backend-to-frontend event emission.
+//!
+//! Background work (worker threads, stream readers) emits through an
+//! `EventSink` rather than a concrete `AppHandle` so tests can capture events.
+
+use std::sync::Arc;
+use tauri::Emitter;
+
+pub type EventSink = Arc<dyn Fn(&str, serde_json::Value) + Send + Sync>;
+
+pub fn app_sink(app: tauri::AppHandle) -> EventSink {
+    Arc::new(move |event, payload| {
+        let _ = app.emit(event, payload);
+    })
+}
+
+/// Serializes `payload` and hands it to `sink`; a payload that cannot be
+/// serialized is dropped rather than surfacing as an error mid-operation.
+pub fn emit<S: serde::Serialize>(sink: &EventSink, event: &str, payload: &S) {
+    if let Ok(value) = serde_json::to_value(payload) {
+        sink(event, value);
+    }
+}
+
+#[cfg(test)]
+pub mod testing {
+    use super::EventSink;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    pub type Captured = Arc<Mutex<Vec<(String, serde_json::Value)>>>;
+
+    pub fn capture() -> (EventSink, Captured) {
+        let captured: Captured = Arc::default();
+        let store = captured.clone();
+        let sink: EventSink = Arc::new(move |event, payload| {
+            store.lock().unwrap().push((event.to_string(), payload));
+        });
+        (sink, captured)
+    }
+
+    /// Polls until `count` events named `event` have been captured.
+    pub fn wait_for(
+        captured: &Captured,
+        event: &str,
+        count: usize,
+        timeout: Duration,
+    ) -> Vec<serde_json::Value> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let matching: Vec<_> = captured
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(name, _)| name == event)
+                .map(|(_, payload)| payload.clone())
+                .collect();
+            if matching.len() >= count || Instant::now() >= deadline {
+                return matching;
+            }
+            std::thread::sleep(Duration::from_millis(2));
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! Decoding helpers for fields inside response payloads the frontend has
+//! already received.
+
+use crate::error::SerialError;
+use crate::frame::Endianness;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+fn invalid_response(message: String) -> SerialError {
+    SerialError::InvalidResponse { message }
+}
+
+/// Decodes the string at `offset`: one length byte, then that many UTF-8
+/// bytes.
+#[tauri::command]
+pub fn read_string_field(payload: Vec<u8>, offset: usize) -> Result<String, SerialError> {
+    decode_string(&payload, offset)
+}
+
+fn decode_string(payload: &[u8], offset: usize) -> Result<String, SerialError> {
+    let len = *payload.get(offset).ok_or_else(|| {
+        invalid_response(format!(
+            "string length at offset {offset} is past the {}-byte payload",
+            payload.len()
+        ))
+    })?;
+    let start = offset + 1;
+    let bytes = payload
+        .get(start..start + usize::from(len))
+        .ok_or_else(|| {
+            invalid_response(format!(
+                "{len}-byte string at offset {offset} runs past the {}-byte payload",
+                payload.len()
+            ))
+        })?;
+    std::str::from_utf8(bytes)
+        .map(str::to_string)
+        .map_err(|_| invalid_response(format!("string at offset {offset} is not utf-8")))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldKind {
+    U8,
+    U16,
+    U32,
+    I8,
+    I16,
+    I32,
+    F32,
+    /// Length-prefixed UTF-8, as read by `read_string_field`.
+    String,
+}
+
+impl FieldKind {
+    /// Fixed width in bytes; `None` for the length-prefixed string.
+    fn width(self) -> Option<usize> {
+        match self {
+            FieldKind::U8 | FieldKind::I8 => Some(1),
+            FieldKind::U16 | FieldKind::I16 => Some(2),
+            FieldKind::U32 | FieldKind::I32 | FieldKind::F32 => Some(4),
+            FieldKind::String => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldSpec {
+    pub name: String,
+    pub kind: FieldKind,
+    /// Byte offset into the payload, TYPE byte included.
+    pub offset: usize,
+    /// Byte order of multi-byte fields; little-endian when omitted.
+    pub endianness: Option<Endianness>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum DecodedValue {
+    Int(i64),
+    Float(f64),
+    Text(String),
+}
+
+fn decode_field(payload: &[u8], field: &FieldSpec) -> Result<DecodedValue, SerialError> {
+    let Some(width) = field.kind.width() else {
+        return decode_string(payload, field.offset).map(DecodedValue::Text);
+    };
+    let bytes = field
+        .offset
+        .checked_add(width)
+        .and_then(|end| payload.get(field.offset..end))
+        .ok_or_else(|| {
+            invalid_response(format!(
+                "{width}-byte field {:?} at offset {} runs past the {}-byte payload",
+                field.name,
+                field.offset,
+                payload.len()
+            ))
+        })?;
+    let raw = field
+        .endianness
+        .unwrap_or(Endianness::Little)
+        .decode_uint(bytes);
+    Ok(match field.kind {
+        FieldKind::U8 | FieldKind::U16 | FieldKind::U32 => DecodedValue::Int(raw as i64),
+        FieldKind::I8 => DecodedValue::Int(i64::from(raw as u8 as i8)),
+        FieldKind::I16 => DecodedValue::Int(i64::from(raw as u16 as i16)),
+        FieldKind::I32 => DecodedValue::Int(i64::from(raw as u32 as i32)),
+        FieldKind::F32 => DecodedValue::Float(f64::from(f32::from_bits(raw as u32))),
+        FieldKind::String => unreachable!("strings have no fixed width"),
+    })
+}
+
+/// Decodes every field in `spec` from `payload`, keyed by name. Fails on the
+/// first field that does not fit, and on a name used twice.
+#[tauri::command]
+pub fn decode_payload(
+    payload: Vec<u8>,
+    spec: Vec<FieldSpec>,
+) -> Result<HashMap<String, DecodedValue>, SerialError> {
+    let mut decoded = HashMap::with_capacity(spec.len());
+    for field in &spec {
+        let value = decode_field(&payload, field)?;
+        if decoded.insert(field.name.clone(), value).is_some() {
+            return Err(SerialError::InvalidConfig {
+                message: format!("field {:?} appears twice in the spec", field.name),
+            });
+        }
+    }
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(name: &str, kind: FieldKind, offset: usize, endianness: Endianness) -> FieldSpec {
+        FieldSpec {
+            name: name.to_string(),
+            kind,
+            offset,
+            endianness: Some(endianness),
+        }
+    }
+
+    #[test]
+    fn payload_decodes_mixed_fields_in_both_byte_orders() {
+        let payload = vec![0x21, 0x7F, 0x34, 0x12, 0x78, 0x56, 0x34, 0x12, 0xFF, 0xFE];
+        for (endianness, u16_value, u32_value, i16_value) in [
+            (Endianness::Little, 0x1234, 0x1234_5678, -257),
+            (Endianness::Big, 0x3412, 0x7856_3412, -2),
+        ] {
+            let spec = vec![
+                field("kind", FieldKind::U8, 0, endianness),
+                field("level", FieldKind::U8, 1, endianness),
+                field("count", FieldKind::U16, 2, endianness),
+                field("serial", FieldKind::U32, 4, endianness),
+                field("offset", FieldKind::I16, 8, endianness),
+            ];
+            let decoded = decode_payload(payload.clone(), spec).unwrap();
+            assert_eq!(decoded["kind"], DecodedValue::Int(0x21));
+            assert_eq!(decoded["level"], DecodedValue::Int(0x7F));
+            assert_eq!(decoded["count"], DecodedValue::Int(u16_value));
+            assert_eq!(decoded["serial"], DecodedValue::Int(u32_value));
+            assert_eq!(decoded["offset"], DecodedValue::Int(i16_value));
+        }
+    }
+
+    #[test]
+    fn payload_decodes_floats_and_strings() {
+        let mut payload = vec![0x22];
+        payload.extend(1.5f32.to_le_bytes());
+        payload.extend([2, b'o', b'k']);
+        let spec = vec![
+            field("gain", FieldKind::F32, 1, Endianness::Little),
+            field("status", FieldKind::String, 5, Endianness::Little),
+        ];
+        let decoded = decode_payload(payload, spec).unwrap();
+        assert_eq!(decoded["gain"], DecodedValue::Float(1.5));
+        assert_eq!(decoded["status"], DecodedValue::Text("ok".to_string()));
+    }
+
+    #[test]
+    fn payload_rejects_out_of_bounds_and_duplicate_fields() {
+        let payload = vec![0x21, 0x01, 0x02];
+        assert!(matches!(
+            decode_payload(
+                payload.clone(),
+                vec![field("serial", FieldKind::U32, 1, Endianness::Little)]
+            ),
+            Err(SerialError::InvalidResponse { .. })
+        ));
+        assert!(matches!(
+            decode_payload(
+                payload.clone(),
+                vec![field("end", FieldKind::U16, usize::MAX, Endianness::Little)]
+            ),
+            Err(SerialError::InvalidResponse { .. })
+        ));
+        assert!(matches!(
+            decode_payload(
+                payload,
+                vec![
+                    field("a", FieldKind::U8, 0, Endianness::Little),
+                    field("a", FieldKind::U8, 1, Endianness::Little),
+                ]
+            ),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+    }
+
+    #[test]
+    fn string_field_decodes_at_offset() {
+        let payload = vec![0x20, 0x07, 3, b'a', b'b', b'c', 0xFF];
+        assert_eq!(read_string_field(payload.clone(), 2).unwrap(), "abc");
+        assert_eq!(read_string_field(vec![0x01, 0], 1).unwrap(), "");
+    }
+
+    #[test]
+    fn string_field_out_of_bounds() {
+        assert!(matches!(
+            read_string_field(vec![0x20, 4, b'a', b'b'], 1),
+            Err(SerialError::InvalidResponse { .. })
+        ));
+        assert!(matches!(
+            read_string_field(vec![0x20], 1),
+            Err(SerialError::InvalidResponse { .. })
+        ));
+    }
+
+    #[test]
+    fn string_field_rejects_invalid_utf8() {
+        assert!(matches!(
+            read_string_field(vec![2, 0xC3, 0x28], 0),
+            Err(SerialError::InvalidResponse { .. })
+        ));
+    }
+}
@@ -0,0 +1,1217 @@
+//! Wire format: `SYNC | LEN | TYPE | PAYLOAD | CRC`, plus the per-handle
+//! parameters that let the same reader/writer talk to non-VScope devices.
+
+use crate::crc::{Checksum, CrcCoverage, CrcVariant};
+use crate::error::SerialError;
+use serde::{Deserialize, Serialize};
+use serialport::SerialPort;
+use std::collections::VecDeque;
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
+
+// Protocol constants matching the code in onboard/vscope.c
+pub const VSCOPE_SYNC_BYTE: u8 = 0xC8;
+pub const MAX_FRAME_LEN: usize = 254;
+pub const MAX_PAYLOAD_LEN: usize = 252;
+/// TYPE of the device's error reply; the payload is a single error code.
+pub const MSG_ERROR: u8 = 0xFF;
+
+/// Framing parameters kept per handle and consulted by the frame reader/writer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ProtocolConfig {
+    pub sync_byte: u8,
+    pub crc_variant: CrcVariant,
+    pub crc_coverage: CrcCoverage,
+    pub crc_init: u8,
+    pub crc_position: CrcPosition,
+    pub length_convention: LengthConvention,
+    /// Byte order of multi-byte fields in typed helpers (device-native).
+    pub endianness: Endianness,
+    /// Largest PAYLOAD (excluding TYPE) `build_frame` will send.
+    pub max_payload_len: usize,
+    /// Largest LEN field `read_frame` will accept; anything above is treated
+    /// as corruption and resynced past. Must not exceed the protocol max.
+    pub max_read_frame_len: usize,
+    /// Sync bytes sent ahead of LEN; noisy links use a longer run so the
+    /// receiver can lock on.
+    pub preamble_count: u8,
+    /// Fixed terminator some devices append after the CRC. It sits outside
+    /// LEN and the checksum.
+    pub footer: Option<u8>,
+    /// Sleep between polls when a read yields no data, trading a little
+    /// latency for not spinning the CPU on idle or trickling links.
+    pub read_poll_interval_us: u64,
+    /// Non-sync bytes tolerated ahead of a request's response before the
+    /// read fails as a protocol error instead of waiting out the timeout.
+    /// `None` skips any amount. The stream reader does not apply it.
+    pub leading_skip_max: Option<usize>,
+    /// Length field some devices repeat inside the body, cross-checked
+    /// against LEN to catch truncated responses.
+    pub validate_inner_length: Option<InnerLengthSpec>,
+    /// Time allowed for the rest of a frame once SYNC and LEN have arrived.
+    /// A device that stalls mid-frame then fails with "incomplete frame"
+    /// instead of a plain timeout. `None` keeps the overall read deadline.
+    pub frame_assembly_timeout_ms: Option<u64>,
+    /// Accept frames with no TYPE byte at all, for protocols that use an
+    /// empty frame as a heartbeat.
+    pub allow_empty_payload: bool,
+    /// Bytes between the end of one frame and the next sync, as sent by a
+    /// device whose LEN under-reports.
+    pub extra_bytes: ExtraBytesPolicy,
+    /// Fixed marker some devices put ahead of every response, outside the
+    /// protocol. Discarded when a read starts with it; a read that does not
+    /// is parsed as usual.
+    pub response_skip_prefix: Vec<u8>,
+}
+
+impl Default for ProtocolConfig {
+    fn default() -> Self {
+        Self {
+            sync_byte: VSCOPE_SYNC_BYTE,
+            crc_variant: CrcVariant::Crc8DvbS2,
+            crc_coverage: CrcCoverage::Payload,
+            crc_init: 0x00,
+            crc_position: CrcPosition::Suffix,
+            length_convention: LengthConvention::BodyAndCrc,
+            endianness: Endianness::Little,
+            max_payload_len: MAX_PAYLOAD_LEN,
+            max_read_frame_len: MAX_FRAME_LEN,
+            preamble_count: 1,
+            footer: None,
+            read_poll_interval_us: 500,
+            leading_skip_max: None,
+            validate_inner_length: None,
+            frame_assembly_timeout_ms: None,
+            allow_empty_payload: false,
+            extra_bytes: ExtraBytesPolicy::Lenient,
+            response_skip_prefix: Vec::new(),
+        }
+    }
+}
+
+impl ProtocolConfig {
+    pub fn validate(&self) -> Result<(), SerialError> {
+        match self.field_errors().into_iter().next() {
+            Some((_, message)) => Err(SerialError::InvalidConfig { message }),
+            None => Ok(()),
+        }
+    }
+
+    /// Every problem `validate` would report, as `(field, message)`, where
+    /// `validate` stops at the first.
+    pub(crate) fn field_errors(&self) -> Vec<(&'static str, String)> {
+        let mut errors = Vec::new();
+        let min_read_len = self.min_body_len() + 1;
+        if !(min_read_len..=MAX_FRAME_LEN).contains(&self.max_read_frame_len) {
+            errors.push((
+                "maxReadFrameLen",
+                format!("maxReadFrameLen must be within {min_read_len}..={MAX_FRAME_LEN}"),
+            ));
+        }
+        if self.max_payload_len > MAX_PAYLOAD_LEN {
+            errors.push((
+                "maxPayloadLen",
+                format!("maxPayloadLen must be at most {MAX_PAYLOAD_LEN}"),
+            ));
+        }
+        if self.preamble_count == 0 {
+            errors.push((
+                "preambleCount",
+                "preambleCount must be at least 1".to_string(),
+            ));
+        }
+        if let Some(spec) = &self.validate_inner_length {
+            if !(1..=4).contains(&spec.width) {
+                errors.push((
+                    "validateInnerLength",
+                    "validateInnerLength width must be within 1..=4".to_string(),
+                ));
+            }
+        }
+        errors
+    }
+
+    fn min_body_len(&self) -> usize {
+        usize::from(!self.allow_empty_payload)
+    }
+
+    /// TYPE + PAYLOAD length implied by a LEN field, if LEN is acceptable.
+    fn body_len(&self, len: usize) -> Option<usize> {
+        self.length_convention
+            .body_len(len)
+            .filter(|&body_len| body_len >= self.min_body_len() && len <= self.max_read_frame_len)
+    }
+
+    /// Bytes on the wire for a frame carrying `body_len` bytes of TYPE +
+    /// PAYLOAD: preamble, LEN, CRC and footer included.
+    pub fn frame_len(&self, body_len: usize) -> usize {
+        let overhead = usize::from(self.preamble_count) + 2 + usize::from(self.footer.is_some());
+        overhead + body_len
+    }
+
+    pub fn read_poll_interval(&self) -> Duration {
+        Duration::from_micros(self.read_poll_interval_us)
+    }
+
+    pub fn checksum(&self) -> Checksum {
+        Checksum {
+            variant: self.crc_variant,
+            coverage: self.crc_coverage,
+            init: self.crc_init,
+        }
+    }
+}
+
+/// Where a body-level length field sits and what it counts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InnerLengthSpec {
+    /// Offset into TYPE + PAYLOAD; 1 is the first byte after TYPE.
+    pub offset: usize,
+    /// Field width in bytes, decoded with the handle's endianness.
+    pub width: u8,
+    /// The field counts the whole of TYPE + PAYLOAD rather than only the
+    /// bytes after itself.
+    pub includes_header: bool,
+}
+
+impl InnerLengthSpec {
+    /// Why `body` disagrees with its own length field, if it does. Device
+    /// error replies (`MSG_ERROR`) carry no such field and are not checked.
+    fn check(&self, body: &[u8], endianness: Endianness) -> Result<(), String> {
+        if body.first() == Some(&MSG_ERROR) {
+            return Ok(());
+        }
+        let end = self.offset + usize::from(self.width);
+        let Some(field) = body.get(self.offset..end) else {
+            return Err(format!(
+                "{}-byte body too short for the inner length field at {}",
+                body.len(),
+                self.offset
+            ));
+        };
+        let actual = if self.includes_header {
+            body.len()
+        } else {
+            body.len() - end
+        };
+        let declared = endianness.decode_uint(field);
+        if declared != actual as u64 {
+            return Err(format!(
+                "inner length {declared} does not match {actual} bytes received"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Where the CRC byte sits relative to TYPE + PAYLOAD. LEN and the
+/// checksum's coverage are the same either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CrcPosition {
+    /// After the payload (VScope).
+    Suffix,
+    /// Between LEN and TYPE.
+    Prefix,
+}
+
+impl CrcPosition {
+    /// Splits the bytes following LEN (body and CRC, in wire order) into the
+    /// body and the CRC byte.
+    fn split(self, section: &[u8]) -> (&[u8], u8) {
+        match self {
+            CrcPosition::Suffix => {
+                let (body, crc) = section.split_at(section.len() - 1);
+                (body, crc[0])
+            }
+            CrcPosition::Prefix => (&section[1..], section[0]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtraBytesPolicy {
+    /// Skip them on the way to the next sync byte, like any line noise.
+    Lenient,
+    /// Fail the read that meets them with `InvalidResponse`, to catch the
+    /// firmware bug rather than paper over it. Applies to reads that follow
+    /// a frame directly: bytes the stream reader finds waiting right after
+    /// a frame (reported as `serial://stream-violation`), and a request
+    /// reading on past a skipped response.
+    Strict,
+}
+
+impl ExtraBytesPolicy {
+    /// `leading_skip_max` for a read that directly follows a frame.
+    pub(crate) fn skip_max(self) -> Option<usize> {
+        match self {
+            ExtraBytesPolicy::Lenient => None,
+            ExtraBytesPolicy::Strict => Some(0),
+        }
+    }
+}
+
+/// What the LEN byte counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LengthConvention {
+    /// TYPE + PAYLOAD + CRC (VScope).
+    BodyAndCrc,
+    /// TYPE + PAYLOAD only.
+    Body,
+}
+
+impl LengthConvention {
+    fn len_field(self, body_len: usize) -> usize {
+        match self {
+            LengthConvention::BodyAndCrc => body_len + 1,
+            LengthConvention::Body => body_len,
+        }
+    }
+
+    fn body_len(self, len_field: usize) -> Option<usize> {
+        match self {
+            LengthConvention::BodyAndCrc => len_field.checked_sub(1),
+            LengthConvention::Body => Some(len_field),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+impl Endianness {
+    pub fn encode_u16(self, value: u16) -> [u8; 2] {
+        match self {
+            Endianness::Little => value.to_le_bytes(),
+            Endianness::Big => value.to_be_bytes(),
+        }
+    }
+
+    pub fn encode_u32(self, value: u32) -> [u8; 4] {
+        match self {
+            Endianness::Little => value.to_le_bytes(),
+            Endianness::Big => value.to_be_bytes(),
+        }
+    }
+
+    pub fn decode_u32(self, bytes: [u8; 4]) -> u32 {
+        match self {
+            Endianness::Little => u32::from_le_bytes(bytes),
+            Endianness::Big => u32::from_be_bytes(bytes),
+        }
+    }
+
+    /// Decodes an unsigned integer of up to eight bytes.
+    pub fn decode_uint(self, bytes: &[u8]) -> u64 {
+        let fold = |acc: u64, byte: &u8| (acc << 8) | u64::from(*byte);
+        match self {
+            Endianness::Little => bytes.iter().rev().fold(0, fold),
+            Endianness::Big => bytes.iter().fold(0, fold),
+        }
+    }
+}
+
+/// Frames `payload` (TYPE + PAYLOAD) with the VScope defaults.
+#[cfg(test)]
+pub fn build_frame(payload: &[u8]) -> Result<Vec<u8>, SerialError> {
+    build_frame_with(payload, &ProtocolConfig::default())
+}
+
+/// CRC byte `build_frame_with` puts on a frame carrying `payload`.
+pub(crate) fn frame_crc(payload: &[u8], protocol: &ProtocolConfig) -> u8 {
+    let len_field = protocol.length_convention.len_field(payload.len()) as u8;
+    protocol
+        .checksum()
+        .compute(protocol.sync_byte, len_field, payload)
+}
+
+pub fn build_frame_with(payload: &[u8], protocol: &ProtocolConfig) -> Result<Vec<u8>, SerialError> {
+    let payload_len = payload.len();
+    if payload_len > (1 + protocol.max_payload_len) {
+        return Err(SerialError::PayloadTooLarge);
+    }
+
+    let len_field = protocol.length_convention.len_field(payload_len) as u8;
+    let mut frame = Vec::with_capacity(protocol.frame_len(payload_len));
+    frame.resize(usize::from(protocol.preamble_count), protocol.sync_byte);
+    frame.push(len_field);
+    let crc = frame_crc(payload, protocol);
+    match protocol.crc_position {
+        CrcPosition::Suffix => {
+            frame.extend_from_slice(payload);
+            frame.push(crc);
+        }
+        CrcPosition::Prefix => {
+            frame.push(crc);
+            frame.extend_from_slice(payload);
+        }
+    }
+    frame.extend(protocol.footer);
+    Ok(frame)
+}
+
+pub fn read_frame(
+    port: &mut dyn SerialPort,
+    protocol: &ProtocolConfig,
+) -> Result<Vec<u8>, SerialError> {
+    read_frame_bounded(port, protocol, None)
+}
+
+/// `read_frame`, failing once more than `leading_skip_max` bytes have been
+/// skipped before the first sync byte.
+pub fn read_frame_bounded(
+    port: &mut dyn SerialPort,
+    protocol: &ProtocolConfig,
+    leading_skip_max: Option<usize>,
+) -> Result<Vec<u8>, SerialError> {
+    read_frame_counted(port, protocol, leading_skip_max, &mut 0)
+}
+
+/// `read_frame_bounded`, adding to `skipped` every byte discarded ahead of
+/// the frame that is returned or rejected.
+pub(crate) fn read_frame_counted(
+    port: &mut dyn SerialPort,
+    protocol: &ProtocolConfig,
+    leading_skip_max: Option<usize>,
+    skipped: &mut usize,
+) -> Result<Vec<u8>, SerialError> {
+    read_frame_parts(port, protocol, leading_skip_max, skipped, true).map(|frame| frame.body)
+}
+
+/// A frame as received, with the CRC byte it carried and the one computed
+/// over it by the handle's checksum.
+pub(crate) struct ReceivedFrame {
+    /// TYPE + PAYLOAD.
+    pub(crate) body: Vec<u8>,
+    pub(crate) received_crc: u8,
+    pub(crate) computed_crc: u8,
+}
+
+/// `read_frame`, returning the CRC the device sent and the one computed over
+/// the body instead of failing when they differ.
+pub(crate) fn read_frame_unchecked(
+    port: &mut dyn SerialPort,
+    protocol: &ProtocolConfig,
+) -> Result<ReceivedFrame, SerialError> {
+    read_frame_parts(port, protocol, None, &mut 0, false)
+}
+
+fn read_frame_parts(
+    port: &mut dyn SerialPort,
+    protocol: &ProtocolConfig,
+    leading_skip_max: Option<usize>,
+    skipped: &mut usize,
+    enforce_crc: bool,
+) -> Result<ReceivedFrame, SerialError> {
+    let deadline = Instant::now() + port.timeout();
+    let mut reader = PolledReader {
+        port,
+        deadline,
+        poll_interval: protocol.read_poll_interval(),
+        replay: VecDeque::new(),
+    };
+    reader.strip_prefix(&protocol.response_skip_prefix)?;
+
+    let mut leading_skipped = 0;
+    let mut synced = false;
+    loop {
+        if Instant::now() >= deadline {
+            return Err(SerialError::Timeout);
+        }
+
+        if reader.byte()? != protocol.sync_byte {
+            *skipped += 1;
+            if !synced {
+                leading_skipped += 1;
+                if leading_skip_max.is_some_and(|max| leading_skipped > max) {
+                    return Err(SerialError::InvalidResponse {
+                        message: format!("{leading_skipped} non-sync bytes ahead of the frame"),
+                    });
+                }
+            }
+            continue;
+        }
+        synced = true;
+
+        // Skip the rest of the preamble. A run longer than the configured
+        // preamble means the last sync byte was really LEN.
+        let mut run = 1;
+        let mut next = reader.byte()?;
+        while next == protocol.sync_byte {
+            if Instant::now() >= deadline {
+                return Err(SerialError::Timeout);
+            }
+            run += 1;
+            next = reader.byte()?;
+        }
+        let (len_byte, first_body) = if run > usize::from(protocol.preamble_count) {
+            (protocol.sync_byte, Some(next))
+        } else {
+            (next, None)
+        };
+
+        let len = len_byte as usize;
+        let Some(body_len) = protocol.body_len(len) else {
+            *skipped += run + 1;
+            continue;
+        };
+
+        let mut buf = vec![0u8; body_len + 1];
+        let start = match first_body {
+            Some(byte) => {
+                buf[0] = byte;
+                1
+            }
+            None => 0,
+        };
+        match protocol.frame_assembly_timeout_ms {
+            Some(ms) => reader.assemble(&mut buf[start..], Duration::from_millis(ms))?,
+            None => reader.exact(&mut buf[start..])?,
+        }
+
+        let (body, crc) = protocol.crc_position.split(&buf);
+        let calc = protocol
+            .checksum()
+            .compute(protocol.sync_byte, len_byte, body);
+        if enforce_crc && crc != calc {
+            return Err(SerialError::CrcMismatch);
+        }
+        if let Some(footer) = protocol.footer {
+            read_footer(&mut reader, footer)?;
+        }
+
+        match protocol.crc_position {
+            CrcPosition::Suffix => buf.truncate(body_len),
+            CrcPosition::Prefix => {
+                buf.remove(0);
+            }
+        }
+        if let Some(spec) = &protocol.validate_inner_length {
+            spec.check(&buf, protocol.endianness)
+                .map_err(|message| SerialError::InvalidResponse { message })?;
+        }
+        return Ok(ReceivedFrame {
+            body: buf,
+            received_crc: crc,
+            computed_crc: calc,
+        });
+    }
+}
+
+/// Outcome of checking caller-supplied frame bytes. Checks run in wire
+/// order and stop at the first failure, so a stage after the failing one is
+/// reported `false` without having been checked.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrameValidation {
+    pub valid: bool,
+    pub sync_ok: bool,
+    pub length_ok: bool,
+    /// The byte count matches what LEN (and the footer setting) implies.
+    pub boundary_ok: bool,
+    pub crc_ok: bool,
+    pub footer_ok: bool,
+    /// Also `true` when no inner length field is configured.
+    pub inner_length_ok: bool,
+    /// TYPE + PAYLOAD when the frame is valid.
+    pub payload: Option<Vec<u8>>,
+    pub reason: Option<String>,
+}
+
+/// Strict counterpart of `read_frame` for a complete frame in memory: no
+/// resyncing, and leftover or missing bytes are errors.
+#[tauri::command]
+pub fn validate_frame(
+    frame: Vec<u8>,
+    config: ProtocolConfig,
+) -> Result<FrameValidation, SerialError> {
+    config.validate()?;
+    let mut result = FrameValidation::default();
+    if let Err(reason) = check_frame(&frame, &config, &mut result) {
+        result.reason = Some(reason);
+    }
+    Ok(result)
+}
+
+fn check_frame(
+    frame: &[u8],
+    protocol: &ProtocolConfig,
+    result: &mut FrameValidation,
+) -> Result<(), String> {
+    let preamble = usize::from(protocol.preamble_count);
+    for offset in 0..preamble {
+        match frame.get(offset) {
+            Some(&byte) if byte == protocol.sync_byte => {}
+            Some(&byte) => {
+                return Err(format!(
+                    "expected sync byte 0x{:02X} at offset {offset}, got 0x{byte:02X}",
+                    protocol.sync_byte
+                ))
+            }
+            None => return Err(format!("frame ends inside the {preamble}-byte preamble")),
+        }
+    }
+    result.sync_ok = true;
+
+    let len_byte = *frame.get(preamble).ok_or("frame ends before LEN")?;
+    let len = usize::from(len_byte);
+    let body_len = protocol.body_len(len).ok_or_else(|| {
+        format!(
+            "LEN {len} is outside {}..={} for {:?}",
+            protocol
+                .length_convention
+                .len_field(protocol.min_body_len()),
+            protocol.max_read_frame_len,
+            protocol.length_convention
+        )
+    })?;
+    result.length_ok = true;
+
+    let section_start = preamble + 1;
+    let section_end = section_start + body_len + 1;
+    let expected_len = section_end + usize::from(protocol.footer.is_some());
+    if frame.len() != expected_len {
+        return Err(format!(
+            "LEN {len} implies a {expected_len}-byte frame, got {} bytes",
+            frame.len()
+        ));
+    }
+    result.boundary_ok = true;
+
+    let (body, crc) = protocol
+        .crc_position
+        .split(&frame[section_start..section_end]);
+    let calc = protocol
+        .checksum()
+        .compute(protocol.sync_byte, len_byte, body);
+    if crc != calc {
+        return Err(format!(
+            "CRC 0x{crc:02X} does not match computed 0x{calc:02X}"
+        ));
+    }
+    result.crc_ok = true;
+
+    if let Some(footer) = protocol.footer {
+        let byte = frame[section_end];
+        if byte != footer {
+            return Err(format!(
+                "expected frame footer 0x{footer:02X}, got 0x{byte:02X}"
+            ));
+        }
+    }
+    result.footer_ok = true;
+
+    if let Some(spec) = &protocol.validate_inner_length {
+        spec.check(body, protocol.endianness)?;
+    }
+    result.inner_length_ok = true;
+    result.valid = true;
+    result.payload = Some(body.to_vec());
+    Ok(())
+}
+
+/// Consumes the trailer after the CRC so it is not mistaken for the start of
+/// the next frame.
+fn read_footer(reader: &mut PolledReader, footer: u8) -> Result<(), SerialError> {
+    match reader.byte() {
+        Ok(byte) if byte == footer => Ok(()),
+        Ok(byte) => Err(SerialError::InvalidResponse {
+            message: format!("expected frame footer 0x{footer:02X}, got 0x{byte:02X}"),
+        }),
+        Err(SerialError::Timeout) => Err(SerialError::InvalidResponse {
+            message: format!("missing frame footer 0x{footer:02X}"),
+        }),
+        Err(err) => Err(err),
+    }
+}
+
+/// Reads against a deadline, sleeping `poll_interval` whenever the port has
+/// nothing to give (a zero-byte read or `WouldBlock`) instead of spinning.
+struct PolledReader<'a> {
+    port: &'a mut dyn SerialPort,
+    deadline: Instant,
+    poll_interval: Duration,
+    /// Bytes already taken from the port that must be read again first.
+    replay: VecDeque<u8>,
+}
+
+impl PolledReader<'_> {
+    /// Consumes `prefix` if the input starts with it. Otherwise the bytes
+    /// read up to the first mismatch are replayed to the frame parser.
+    fn strip_prefix(&mut self, prefix: &[u8]) -> Result<(), SerialError> {
+        for (i, &expected) in prefix.iter().enumerate() {
+            let byte = self.byte()?;
+            if byte != expected {
+                self.replay.extend(&prefix[..i]);
+                self.replay.push_back(byte);
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    fn byte(&mut self) -> Result<u8, SerialError> {
+        let mut byte = [0u8; 1];
+        self.exact(&mut byte)?;
+        Ok(byte[0])
+    }
+
+    fn exact(&mut self, buf: &mut [u8]) -> Result<(), SerialError> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            if let Some(byte) = self.replay.pop_front() {
+                buf[filled] = byte;
+                filled += 1;
+                continue;
+            }
+            match self.port.read(&mut buf[filled..]) {
+                Ok(0) => {}
+                Ok(n) => {
+                    filled += n;
+                    continue;
+                }
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+                Err(err) => return Err(err.into()),
+            }
+            if Instant::now() >= self.deadline {
+                return Err(SerialError::Timeout);
+            }
+            std::thread::sleep(self.poll_interval);
+        }
+        Ok(())
+    }
+
+    /// `exact` against a fresh `timeout`, which also bounds the rest of the
+    /// frame (the footer included). The port's own read timeout is shortened
+    /// to match for the duration, so a blocking read cannot outlast it.
+    fn assemble(&mut self, buf: &mut [u8], timeout: Duration) -> Result<(), SerialError> {
+        self.deadline = Instant::now() + timeout;
+        let original = self.port.timeout();
+        let shortened = timeout < original && self.port.set_timeout(timeout).is_ok();
+        let result = self.exact(buf);
+        if shortened {
+            self.port.set_timeout(original)?;
+        }
+        match result {
+            Err(SerialError::Timeout) => Err(SerialError::IoError {
+                message: "incomplete frame".to_string(),
+            }),
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crc::crc8;
+    use crate::mock_port::MockPort;
+
+    #[test]
+    fn build_frame_structure() {
+        let payload = vec![0x01, 0x02, 0x03];
+        let frame = build_frame(&payload).unwrap();
+
+        assert_eq!(frame[0], VSCOPE_SYNC_BYTE);
+        // len field = payload_len + 1 (for crc) = 4
+        assert_eq!(frame[1], 0x04);
+        // payload
+        assert_eq!(&frame[2..5], &payload);
+        // crc
+        assert_eq!(frame[5], crc8(&payload));
+    }
+
+    #[test]
+    fn build_frame_min_payload() {
+        let payload = vec![0x42]; // single byte (message type)
+        let frame = build_frame(&payload).unwrap();
+
+        assert_eq!(frame.len(), 1 + 1 + 1 + 1); // sync + len(1) + payload + crc
+        assert_eq!(frame[0], VSCOPE_SYNC_BYTE);
+        assert_eq!(frame[1], 2); // payload + crc
+    }
+
+    #[test]
+    fn build_frame_larger_payload() {
+        let payload = vec![0xAA; 253];
+        let frame = build_frame(&payload).unwrap();
+
+        assert_eq!(frame.len(), 1 + 1 + 253 + 1);
+        let len_field = frame[1];
+        assert_eq!(len_field, 254); // 253 + 1 for crc
+    }
+
+    #[test]
+    fn build_frame_too_large() {
+        let payload = vec![0xAA; 254];
+        assert!(build_frame(&payload).is_err());
+    }
+
+    #[test]
+    fn read_frame_accepts_valid_frame() {
+        let mut port = MockPort::with_rx(&build_frame(&[0x01, 0x02]).unwrap());
+        let payload = read_frame(&mut port, &ProtocolConfig::default()).unwrap();
+        assert_eq!(payload, vec![0x01, 0x02]);
+    }
+
+    #[test]
+    fn validate_frame_accepts_valid_frame() {
+        let frame = build_frame(&[0x01, 0x02]).unwrap();
+        let result = validate_frame(frame, ProtocolConfig::default()).unwrap();
+        assert!(result.valid && result.crc_ok && result.footer_ok);
+        assert_eq!(result.payload, Some(vec![0x01, 0x02]));
+        assert_eq!(result.reason, None);
+    }
+
+    #[test]
+    fn validate_frame_reports_bad_crc() {
+        let mut frame = build_frame(&[0x01, 0x02]).unwrap();
+        *frame.last_mut().unwrap() ^= 0xFF;
+        let result = validate_frame(frame, ProtocolConfig::default()).unwrap();
+        assert!(!result.valid);
+        assert!(result.boundary_ok && !result.crc_ok);
+        assert!(result.reason.unwrap().starts_with("CRC"));
+    }
+
+    #[test]
+    fn validate_frame_reports_bad_length() {
+        let mut frame = build_frame(&[0x01, 0x02]).unwrap();
+        frame[1] += 1;
+        let result = validate_frame(frame.clone(), ProtocolConfig::default()).unwrap();
+        assert!(result.length_ok && !result.boundary_ok);
+        assert!(result.reason.unwrap().contains("implies"));
+
+        frame[1] = 0;
+        let result = validate_frame(frame, ProtocolConfig::default()).unwrap();
+        assert!(result.sync_ok && !result.length_ok);
+    }
+
+    #[test]
+    fn validate_frame_reports_wrong_sync() {
+        let mut frame = build_frame(&[0x01, 0x02]).unwrap();
+        frame[0] = 0xAA;
+        let result = validate_frame(frame, ProtocolConfig::default()).unwrap();
+        assert_eq!(
+            result,
+            FrameValidation {
+                reason: Some("expected sync byte 0xC8 at offset 0, got 0xAA".to_string()),
+                ..FrameValidation::default()
+            }
+        );
+    }
+
+    #[test]
+    fn leading_skip_max_bounds_bytes_before_sync() {
+        let frame = build_frame(&[0x01, 0x02]).unwrap();
+        let one_spurious = [&[0x00][..], &frame].concat();
+        let mut port = MockPort::with_rx(&one_spurious);
+        assert_eq!(
+            read_frame_bounded(&mut port, &ProtocolConfig::default(), Some(1)).unwrap(),
+            vec![0x01, 0x02]
+        );
+
+        let noisy = [&[0x00, 0x11, 0x22][..], &frame].concat();
+        let mut port = MockPort::with_rx(&noisy);
+        assert!(matches!(
+            read_frame_bounded(&mut port, &ProtocolConfig::default(), Some(1)),
+            Err(SerialError::InvalidResponse { .. })
+        ));
+
+        let mut port = MockPort::with_rx(&noisy);
+        assert_eq!(
+            read_frame(&mut port, &ProtocolConfig::default()).unwrap(),
+            vec![0x01, 0x02]
+        );
+    }
+
+    fn with_inner_length(includes_header: bool) -> ProtocolConfig {
+        ProtocolConfig {
+            validate_inner_length: Some(InnerLengthSpec {
+                offset: 1,
+                width: 1,
+                includes_header,
+            }),
+            ..ProtocolConfig::default()
+        }
+    }
+
+    #[test]
+    fn matching_inner_length_is_accepted() {
+        let protocol = with_inner_length(false);
+        let frame = build_frame(&[0x05, 0x02, 0xAA, 0xBB]).unwrap();
+        let mut port = MockPort::with_rx(&frame);
+        assert_eq!(
+            read_frame(&mut port, &protocol).unwrap(),
+            vec![0x05, 0x02, 0xAA, 0xBB]
+        );
+        assert!(validate_frame(frame, protocol).unwrap().inner_length_ok);
+
+        let protocol = with_inner_length(true);
+        let mut port = MockPort::with_rx(&build_frame(&[0x05, 0x03, 0xAA]).unwrap());
+        assert!(read_frame(&mut port, &protocol).is_ok());
+
+        // Device error replies have no inner length to check.
+        let mut port = MockPort::with_rx(&build_frame(&[MSG_ERROR, 0x01]).unwrap());
+        assert!(read_frame(&mut port, &protocol).is_ok());
+    }
+
+    #[test]
+    fn mismatching_inner_length_is_rejected() {
+        let protocol = with_inner_length(false);
+        // Claims three bytes follow, but the device cut the reply at two.
+        let frame = build_frame(&[0x05, 0x03, 0xAA, 0xBB]).unwrap();
+        let mut port = MockPort::with_rx(&frame);
+        assert!(matches!(
+            read_frame(&mut port, &protocol),
+            Err(SerialError::InvalidResponse { .. })
+        ));
+        let result = validate_frame(frame, protocol.clone()).unwrap();
+        assert!(result.footer_ok && !result.inner_length_ok && !result.valid);
+
+        let mut port = MockPort::with_rx(&build_frame(&[0x05]).unwrap());
+        assert!(matches!(
+            read_frame(&mut port, &protocol),
+            Err(SerialError::InvalidResponse { .. })
+        ));
+    }
+
+    #[test]
+    fn stall_after_header_is_an_incomplete_frame() {
+        let protocol = ProtocolConfig {
+            frame_assembly_timeout_ms: Some(10),
+            ..ProtocolConfig::default()
+        };
+        let frame = build_frame(&[0x01, 0x02, 0x03]).unwrap();
+        let mut port = MockPort::with_rx(&frame[..3]);
+        port.set_timeout(Duration::from_secs(1)).unwrap();
+
+        let started = Instant::now();
+        let err = read_frame(&mut port, &protocol).unwrap_err();
+        assert!(
+            matches!(&err, SerialError::IoError { message } if message == "incomplete frame"),
+            "{err:?}"
+        );
+        assert!(started.elapsed() < Duration::from_millis(500));
+        assert_eq!(port.timeout(), Duration::from_secs(1));
+
+        let mut port = MockPort::with_rx(&frame);
+        assert_eq!(
+            read_frame(&mut port, &protocol).unwrap(),
+            vec![0x01, 0x02, 0x03]
+        );
+        // Without the setting a stall is an ordinary timeout.
+        let mut port = MockPort::with_rx(&frame[..3]);
+        assert!(matches!(
+            read_frame(&mut port, &ProtocolConfig::default()),
+            Err(SerialError::Timeout)
+        ));
+    }
+
+    #[test]
+    fn read_frame_resyncs_past_len_above_read_limit() {
+        // Declared LEN of 80 is legal for the protocol but above the configured
+        // limit, so the reader should hunt for the next sync byte instead.
+        let mut bytes = vec![VSCOPE_SYNC_BYTE, 80, 0x11, 0x22];
+        bytes.extend(build_frame(&[0x07]).unwrap());
+        let protocol = ProtocolConfig {
+            max_read_frame_len: 64,
+            ..ProtocolConfig::default()
+        };
+
+        let mut port = MockPort::with_rx(&bytes);
+        assert_eq!(read_frame(&mut port, &protocol).unwrap(), vec![0x07]);
+
+        // With the default limit the same stream is swallowed as an 80-byte frame.
+        let mut port = MockPort::with_rx(&bytes);
+        assert!(matches!(
+            read_frame(&mut port, &ProtocolConfig::default()),
+            Err(SerialError::Timeout)
+        ));
+    }
+
+    #[test]
+    fn protocol_config_rejects_read_limit_above_protocol_max() {
+        let protocol = ProtocolConfig {
+            max_read_frame_len: MAX_FRAME_LEN + 1,
+            ..ProtocolConfig::default()
+        };
+        assert!(protocol.validate().is_err());
+        assert!(ProtocolConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn round_trip_with_non_default_framing() {
+        let protocol = ProtocolConfig {
+            sync_byte: 0xAA,
+            crc_variant: CrcVariant::Crc8Smbus,
+            crc_coverage: CrcCoverage::LenAndPayload,
+            crc_init: 0xFF,
+            length_convention: LengthConvention::Body,
+            ..ProtocolConfig::default()
+        };
+        let frame = build_frame_with(&[0x05, 0x10, 0x20], &protocol).unwrap();
+        assert_eq!(frame[0], 0xAA);
+        assert_eq!(frame[1], 3);
+
+        let mut port = MockPort::with_rx(&frame);
+        assert_eq!(
+            read_frame(&mut port, &protocol).unwrap(),
+            vec![0x05, 0x10, 0x20]
+        );
+
+        // The default reader neither recognises the sync byte nor the trailer.
+        let mut port = MockPort::with_rx(&frame);
+        assert!(read_frame(&mut port, &ProtocolConfig::default()).is_err());
+    }
+
+    #[test]
+    fn build_frame_honours_max_payload_len() {
+        let protocol = ProtocolConfig {
+            max_payload_len: 4,
+            ..ProtocolConfig::default()
+        };
+        assert!(build_frame_with(&[0x01; 5], &protocol).is_ok());
+        assert!(matches!(
+            build_frame_with(&[0x01; 6], &protocol),
+            Err(SerialError::PayloadTooLarge)
+        ));
+    }
+
+    #[test]
+    fn prefix_crc_round_trips() {
+        let protocol = ProtocolConfig {
+            crc_position: CrcPosition::Prefix,
+            ..ProtocolConfig::default()
+        };
+        let payload = [0x01, 0x10, 0x20, 0x30];
+        let frame = build_frame_with(&payload, &protocol).unwrap();
+        let suffix = build_frame(&payload).unwrap();
+        // Same bytes, with the CRC moved up behind LEN.
+        assert_eq!(frame[..2], suffix[..2]);
+        assert_eq!(frame[2], *suffix.last().unwrap());
+        assert_eq!(frame[3..], suffix[2..suffix.len() - 1]);
+
+        let mut bytes = frame.clone();
+        bytes.extend(build_frame_with(&[0x02], &protocol).unwrap());
+        let mut port = MockPort::with_rx(&bytes);
+        assert_eq!(read_frame(&mut port, &protocol).unwrap(), payload.to_vec());
+        assert_eq!(read_frame(&mut port, &protocol).unwrap(), vec![0x02]);
+
+        let result = validate_frame(frame.clone(), protocol.clone()).unwrap();
+        assert_eq!(result.payload, Some(payload.to_vec()));
+
+        let mut corrupt = frame;
+        corrupt[4] ^= 0x01;
+        let mut port = MockPort::with_rx(&corrupt);
+        assert!(matches!(
+            read_frame(&mut port, &protocol),
+            Err(SerialError::CrcMismatch)
+        ));
+    }
+
+    #[test]
+    fn empty_payload_round_trips_when_allowed() {
+        let protocol = ProtocolConfig {
+            allow_empty_payload: true,
+            ..ProtocolConfig::default()
+        };
+        let frame = build_frame_with(&[], &protocol).unwrap();
+        assert_eq!(frame, vec![VSCOPE_SYNC_BYTE, 1, crc8(&[])]);
+
+        let mut bytes = frame.clone();
+        bytes.extend(build_frame_with(&[0x04], &protocol).unwrap());
+        let mut port = MockPort::with_rx(&bytes);
+        assert_eq!(read_frame(&mut port, &protocol).unwrap(), Vec::<u8>::new());
+        assert_eq!(read_frame(&mut port, &protocol).unwrap(), vec![0x04]);
+        assert!(validate_frame(frame.clone(), protocol).unwrap().valid);
+
+        // By default LEN 1 is still treated as corruption.
+        let result = validate_frame(frame, ProtocolConfig::default()).unwrap();
+        assert!(result.sync_ok && !result.length_ok);
+    }
+
+    fn with_preamble(preamble_count: u8) -> ProtocolConfig {
+        ProtocolConfig {
+            preamble_count,
+            ..ProtocolConfig::default()
+        }
+    }
+
+    #[test]
+    fn preamble_round_trips() {
+        for count in [1, 3, 8] {
+            let protocol = with_preamble(count);
+            let frame = build_frame_with(&[0x02, 0x11, 0x22], &protocol).unwrap();
+            let preamble = usize::from(count);
+            assert!(frame[..preamble].iter().all(|&b| b == VSCOPE_SYNC_BYTE));
+            assert_eq!(frame[preamble], 0x04);
+
+            let mut port = MockPort::with_rx(&frame);
+            assert_eq!(
+                read_frame(&mut port, &protocol).unwrap(),
+                vec![0x02, 0x11, 0x22],
+                "preamble {count}"
+            );
+        }
+    }
+
+    #[test]
+    fn preamble_tolerates_dropped_leading_sync_bytes() {
+        let frame = build_frame_with(&[0x04], &with_preamble(8)).unwrap();
+        let mut port = MockPort::with_rx(&frame[5..]);
+        assert_eq!(
+            read_frame(&mut port, &with_preamble(8)).unwrap(),
+            vec![0x04]
+        );
+    }
+
+    #[test]
+    fn preamble_handles_len_equal_to_sync_byte() {
+        // 199 body bytes + CRC gives LEN 0xC8, indistinguishable from a sync byte.
+        let mut body = vec![0x09];
+        body.extend([0x5A; 198]);
+        for count in [1, 3] {
+            let protocol = with_preamble(count);
+            let frame = build_frame_with(&body, &protocol).unwrap();
+            assert_eq!(frame[usize::from(count)], VSCOPE_SYNC_BYTE);
+            let mut port = MockPort::with_rx(&frame);
+            assert_eq!(read_frame(&mut port, &protocol).unwrap(), body);
+        }
+    }
+
+    #[test]
+    fn protocol_config_rejects_zero_preamble() {
+        assert!(with_preamble(0).validate().is_err());
+    }
+
+    fn with_footer(footer: Option<u8>) -> ProtocolConfig {
+        ProtocolConfig {
+            footer,
+            ..ProtocolConfig::default()
+        }
+    }
+
+    fn with_skip_prefix() -> ProtocolConfig {
+        ProtocolConfig {
+            response_skip_prefix: vec![VSCOPE_SYNC_BYTE, 0x01],
+            ..ProtocolConfig::default()
+        }
+    }
+
+    #[test]
+    fn response_skip_prefix_is_discarded_before_each_frame() {
+        let protocol = with_skip_prefix();
+        let mut bytes = vec![VSCOPE_SYNC_BYTE, 0x01];
+        bytes.extend(build_frame(&[0x01, 0x33]).unwrap());
+        bytes.extend([VSCOPE_SYNC_BYTE, 0x01]);
+        bytes.extend(build_frame(&[0x02]).unwrap());
+        let mut port = MockPort::with_rx(&bytes);
+        assert_eq!(read_frame(&mut port, &protocol).unwrap(), vec![0x01, 0x33]);
+        assert_eq!(read_frame(&mut port, &protocol).unwrap(), vec![0x02]);
+    }
+
+    #[test]
+    fn missing_skip_prefix_parses_as_usual() {
+        // The frame's own sync byte matches the prefix's first byte, so the
+        // reader has to hand both bytes back to the parser.
+        let frame = build_frame(&[0x01, 0x33]).unwrap();
+        let mut port = MockPort::with_rx(&frame);
+        assert_eq!(
+            read_frame(&mut port, &with_skip_prefix()).unwrap(),
+            vec![0x01, 0x33]
+        );
+    }
+
+    #[test]
+    fn footer_round_trips_and_is_consumed() {
+        let protocol = with_footer(Some(0x0A));
+        let first = build_frame_with(&[0x01, 0x33], &protocol).unwrap();
+        assert_eq!(first.last(), Some(&0x0A));
+        assert_eq!(first.len(), build_frame(&[0x01, 0x33]).unwrap().len() + 1);
+
+        let mut bytes = first;
+        bytes.extend(build_frame_with(&[0x02], &protocol).unwrap());
+        let mut port = MockPort::with_rx(&bytes);
+        assert_eq!(read_frame(&mut port, &protocol).unwrap(), vec![0x01, 0x33]);
+        assert_eq!(read_frame(&mut port, &protocol).unwrap(), vec![0x02]);
+    }
+
+    #[test]
+    fn no_footer_keeps_default_framing() {
+        let frame = build_frame_with(&[0x01, 0x33], &with_footer(None)).unwrap();
+        assert_eq!(frame, build_frame(&[0x01, 0x33]).unwrap());
+        let mut port = MockPort::with_rx(&frame);
+        assert_eq!(
+            read_frame(&mut port, &with_footer(None)).unwrap(),
+            vec![0x01, 0x33]
+        );
+    }
+
+    #[test]
+    fn missing_footer_is_reported() {
+        let protocol = with_footer(Some(0x0A));
+        let frame = build_frame(&[0x01]).unwrap();
+
+        let mut port = MockPort::with_rx(&frame);
+        assert!(matches!(
+            read_frame(&mut port, &protocol),
+            Err(SerialError::InvalidResponse { .. })
+        ));
+
+        let mut bytes = frame.clone();
+        bytes.push(0x0D);
+        let mut port = MockPort::with_rx(&bytes);
+        assert!(matches!(
+            read_frame(&mut port, &protocol),
+            Err(SerialError::InvalidResponse { .. })
+        ));
+    }
+
+    #[test]
+    fn poll_interval_limits_spinning_on_trickling_nonblocking_port() {
+        let frame = build_frame(&[0x03, 0x01, 0x02]).unwrap();
+        let trickle = |port: &MockPort| {
+            for (i, byte) in frame.iter().enumerate() {
+                port.schedule_rx(Duration::from_millis(4 * (i as u64 + 1)), &[*byte]);
+            }
+        };
+
+        let mut port = MockPort::new();
+        port.state.lock().unwrap().nonblocking = true;
+        trickle(&port);
+        assert_eq!(
+            read_frame(&mut port, &ProtocolConfig::default()).unwrap(),
+            vec![0x03, 0x01, 0x02]
+        );
+        let polled_reads = port.state.lock().unwrap().read_calls;
+
+        let mut port = MockPort::new();
+        port.state.lock().unwrap().nonblocking = true;
+        trickle(&port);
+        let spinning = ProtocolConfig {
+            read_poll_interval_us: 0,
+            ..ProtocolConfig::default()
+        };
+        assert_eq!(
+            read_frame(&mut port, &spinning).unwrap(),
+            vec![0x03, 0x01, 0x02]
+        );
+        let spinning_reads = port.state.lock().unwrap().read_calls;
+
+        // ~24 ms of trickle at a 0.5 ms poll is on the order of 50 reads.
+        assert!(polled_reads < 200, "{polled_reads} reads");
+        assert!(
+            spinning_reads > polled_reads * 5,
+            "{spinning_reads} vs {polled_reads}"
+        );
+    }
+}
@@ -0,0 +1,270 @@
+//! Identify handshake used to confirm which device is on the other end.
+//!
+//! IDENTIFY is a host-side extension to the VScope message set; the response
+//! layout below is the contract firmware is expected to implement.
+
+use crate::error::SerialError;
+use crate::frame::MSG_ERROR;
+use crate::serial::{exchange, registry, with_entry};
+use serde::{Deserialize, Serialize};
+
+pub const MSG_IDENTIFY: u8 = 0x20;
+
+// Response data offsets (after the TYPE byte).
+const PROTOCOL_VERSION_OFFSET: usize = 0;
+const FIRMWARE_VERSION_OFFSET: usize = 1;
+const FIRMWARE_VERSION_LEN: usize = 3;
+const HARDWARE_REVISION_OFFSET: usize = 4;
+/// Build date as ASCII `YYYY-MM-DD`.
+const BUILD_DATE_OFFSET: usize = 5;
+const BUILD_DATE_LEN: usize = 10;
+const SERIAL_LEN_OFFSET: usize = 15;
+const SERIAL_OFFSET: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceFingerprint {
+    pub firmware_version: String,
+    pub protocol_version: u8,
+    pub serial: String,
+}
+
+/// Everything the IDENTIFY response carries, for display.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceIdentity {
+    pub protocol_version: u8,
+    pub firmware_version: String,
+    pub hardware_revision: u8,
+    pub build_date: String,
+    pub serial: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyResult {
+    pub matches: bool,
+    /// Names of the fingerprint fields that differed, in declaration order.
+    pub mismatched_fields: Vec<String>,
+    pub actual: DeviceFingerprint,
+    pub closed: bool,
+}
+
+fn invalid_response(message: impl Into<String>) -> SerialError {
+    SerialError::InvalidResponse {
+        message: message.into(),
+    }
+}
+
+/// Strips and checks the TYPE byte of an IDENTIFY response.
+fn identify_data(response: &[u8]) -> Result<&[u8], SerialError> {
+    match response.split_first() {
+        Some((&MSG_IDENTIFY, data)) => Ok(data),
+        Some((&MSG_ERROR, data)) => Err(invalid_response(format!(
+            "device rejected identify (error code {:?})",
+            data.first()
+        ))),
+        Some((other, _)) => Err(invalid_response(format!(
+            "expected identify response, got type 0x{other:02X}"
+        ))),
+        None => Err(invalid_response("empty identify response")),
+    }
+}
+
+fn ascii_field<'a>(data: &'a [u8], name: &str) -> Result<&'a str, SerialError> {
+    std::str::from_utf8(data).map_err(|_| invalid_response(format!("identify {name} is not utf-8")))
+}
+
+fn parse_identity(response: &[u8]) -> Result<DeviceIdentity, SerialError> {
+    let data = identify_data(response)?;
+    if data.len() <= SERIAL_LEN_OFFSET {
+        return Err(invalid_response("identify response too short"));
+    }
+    let serial_len = data[SERIAL_LEN_OFFSET] as usize;
+    let serial = data
+        .get(SERIAL_OFFSET..SERIAL_OFFSET + serial_len)
+        .ok_or_else(|| invalid_response("identify serial truncated"))?;
+
+    let version = &data[FIRMWARE_VERSION_OFFSET..FIRMWARE_VERSION_OFFSET + FIRMWARE_VERSION_LEN];
+    let build_date = &data[BUILD_DATE_OFFSET..BUILD_DATE_OFFSET + BUILD_DATE_LEN];
+    Ok(DeviceIdentity {
+        protocol_version: data[PROTOCOL_VERSION_OFFSET],
+        firmware_version: format!("{}.{}.{}", version[0], version[1], version[2]),
+        hardware_revision: data[HARDWARE_REVISION_OFFSET],
+        build_date: ascii_field(build_date, "build date")?.to_string(),
+        serial: ascii_field(serial, "serial")?.to_string(),
+    })
+}
+
+fn parse_fingerprint(response: &[u8]) -> Result<DeviceFingerprint, SerialError> {
+    let identity = parse_identity(response)?;
+    Ok(DeviceFingerprint {
+        firmware_version: identity.firmware_version,
+        protocol_version: identity.protocol_version,
+        serial: identity.serial,
+    })
+}
+
+fn mismatched_fields(expected: &DeviceFingerprint, actual: &DeviceFingerprint) -> Vec<String> {
+    let mut fields = Vec::new();
+    if expected.firmware_version != actual.firmware_version {
+        fields.push("firmwareVersion".to_string());
+    }
+    if expected.protocol_version != actual.protocol_version {
+        fields.push("protocolVersion".to_string());
+    }
+    if expected.serial != actual.serial {
+        fields.push("serial".to_string());
+    }
+    fields
+}
+
+#[tauri::command]
+pub fn device_identity(handle_id: u64) -> Result<DeviceIdentity, SerialError> {
+    with_entry(handle_id, "device_identity", |entry| {
+        let response = exchange(entry, &[MSG_IDENTIFY])?;
+        parse_identity(&response)
+    })
+}
+
+/// Runs the identify exchange and compares the answer against `expected`.
+/// With `close_on_mismatch` the handle is dropped when any field differs, so
+/// nothing further can be sent to an unapproved device.
+#[tauri::command]
+pub fn verify_device(
+    handle_id: u64,
+    expected: DeviceFingerprint,
+    close_on_mismatch: bool,
+) -> Result<VerifyResult, SerialError> {
+    let actual = with_entry(handle_id, "verify_device", |entry| {
+        let response = exchange(entry, &[MSG_IDENTIFY])?;
+        parse_fingerprint(&response)
+    })?;
+
+    let mismatched_fields = mismatched_fields(&expected, &actual);
+    let matches = mismatched_fields.is_empty();
+    let closed = !matches && close_on_mismatch;
+    if closed {
+        let _ = registry().remove(handle_id);
+    }
+
+    Ok(VerifyResult {
+        matches,
+        mismatched_fields,
+        actual,
+        closed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::build_frame;
+    use crate::mock_port::MockPort;
+    use crate::serial::insert_mock;
+
+    fn identify_payload(protocol: u8, version: [u8; 3], serial: &str) -> Vec<u8> {
+        let mut payload = vec![MSG_IDENTIFY, protocol];
+        payload.extend_from_slice(&version);
+        payload.push(2); // hardware revision
+        payload.extend_from_slice(b"2026-03-01");
+        payload.push(serial.len() as u8);
+        payload.extend_from_slice(serial.as_bytes());
+        payload
+    }
+
+    fn approved() -> DeviceFingerprint {
+        DeviceFingerprint {
+            firmware_version: "1.4.2".to_string(),
+            protocol_version: 3,
+            serial: "VS-0042".to_string(),
+        }
+    }
+
+    fn mock_device() -> (MockPort, u64) {
+        let port = MockPort::new();
+        let reply = identify_payload(3, [1, 4, 2], "VS-0042");
+        port.queue_reply(&build_frame(&reply).unwrap());
+        let id = insert_mock(&port);
+        (port, id)
+    }
+
+    #[test]
+    fn verify_device_accepts_matching_fingerprint() {
+        let (port, id) = mock_device();
+        let result = verify_device(id, approved(), true).unwrap();
+        assert!(result.matches);
+        assert!(result.mismatched_fields.is_empty());
+        assert!(!result.closed);
+        assert_eq!(port.written(), build_frame(&[MSG_IDENTIFY]).unwrap());
+        assert!(registry().get(id).is_some());
+    }
+
+    #[test]
+    fn verify_device_reports_mismatches_and_closes() {
+        let (_port, id) = mock_device();
+        let expected = DeviceFingerprint {
+            firmware_version: "1.5.0".to_string(),
+            serial: "VS-0001".to_string(),
+            ..approved()
+        };
+        let result = verify_device(id, expected, true).unwrap();
+        assert!(!result.matches);
+        assert_eq!(result.mismatched_fields, vec!["firmwareVersion", "serial"]);
+        assert!(result.closed);
+        assert!(registry().get(id).is_none());
+    }
+
+    #[test]
+    fn verify_device_keeps_handle_when_not_asked_to_close() {
+        let (_port, id) = mock_device();
+        let expected = DeviceFingerprint {
+            protocol_version: 2,
+            ..approved()
+        };
+        let result = verify_device(id, expected, false).unwrap();
+        assert_eq!(result.mismatched_fields, vec!["protocolVersion"]);
+        assert!(!result.closed);
+        assert!(registry().get(id).is_some());
+    }
+
+    #[test]
+    fn parse_fingerprint_rejects_truncated_serial() {
+        let mut payload = identify_payload(3, [1, 4, 2], "VS-0042");
+        payload.truncate(payload.len() - 2);
+        assert!(matches!(
+            parse_fingerprint(&payload),
+            Err(SerialError::InvalidResponse { .. })
+        ));
+    }
+
+    #[test]
+    fn device_identity_decodes_all_fields() {
+        let (_port, id) = mock_device();
+        assert_eq!(
+            device_identity(id).unwrap(),
+            DeviceIdentity {
+                protocol_version: 3,
+                firmware_version: "1.4.2".to_string(),
+                hardware_revision: 2,
+                build_date: "2026-03-01".to_string(),
+                serial: "VS-0042".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_identity_rejects_short_or_garbled_response() {
+        let payload = identify_payload(3, [1, 4, 2], "VS-0042");
+        assert!(matches!(
+            parse_identity(&payload[..10]),
+            Err(SerialError::InvalidResponse { .. })
+        ));
+        let mut garbled = payload.clone();
+        garbled[1 + BUILD_DATE_OFFSET] = 0xFF;
+        assert!(matches!(
+            parse_identity(&garbled),
+            Err(SerialError::InvalidResponse { .. })
+        ));
+    }
+}
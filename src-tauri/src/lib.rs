@@ -10,16 +10,123 @@ pub fn run() {
         .plugin(tauri_plugin_sql::Builder::default().build())
         .invoke_handler(tauri::generate_handler![
             crate::db::take_startup_notice,
+            crate::db::read_notice_log,
+            crate::db::reset_database,
+            crate::db::prepare_for_backup,
+            crate::db::db_busy_timeout,
+            crate::db::set_db_busy_timeout,
+            crate::db::list_embedded_migrations,
             crate::serial::list_ports,
+            crate::serial::list_ports_annotated,
             crate::serial::open_device,
+            crate::serial::open_device_retry,
+            crate::echo::open_echo_device,
             crate::serial::close_device,
+            crate::serial::handle_for_path,
             crate::serial::flush_device,
+            crate::serial::flush_if_stale,
+            crate::serial::line_status,
+            crate::serial::drain_until_quiet,
             crate::serial::send_request,
+            crate::serial::send_no_reply,
+            crate::serial::flush_output,
+            crate::serial::send_confirm_echo,
+            crate::serial::send_raw_frame,
+            crate::policy::set_message_policy,
+            crate::policy::set_response_policy,
+            crate::ratelimit::set_rate_limit,
+            crate::probe::find_responsive_port,
+            crate::serial::read_raw,
+            crate::console::read_hex,
+            crate::console::write_hex,
+            crate::console::read_burst,
+            crate::queue::send_request_async,
+            crate::transfer::send_chunked,
+            crate::transfer::send_file,
+            crate::sequence::run_sequence,
+            crate::stream::start_stream,
+            crate::stream::stop_stream,
+            crate::stream::set_stream_conflict,
+            crate::stream::set_unsolicited_capacity,
+            crate::stream::pending_frame_count,
+            crate::stream::drain_unsolicited,
+            crate::stream::stream_checksum,
+            crate::stream::stream_frame_stats,
+            crate::stream_file::start_stream_to_file,
+            crate::stream_file::stop_stream_to_file,
+            crate::serial::protocol_config,
+            crate::serial::set_protocol_config,
+            crate::serial::set_crc_variant,
+            crate::crc::benchmark_crc,
+            crate::serial::estimate_tx_time_ms,
+            crate::serial::identify_checksum,
+            crate::serial::crc_conformance,
+            crate::serial::last_frame,
+            crate::serial::last_tx,
+            crate::serial::send_request_timed,
+            crate::serial::set_handle_label,
+            crate::config_check::validate_config,
+            crate::diagnostics::diagnostics_report,
+            crate::disconnect::set_disconnect_error_kinds,
+            crate::drift::clock_drift,
+            crate::drift::sync_device_clock,
+            crate::frame::validate_frame,
+            crate::capture::parse_capture,
+            crate::capture::detect_framing,
+            crate::bootloader::enter_bootloader,
+            crate::fields::read_string_field,
+            crate::fields::decode_payload,
+            crate::identity::verify_device,
+            crate::identity::device_identity,
+            crate::registers::read_register,
+            crate::registers::write_verify_register,
+            crate::reopen::reopen_handle,
+            crate::snapshots::list_snapshots_page,
+            crate::snapshots::diff_snapshots,
+            crate::snapshots::prune_snapshots,
+            crate::disk_space::disk_space_available,
+            crate::disk_space::check_snapshot_space,
+            crate::nicknames::set_device_nickname,
+            crate::nicknames::get_device_nickname,
+            crate::port_history::record_port_snapshot,
+            crate::port_history::list_port_history,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
 
+mod access;
+mod bootloader;
+mod capture;
+mod config_check;
+mod console;
+mod crc;
 mod db;
+mod diagnostics;
+mod disconnect;
+mod disk_space;
+mod drift;
+mod echo;
 mod error;
+mod events;
+mod fields;
+mod frame;
+mod identity;
+mod line_errors;
+#[cfg(test)]
+mod mock_port;
+mod nicknames;
+mod policy;
+mod port_history;
+mod probe;
+mod queue;
+mod ratelimit;
+mod read_buffer;
+mod registers;
+mod reopen;
+mod sequence;
 mod serial;
+mod snapshots;
+mod stream;
+mod stream_file;
+mod transfer;
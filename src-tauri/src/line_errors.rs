@@ -0,0 +1,204 @@
+//! Driver line-error counters behind `line_status`.
+//!
+//! The counters live on the OS handle, which the boxed `SerialPort` in the
+//! registry does not expose, so `open_port_counted` keeps a native clone of
+//! the port (a duplicated descriptor or handle) just for reading them.
+//!
+//! On Linux `TIOCGICOUNT` returns running totals, and each read reports the
+//! change since the previous one. Drivers without the ioctl (ptys, some USB
+//! adapters) leave the handle unsupported. Windows only has `ClearCommError`
+//! flags, so each read counts at most one of each kind; `serialport` also
+//! calls `ClearCommError` for `bytes_to_read`, which clears flags before
+//! they can be read here, so Windows counts are a lower bound. Other
+//! platforms report unsupported.
+
+use crate::serial::LineStatus;
+use std::io;
+
+#[cfg(unix)]
+pub(crate) type NativePort = serialport::TTYPort;
+#[cfg(windows)]
+pub(crate) type NativePort = serialport::COMPort;
+
+pub(crate) struct LineErrors(platform::Counter);
+
+impl LineErrors {
+    /// `None` when the platform or driver cannot report line errors.
+    pub(crate) fn attach(port: &NativePort) -> Option<Self> {
+        platform::Counter::new(port).map(Self)
+    }
+
+    /// Errors counted since the previous call (or since `attach`).
+    pub(crate) fn take(&mut self) -> io::Result<LineStatus> {
+        self.0.take()
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod platform {
+    use super::NativePort;
+    use crate::serial::LineStatus;
+    use std::io;
+    use std::os::raw::c_int;
+    use std::os::unix::io::AsRawFd;
+
+    /// `struct serial_icounter_struct` from `<linux/serial.h>`.
+    #[repr(C)]
+    #[derive(Debug, Default, Clone, Copy)]
+    // Filled in whole by the kernel; only the error counts are read.
+    #[allow(dead_code)]
+    pub(super) struct Icounter {
+        pub(super) cts: c_int,
+        pub(super) dsr: c_int,
+        pub(super) rng: c_int,
+        pub(super) dcd: c_int,
+        pub(super) rx: c_int,
+        pub(super) tx: c_int,
+        pub(super) frame: c_int,
+        pub(super) overrun: c_int,
+        pub(super) parity: c_int,
+        pub(super) brk: c_int,
+        pub(super) buf_overrun: c_int,
+        pub(super) reserved: [c_int; 9],
+    }
+
+    pub(super) struct Counter {
+        port: NativePort,
+        last: Icounter,
+    }
+
+    impl Counter {
+        pub(super) fn new(port: &NativePort) -> Option<Self> {
+            let port = port.try_clone_native().ok()?;
+            let last = read(&port).ok()?;
+            Some(Self { port, last })
+        }
+
+        pub(super) fn take(&mut self) -> io::Result<LineStatus> {
+            let now = read(&self.port)?;
+            let status = since(&self.last, &now);
+            self.last = now;
+            Ok(status)
+        }
+    }
+
+    fn read(port: &NativePort) -> io::Result<Icounter> {
+        let mut counts = Icounter::default();
+        // SAFETY: the descriptor is open for the life of `port`, and
+        // TIOCGICOUNT fills exactly one `serial_icounter_struct`.
+        if unsafe { libc::ioctl(port.as_raw_fd(), libc::TIOCGICOUNT, &mut counts) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(counts)
+    }
+
+    /// The kernel counters are `int` and wrap, so differences wrap too.
+    pub(super) fn since(last: &Icounter, now: &Icounter) -> LineStatus {
+        let delta = |now: c_int, last: c_int| now.wrapping_sub(last) as u32;
+        LineStatus {
+            unsupported: false,
+            breaks: delta(now.brk, last.brk),
+            framing_errors: delta(now.frame, last.frame),
+            parity_errors: delta(now.parity, last.parity),
+            overruns: delta(now.overrun, last.overrun)
+                .wrapping_add(delta(now.buf_overrun, last.buf_overrun)),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::NativePort;
+    use crate::serial::LineStatus;
+    use std::io;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Devices::Communication::{
+        ClearCommError, CE_BREAK, CE_FRAME, CE_OVERRUN, CE_RXOVER, CE_RXPARITY,
+    };
+
+    pub(super) struct Counter {
+        port: NativePort,
+    }
+
+    impl Counter {
+        pub(super) fn new(port: &NativePort) -> Option<Self> {
+            let port = port.try_clone_native().ok()?;
+            Some(Self { port })
+        }
+
+        pub(super) fn take(&mut self) -> io::Result<LineStatus> {
+            let mut errors = 0;
+            // SAFETY: the handle is open for the life of `port`; the COMSTAT
+            // output is optional.
+            let ok = unsafe {
+                ClearCommError(
+                    self.port.as_raw_handle() as _,
+                    &mut errors,
+                    std::ptr::null_mut(),
+                )
+            };
+            if ok == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let flag = |mask| u32::from(errors & mask != 0);
+            Ok(LineStatus {
+                unsupported: false,
+                breaks: flag(CE_BREAK),
+                framing_errors: flag(CE_FRAME),
+                parity_errors: flag(CE_RXPARITY),
+                overruns: flag(CE_OVERRUN) + flag(CE_RXOVER),
+            })
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", windows)))]
+mod platform {
+    use super::NativePort;
+    use crate::serial::LineStatus;
+    use std::io;
+
+    pub(super) enum Counter {}
+
+    impl Counter {
+        pub(super) fn new(_port: &NativePort) -> Option<Self> {
+            None
+        }
+
+        pub(super) fn take(&mut self) -> io::Result<LineStatus> {
+            match *self {}
+        }
+    }
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "android")))]
+mod tests {
+    use super::platform::{since, Icounter};
+
+    #[test]
+    fn counts_are_differences_between_reads() {
+        let last = Icounter {
+            frame: 3,
+            parity: 1,
+            overrun: 2,
+            buf_overrun: 0,
+            brk: i32::MAX,
+            ..Icounter::default()
+        };
+        let now = Icounter {
+            frame: 5,
+            parity: 1,
+            overrun: 4,
+            buf_overrun: 1,
+            // The kernel counter wrapped past `int` max.
+            brk: i32::MIN,
+            ..Icounter::default()
+        };
+        let status = since(&last, &now);
+        assert!(!status.unsupported);
+        assert_eq!(status.framing_errors, 2);
+        assert_eq!(status.parity_errors, 0);
+        assert_eq!(status.overruns, 3);
+        assert_eq!(status.breaks, 1);
+    }
+}
@@ -0,0 +1,298 @@
+//! In-memory `SerialPort` used by the unit tests.
+//!
+//! The mock shares its state behind an `Arc<Mutex<_>>` so tests can keep a
+//! handle to it after the port itself has been boxed and moved into the
+//! registry.
+
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+pub struct MockState {
+    /// Bytes the "device" will hand back to reads, in order.
+    pub rx: VecDeque<u8>,
+    /// Bytes that only become readable once their instant has passed.
+    pub scheduled: VecDeque<(Instant, Vec<u8>)>,
+    /// Canned responses; each `flush` moves the next one into `rx`, which
+    /// mimics a device answering once the request has hit the wire.
+    pub replies: VecDeque<Vec<u8>>,
+    /// Every byte written by the host.
+    pub written: Vec<u8>,
+    pub write_calls: usize,
+    pub read_calls: usize,
+    pub flush_calls: usize,
+    pub clears: Vec<ClearBuffer>,
+    pub dtr: Option<bool>,
+    pub rts: Option<bool>,
+    /// Every successful control-line write, in order, as `("dtr", level)`
+    /// or `("rts", level)`.
+    pub line_changes: Vec<(&'static str, bool)>,
+    /// When set, control-line writes fail as if the driver rejected them.
+    pub fail_control_lines: bool,
+    /// When set, reads with no data fail with `WouldBlock` at once instead
+    /// of waiting out the timeout.
+    pub nonblocking: bool,
+    /// When set, reads and `bytes_to_read` fail with this kind, as when the
+    /// device has gone away.
+    pub read_error: Option<io::ErrorKind>,
+    /// Failures handed out by the next writes, one per call, before any
+    /// bytes are taken.
+    pub write_errors: VecDeque<io::ErrorKind>,
+}
+
+#[derive(Clone)]
+pub struct MockPort {
+    pub state: Arc<Mutex<MockState>>,
+    timeout: Duration,
+}
+
+impl MockPort {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(MockState::default())),
+            timeout: Duration::from_millis(50),
+        }
+    }
+
+    pub fn with_rx(bytes: &[u8]) -> Self {
+        let port = Self::new();
+        port.push_rx(bytes);
+        port
+    }
+
+    pub fn queue_reply(&self, bytes: &[u8]) {
+        self.state.lock().unwrap().replies.push_back(bytes.to_vec());
+    }
+
+    pub fn push_rx(&self, bytes: &[u8]) {
+        self.state.lock().unwrap().rx.extend(bytes.iter().copied());
+    }
+
+    /// Makes `bytes` readable `delay` from now. Calls must be in time order.
+    pub fn schedule_rx(&self, delay: Duration, bytes: &[u8]) {
+        self.state
+            .lock()
+            .unwrap()
+            .scheduled
+            .push_back((Instant::now() + delay, bytes.to_vec()));
+    }
+
+    pub fn written(&self) -> Vec<u8> {
+        self.state.lock().unwrap().written.clone()
+    }
+}
+
+fn control_line_error() -> serialport::Error {
+    serialport::Error::new(
+        serialport::ErrorKind::Io(io::ErrorKind::Other),
+        "mock control line failure",
+    )
+}
+
+impl MockState {
+    fn release_due(&mut self, now: Instant) {
+        while let Some((due, _)) = self.scheduled.front() {
+            if *due > now {
+                break;
+            }
+            let (_, bytes) = self.scheduled.pop_front().unwrap();
+            self.rx.extend(bytes);
+        }
+    }
+}
+
+impl Read for MockPort {
+    /// Behaves like a blocking port read: waits up to the timeout for data
+    /// (including scheduled bytes) and fails with `TimedOut` if none arrives.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let deadline = Instant::now() + self.timeout;
+        {
+            let mut state = self.state.lock().unwrap();
+            state.read_calls += 1;
+            if let Some(kind) = state.read_error {
+                return Err(io::Error::new(kind, "mock read failed"));
+            }
+        }
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+                state.release_due(now);
+                if !state.rx.is_empty() {
+                    let n = buf.len().min(state.rx.len());
+                    for (slot, byte) in buf.iter_mut().zip(state.rx.drain(..n)) {
+                        *slot = byte;
+                    }
+                    return Ok(n);
+                }
+                if state.nonblocking {
+                    return Err(io::Error::new(
+                        io::ErrorKind::WouldBlock,
+                        "mock read would block",
+                    ));
+                }
+                if now >= deadline {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "mock read timed out",
+                    ));
+                }
+                let next = state.scheduled.front().map_or(deadline, |(due, _)| *due);
+                next.min(deadline) - now
+            };
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+impl Write for MockPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        state.write_calls += 1;
+        if let Some(kind) = state.write_errors.pop_front() {
+            return Err(io::Error::new(kind, "mock write failed"));
+        }
+        state.written.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.flush_calls += 1;
+        if let Some(reply) = state.replies.pop_front() {
+            state.rx.extend(reply);
+        }
+        Ok(())
+    }
+}
+
+impl SerialPort for MockPort {
+    fn name(&self) -> Option<String> {
+        Some("mock".to_string())
+    }
+
+    fn baud_rate(&self) -> serialport::Result<u32> {
+        Ok(115_200)
+    }
+
+    fn data_bits(&self) -> serialport::Result<DataBits> {
+        Ok(DataBits::Eight)
+    }
+
+    fn flow_control(&self) -> serialport::Result<FlowControl> {
+        Ok(FlowControl::None)
+    }
+
+    fn parity(&self) -> serialport::Result<Parity> {
+        Ok(Parity::None)
+    }
+
+    fn stop_bits(&self) -> serialport::Result<StopBits> {
+        Ok(StopBits::One)
+    }
+
+    fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    fn set_baud_rate(&mut self, _baud_rate: u32) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn set_data_bits(&mut self, _data_bits: DataBits) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn set_flow_control(&mut self, _flow_control: FlowControl) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn set_parity(&mut self, _parity: Parity) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn set_stop_bits(&mut self, _stop_bits: StopBits) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> serialport::Result<()> {
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    fn write_request_to_send(&mut self, level: bool) -> serialport::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.fail_control_lines {
+            return Err(control_line_error());
+        }
+        state.rts = Some(level);
+        state.line_changes.push(("rts", level));
+        Ok(())
+    }
+
+    fn write_data_terminal_ready(&mut self, level: bool) -> serialport::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.fail_control_lines {
+            return Err(control_line_error());
+        }
+        state.dtr = Some(level);
+        state.line_changes.push(("dtr", level));
+        Ok(())
+    }
+
+    fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+
+    fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+
+    fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+
+    fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+
+    fn bytes_to_read(&self) -> serialport::Result<u32> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(kind) = state.read_error {
+            return Err(serialport::Error::new(
+                serialport::ErrorKind::Io(kind),
+                "mock read failed",
+            ));
+        }
+        state.release_due(Instant::now());
+        Ok(state.rx.len() as u32)
+    }
+
+    fn bytes_to_write(&self) -> serialport::Result<u32> {
+        Ok(0)
+    }
+
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> serialport::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.clears.push(buffer_to_clear);
+        if matches!(buffer_to_clear, ClearBuffer::Input | ClearBuffer::All) {
+            state.rx.clear();
+        }
+        Ok(())
+    }
+
+    fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+        Ok(Box::new(self.clone()))
+    }
+
+    fn set_break(&self) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn clear_break(&self) -> serialport::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+//! User-assigned labels for boards, keyed by USB serial number so they
+//! survive reconnects and port renumbering.
+
+use crate::db;
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// Boards without a USB serial number cannot be told apart across
+/// reconnects, so they cannot carry a nickname.
+fn require_serial(serial_number: &str) -> Result<&str, String> {
+    let serial = serial_number.trim();
+    if serial.is_empty() {
+        return Err("device has no serial number; nicknames need one".to_string());
+    }
+    Ok(serial)
+}
+
+/// Stores `nickname`; an empty nickname removes the label instead.
+fn store_nickname(conn: &Connection, serial_number: &str, nickname: &str) -> Result<(), String> {
+    let serial = require_serial(serial_number)?;
+    let nickname = nickname.trim();
+    let result = if nickname.is_empty() {
+        conn.execute(
+            "DELETE FROM device_nicknames WHERE serial_number = ?1",
+            params![serial],
+        )
+    } else {
+        conn.execute(
+            "INSERT INTO device_nicknames (serial_number, nickname) VALUES (?1, ?2)
+             ON CONFLICT(serial_number) DO UPDATE SET nickname = excluded.nickname",
+            params![serial, nickname],
+        )
+    };
+    result.map(|_| ()).map_err(|err| err.to_string())
+}
+
+fn load_nickname(conn: &Connection, serial_number: &str) -> Result<Option<String>, String> {
+    let serial = require_serial(serial_number)?;
+    conn.query_row(
+        "SELECT nickname FROM device_nicknames WHERE serial_number = ?1",
+        params![serial],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub fn set_device_nickname(
+    app: tauri::AppHandle,
+    serial_number: String,
+    nickname: String,
+) -> Result<(), String> {
+    let conn = db::open_connection(&app).map_err(|err| err.to_string())?;
+    store_nickname(&conn, &serial_number, &nickname)
+}
+
+#[tauri::command]
+pub fn get_device_nickname(
+    app: tauri::AppHandle,
+    serial_number: String,
+) -> Result<Option<String>, String> {
+    let conn = db::open_connection(&app).map_err(|err| err.to_string())?;
+    load_nickname(&conn, &serial_number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_conn() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        db::run_migrations_once(&mut conn, None).unwrap();
+        conn
+    }
+
+    #[test]
+    fn nickname_round_trips_and_updates() {
+        let conn = test_conn();
+        store_nickname(&conn, "A1B2C3", "Bench A").unwrap();
+        assert_eq!(
+            load_nickname(&conn, "A1B2C3").unwrap(),
+            Some("Bench A".to_string())
+        );
+        store_nickname(&conn, "A1B2C3", "Bench B").unwrap();
+        assert_eq!(
+            load_nickname(&conn, "A1B2C3").unwrap(),
+            Some("Bench B".to_string())
+        );
+        store_nickname(&conn, "A1B2C3", "  ").unwrap();
+        assert_eq!(load_nickname(&conn, "A1B2C3").unwrap(), None);
+    }
+
+    #[test]
+    fn unknown_serial_has_no_nickname() {
+        let conn = test_conn();
+        assert_eq!(load_nickname(&conn, "UNKNOWN").unwrap(), None);
+    }
+
+    #[test]
+    fn missing_serial_is_rejected() {
+        let conn = test_conn();
+        assert!(store_nickname(&conn, "", "Bench A").is_err());
+        assert!(load_nickname(&conn, " ").is_err());
+    }
+}
@@ -0,0 +1,282 @@
+//! Per-handle message-type policy: lets a safety-reviewed deployment keep
+//! dangerous requests (e.g. flash erase) from ever reaching the wire. The
+//! response policy is the inbound counterpart, catching protocol desync.
+
+use crate::error::SerialError;
+use crate::serial::with_entry;
+
+#[derive(Debug, Default)]
+pub(crate) struct MessagePolicy {
+    /// When set, only these TYPE bytes may be sent.
+    allow: Option<Vec<u8>>,
+    /// TYPE bytes that are always refused, even if allowed above.
+    deny: Option<Vec<u8>>,
+    /// Whether empty payloads (see `allow_empty_payload`) pass the allowlist;
+    /// they have no TYPE byte to match against it.
+    allow_empty: bool,
+}
+
+impl MessagePolicy {
+    pub(crate) fn check(&self, message_type: u8) -> Result<(), SerialError> {
+        let allowed = self
+            .allow
+            .as_ref()
+            .is_none_or(|allow| allow.contains(&message_type));
+        let denied = self
+            .deny
+            .as_ref()
+            .is_some_and(|deny| deny.contains(&message_type));
+        if allowed && !denied {
+            Ok(())
+        } else {
+            Err(SerialError::PolicyDenied { message_type })
+        }
+    }
+
+    /// Policy for a payload with no TYPE byte: refused under an allowlist
+    /// unless it opts them in.
+    pub(crate) fn check_empty(&self) -> Result<(), SerialError> {
+        if self.allow.is_some() && !self.allow_empty {
+            return Err(SerialError::InvalidConfig {
+                message: "empty payloads are not on the handle's allowlist".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether either list is set. Writes whose TYPE byte cannot be told
+    /// apart (raw hex) are refused while it is.
+    pub(crate) fn is_restricted(&self) -> bool {
+        self.allow.is_some() || self.deny.is_some()
+    }
+}
+
+/// Shape every request response must have. The default accepts anything.
+#[derive(Debug)]
+pub(crate) struct ResponsePolicy {
+    /// Bounds on the response length, TYPE included.
+    min_len: usize,
+    max_len: usize,
+    required_first_byte: Option<u8>,
+    /// Read past a non-conforming frame as if it were not the response,
+    /// instead of failing the request.
+    pub(crate) skip_mismatched: bool,
+}
+
+impl Default for ResponsePolicy {
+    fn default() -> Self {
+        Self {
+            min_len: 0,
+            max_len: usize::MAX,
+            required_first_byte: None,
+            skip_mismatched: false,
+        }
+    }
+}
+
+impl ResponsePolicy {
+    /// Why `response` does not conform, if it does not.
+    pub(crate) fn check(&self, response: &[u8]) -> Result<(), String> {
+        if !(self.min_len..=self.max_len).contains(&response.len()) {
+            return Err(format!(
+                "response length {} outside {}..={}",
+                response.len(),
+                self.min_len,
+                self.max_len
+            ));
+        }
+        match (self.required_first_byte, response.first()) {
+            (Some(required), Some(&first)) if first != required => Err(format!(
+                "response starts with 0x{first:02X}, expected 0x{required:02X}"
+            )),
+            (Some(required), None) => Err(format!("empty response, expected 0x{required:02X}")),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Replaces the handle's policy; passing neither list lifts all restrictions.
+/// With an allowlist, empty payloads are refused unless `allow_empty` is set.
+#[tauri::command]
+pub fn set_message_policy(
+    handle_id: u64,
+    allow: Option<Vec<u8>>,
+    deny: Option<Vec<u8>>,
+    allow_empty: Option<bool>,
+) -> Result<(), SerialError> {
+    with_entry(handle_id, "set_message_policy", |entry| {
+        entry.policy = MessagePolicy {
+            allow,
+            deny,
+            allow_empty: allow_empty.unwrap_or(false),
+        };
+        Ok(())
+    })
+}
+
+/// Sets the validation applied to every framed response on the handle.
+/// With `skip_mismatched` a non-conforming frame is skipped and reading
+/// continues; otherwise the request fails with `InvalidResponse`.
+#[tauri::command]
+pub fn set_response_policy(
+    handle_id: u64,
+    min_len: usize,
+    max_len: usize,
+    required_first_byte: Option<u8>,
+    skip_mismatched: bool,
+) -> Result<(), SerialError> {
+    if min_len > max_len {
+        return Err(SerialError::InvalidConfig {
+            message: format!("minLen {min_len} exceeds maxLen {max_len}"),
+        });
+    }
+    with_entry(handle_id, "set_response_policy", |entry| {
+        entry.response_policy = ResponsePolicy {
+            min_len,
+            max_len,
+            required_first_byte,
+            skip_mismatched,
+        };
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{build_frame, build_frame_with, ProtocolConfig};
+    use crate::mock_port::MockPort;
+    use crate::serial::{insert_mock, insert_mock_with, send_no_reply, send_request};
+
+    const MSG_ERASE_FLASH: u8 = 0x40;
+
+    #[test]
+    fn policy_combines_allow_and_deny() {
+        let open = MessagePolicy::default();
+        assert!(open.check(MSG_ERASE_FLASH).is_ok());
+
+        let policy = MessagePolicy {
+            allow: Some(vec![0x01, 0x04, MSG_ERASE_FLASH]),
+            deny: Some(vec![MSG_ERASE_FLASH]),
+            allow_empty: false,
+        };
+        assert!(policy.check(0x01).is_ok());
+        assert!(policy.check(0x02).is_err());
+        assert!(matches!(
+            policy.check(MSG_ERASE_FLASH),
+            Err(SerialError::PolicyDenied {
+                message_type: MSG_ERASE_FLASH
+            })
+        ));
+    }
+
+    #[test]
+    fn denied_types_are_refused_before_any_write() {
+        let port = MockPort::new();
+        port.queue_reply(&build_frame(&[0x04, 0x01]).unwrap());
+        let handle_id = insert_mock(&port);
+        set_message_policy(handle_id, None, Some(vec![MSG_ERASE_FLASH]), None).unwrap();
+
+        assert!(matches!(
+            send_request(handle_id, vec![MSG_ERASE_FLASH]),
+            Err(SerialError::PolicyDenied { .. })
+        ));
+        assert!(matches!(
+            send_no_reply(handle_id, vec![MSG_ERASE_FLASH, 0x00]),
+            Err(SerialError::PolicyDenied { .. })
+        ));
+        assert!(port.written().is_empty());
+
+        assert_eq!(
+            send_request(handle_id, vec![0x04]).unwrap(),
+            vec![0x04, 0x01]
+        );
+        set_message_policy(handle_id, None, None, None).unwrap();
+        send_no_reply(handle_id, vec![MSG_ERASE_FLASH]).unwrap();
+        assert!(port
+            .written()
+            .ends_with(&build_frame(&[MSG_ERASE_FLASH]).unwrap()));
+    }
+
+    #[test]
+    fn allowlist_refuses_empty_payloads_unless_opted_in() {
+        let protocol = ProtocolConfig {
+            allow_empty_payload: true,
+            ..ProtocolConfig::default()
+        };
+        let heartbeat = build_frame_with(&[], &protocol).unwrap();
+        let port = MockPort::new();
+        let handle_id = insert_mock_with(&port, protocol);
+        set_message_policy(handle_id, Some(vec![0x04]), None, None).unwrap();
+        assert!(matches!(
+            send_no_reply(handle_id, Vec::new()),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+        assert!(port.written().is_empty());
+
+        set_message_policy(handle_id, Some(vec![0x04]), None, Some(true)).unwrap();
+        send_no_reply(handle_id, Vec::new()).unwrap();
+        set_message_policy(handle_id, None, Some(vec![MSG_ERASE_FLASH]), None).unwrap();
+        send_no_reply(handle_id, Vec::new()).unwrap();
+        assert_eq!(port.written(), [heartbeat.clone(), heartbeat].concat());
+    }
+
+    #[test]
+    fn response_policy_checks_length_and_first_byte() {
+        let policy = ResponsePolicy {
+            min_len: 2,
+            max_len: 3,
+            required_first_byte: Some(0x04),
+            skip_mismatched: false,
+        };
+        assert!(policy.check(&[0x04, 0x01]).is_ok());
+        assert!(policy.check(&[0x04, 0x01, 0x02]).is_ok());
+        assert!(policy.check(&[0x04]).is_err());
+        assert!(policy.check(&[0x04, 0x01, 0x02, 0x03]).is_err());
+        assert!(policy.check(&[0x05, 0x01]).is_err());
+        assert!(ResponsePolicy::default().check(&[]).is_ok());
+    }
+
+    #[test]
+    fn mismatched_response_fails_or_is_skipped() {
+        let stray = build_frame(&[0x09, 0xEE]).unwrap();
+        let reply = build_frame(&[0x04, 0x01]).unwrap();
+
+        let port = MockPort::new();
+        port.queue_reply(&[stray.clone(), reply.clone()].concat());
+        let handle_id = insert_mock(&port);
+        set_response_policy(handle_id, 2, 2, Some(0x04), false).unwrap();
+        assert!(matches!(
+            send_request(handle_id, vec![0x04]),
+            Err(SerialError::InvalidResponse { .. })
+        ));
+
+        let port = MockPort::new();
+        port.queue_reply(&[stray, reply].concat());
+        let handle_id = insert_mock(&port);
+        set_response_policy(handle_id, 2, 2, Some(0x04), true).unwrap();
+        assert_eq!(
+            send_request(handle_id, vec![0x04]).unwrap(),
+            vec![0x04, 0x01]
+        );
+
+        let short = build_frame(&[0x04]).unwrap();
+        let port = MockPort::new();
+        port.queue_reply(&short);
+        let handle_id = insert_mock(&port);
+        set_response_policy(handle_id, 2, 2, None, true).unwrap();
+        assert!(matches!(
+            send_request(handle_id, vec![0x04]),
+            Err(SerialError::Timeout)
+        ));
+    }
+
+    #[test]
+    fn response_policy_rejects_inverted_bounds() {
+        let handle_id = insert_mock(&MockPort::new());
+        assert!(matches!(
+            set_response_policy(handle_id, 4, 2, None, false),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+    }
+}
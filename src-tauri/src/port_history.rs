@@ -0,0 +1,139 @@
+//! Audit trail of which serial ports were present, one row per recorded
+//! enumeration.
+
+use crate::db;
+use crate::serial::{list_ports, PortInfo};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+const MAX_HISTORY_LIMIT: u32 = 500;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortSnapshot {
+    pub id: i64,
+    pub recorded_at: String,
+    pub ports: Vec<PortInfo>,
+}
+
+fn store_snapshot(conn: &Connection, ports: &[PortInfo]) -> Result<i64, String> {
+    let ports_json = serde_json::to_string(ports).map_err(|err| err.to_string())?;
+    conn.execute(
+        "INSERT INTO port_history (recorded_at, ports_json)
+         VALUES (strftime('%Y-%m-%dT%H:%M:%SZ', 'now'), ?1)",
+        params![ports_json],
+    )
+    .map_err(|err| err.to_string())?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Newest first; rows recorded in the same second fall back to insertion order.
+fn load_history(conn: &Connection, limit: u32) -> Result<Vec<PortSnapshot>, String> {
+    if limit == 0 || limit > MAX_HISTORY_LIMIT {
+        return Err(format!("limit must be within 1..={MAX_HISTORY_LIMIT}"));
+    }
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, recorded_at, ports_json FROM port_history
+             ORDER BY recorded_at DESC, id DESC LIMIT ?1",
+        )
+        .map_err(|err| err.to_string())?;
+    let rows = stmt
+        .query_map(params![limit], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get::<_, String>(2)?))
+        })
+        .map_err(|err| err.to_string())?;
+    rows.map(|row| {
+        let (id, recorded_at, ports_json) = row.map_err(|err| err.to_string())?;
+        let ports = serde_json::from_str(&ports_json)
+            .map_err(|err| format!("port history row {id} is corrupt: {err}"))?;
+        Ok(PortSnapshot {
+            id,
+            recorded_at,
+            ports,
+        })
+    })
+    .collect()
+}
+
+/// Enumerates ports as `list_ports` does and stores the result, returning
+/// the new row id.
+#[tauri::command]
+pub fn record_port_snapshot(app: tauri::AppHandle) -> Result<i64, String> {
+    let ports = list_ports(None).map_err(|err| err.to_string())?;
+    let conn = db::open_connection(&app).map_err(|err| err.to_string())?;
+    store_snapshot(&conn, &ports)
+}
+
+#[tauri::command]
+pub fn list_port_history(app: tauri::AppHandle, limit: u32) -> Result<Vec<PortSnapshot>, String> {
+    let conn = db::open_connection(&app).map_err(|err| err.to_string())?;
+    load_history(&conn, limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_conn() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        db::run_migrations_once(&mut conn, None).unwrap();
+        conn
+    }
+
+    fn usb_port(path: &str, serial_number: &str) -> PortInfo {
+        PortInfo {
+            path: path.to_string(),
+            vid: Some(0x0483),
+            pid: Some(0x5740),
+            manufacturer: Some("STMicroelectronics".to_string()),
+            product: Some("VScope".to_string()),
+            serial_number: Some(serial_number.to_string()),
+            port_type: "usb".to_string(),
+            stable_id: format!("usb:0483:5740:{serial_number}"),
+        }
+    }
+
+    #[test]
+    fn snapshot_round_trips_port_list() {
+        let conn = test_conn();
+        let ports = vec![
+            usb_port("/dev/ttyACM0", "A1B2C3"),
+            PortInfo {
+                path: "/dev/ttyS0".to_string(),
+                vid: None,
+                pid: None,
+                manufacturer: None,
+                product: None,
+                serial_number: None,
+                port_type: "pci".to_string(),
+                stable_id: "path:/dev/ttyS0".to_string(),
+            },
+        ];
+        let id = store_snapshot(&conn, &ports).unwrap();
+
+        let history = load_history(&conn, 10).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].id, id);
+        assert_eq!(history[0].ports, ports);
+        assert!(history[0].recorded_at.ends_with('Z'));
+    }
+
+    #[test]
+    fn history_is_newest_first_and_limited() {
+        let conn = test_conn();
+        let first = store_snapshot(&conn, &[]).unwrap();
+        let second = store_snapshot(&conn, &[usb_port("/dev/ttyACM0", "A1")]).unwrap();
+        let third = store_snapshot(&conn, &[usb_port("/dev/ttyACM1", "B2")]).unwrap();
+
+        let ids: Vec<i64> = load_history(&conn, 2)
+            .unwrap()
+            .iter()
+            .map(|snapshot| snapshot.id)
+            .collect();
+        assert_eq!(ids, vec![third, second]);
+        assert_eq!(load_history(&conn, 10).unwrap()[2].id, first);
+        assert!(load_history(&conn, 0).is_err());
+        assert!(load_history(&conn, MAX_HISTORY_LIMIT + 1).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+//! Parallel search for the port a device is answering on.
+//!
+//! Each candidate is opened on a small pool of worker threads, sent one probe
+//! request and closed again. Only the winning path is returned; the caller
+//! opens it for real with `open_device`.
+
+use crate::error::SerialError;
+use crate::serial::{apply_initial_control_lines, exchange, open_port, DeviceEntry, SerialConfig};
+use serialport::SerialPort;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Upper bound on ports probed at once.
+const MAX_PROBE_WORKERS: usize = 4;
+
+/// Opens every path in `paths` (a few at a time), sends `probe_payload` and
+/// returns the first path that answers with a valid frame. Each probe gets
+/// `probe_timeout_ms`; the whole search gives up after `deadline_ms`.
+#[tauri::command]
+pub fn find_responsive_port(
+    paths: Vec<String>,
+    config: SerialConfig,
+    probe_payload: Vec<u8>,
+    probe_timeout_ms: u64,
+    deadline_ms: u64,
+) -> Result<Option<String>, SerialError> {
+    config.protocol.validate()?;
+    let open = |path: &str| {
+        let mut port = open_port(path, &config)?;
+        apply_initial_control_lines(&mut *port, &config)?;
+        Ok(port)
+    };
+    probe_ports_with(
+        &paths,
+        open,
+        &config,
+        &probe_payload,
+        Duration::from_millis(probe_timeout_ms),
+        Duration::from_millis(deadline_ms),
+    )
+}
+
+/// Probe loop with the port opener injected so tests can hand out mocks.
+///
+/// Trial ports live only inside the worker that opened them, and the scope
+/// joins every worker before returning, so no port outlives the call even
+/// when a winner is found early. Workers stop picking up new paths once a
+/// winner is found or the deadline passes.
+fn probe_ports_with(
+    paths: &[String],
+    open: impl Fn(&str) -> Result<Box<dyn SerialPort>, SerialError> + Sync,
+    config: &SerialConfig,
+    probe_payload: &[u8],
+    probe_timeout: Duration,
+    deadline: Duration,
+) -> Result<Option<String>, SerialError> {
+    if probe_payload.is_empty() {
+        return Err(SerialError::InvalidConfig {
+            message: "probe payload must include message type".to_string(),
+        });
+    }
+    let deadline = Instant::now() + deadline;
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let workers = paths.len().min(MAX_PROBE_WORKERS);
+
+    Ok(std::thread::scope(|scope| {
+        let (tx, rx) = mpsc::channel::<Option<&str>>();
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let (open, next, stop) = (&open, &next, &stop);
+            scope.spawn(move || loop {
+                if stop.load(Ordering::Relaxed) {
+                    break;
+                }
+                let Some(path) = paths.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                    break;
+                };
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    break;
+                }
+                let responded = probe_one(
+                    path,
+                    open,
+                    config,
+                    probe_payload,
+                    probe_timeout.min(remaining),
+                );
+                let _ = tx.send(responded.then_some(path.as_str()));
+            });
+        }
+        drop(tx);
+
+        let winner = loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match rx.recv_timeout(remaining) {
+                Ok(Some(path)) => break Some(path.to_string()),
+                Ok(None) => continue,
+                // Every worker finished without a winner, or time ran out.
+                Err(_) => break None,
+            }
+        };
+        stop.store(true, Ordering::Relaxed);
+        winner
+    }))
+}
+
+/// True when `path` opened and answered the probe with a well-formed frame.
+/// The port is dropped, and so closed, before this returns.
+fn probe_one(
+    path: &str,
+    open: &impl Fn(&str) -> Result<Box<dyn SerialPort>, SerialError>,
+    config: &SerialConfig,
+    probe_payload: &[u8],
+    timeout: Duration,
+) -> bool {
+    let port = match open(path) {
+        Ok(port) => port,
+        Err(_) => return false,
+    };
+    let mut entry = DeviceEntry::new(path.to_string(), port, config.protocol.clone());
+    entry.retry.deadline_ms = Some(timeout.as_millis() as u64);
+    if entry.port.set_timeout(timeout).is_err() {
+        return false;
+    }
+    exchange(&mut entry, probe_payload).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::build_frame;
+    use crate::mock_port::MockPort;
+    use crate::serial::test_config;
+    use std::sync::Arc;
+
+    #[test]
+    fn finds_the_only_responding_port_and_closes_all_trials() {
+        let silent = MockPort::new();
+        let responder = MockPort::new();
+        responder.queue_reply(&build_frame(&[0x20, 0x01]).unwrap());
+        let paths: Vec<String> = ["/dev/ttyPROBE0", "/dev/ttyPROBE1", "/dev/ttyPROBE2"]
+            .map(String::from)
+            .to_vec();
+        let open = |path: &str| -> Result<Box<dyn SerialPort>, SerialError> {
+            match path {
+                "/dev/ttyPROBE0" => Ok(Box::new(silent.clone())),
+                "/dev/ttyPROBE1" => Ok(Box::new(responder.clone())),
+                _ => Err(SerialError::PortNotFound {
+                    path: path.to_string(),
+                }),
+            }
+        };
+
+        let found = probe_ports_with(
+            &paths,
+            open,
+            &test_config(),
+            &[0x20],
+            Duration::from_millis(50),
+            Duration::from_secs(2),
+        )
+        .unwrap();
+
+        assert_eq!(found.as_deref(), Some("/dev/ttyPROBE1"));
+        assert_eq!(responder.written(), build_frame(&[0x20]).unwrap());
+        // Only the test's own handles remain: every trial port was dropped.
+        assert_eq!(Arc::strong_count(&silent.state), 1);
+        assert_eq!(Arc::strong_count(&responder.state), 1);
+    }
+
+    #[test]
+    fn no_response_within_deadline_yields_none() {
+        let paths = vec!["/dev/ttyPROBE3".to_string()];
+        let open =
+            |_: &str| -> Result<Box<dyn SerialPort>, SerialError> { Ok(Box::new(MockPort::new())) };
+        let started = Instant::now();
+        let found = probe_ports_with(
+            &paths,
+            open,
+            &test_config(),
+            &[0x20],
+            Duration::from_secs(5),
+            Duration::from_millis(100),
+        )
+        .unwrap();
+        assert_eq!(found, None);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,151 @@
+//! Per-handle request queue: a worker thread that runs framed exchanges off
+//! the IPC thread and reports each result as a `serial://response` event.
+
+use crate::error::SerialError;
+use crate::events::{self, EventSink};
+use crate::serial::{exchange, handle_label, with_entry};
+use serde::Serialize;
+use std::sync::mpsc::{self, Sender};
+
+pub const RESPONSE_EVENT: &str = "serial://response";
+
+pub struct QueuedRequest {
+    pub payload: Vec<u8>,
+    pub request_id: String,
+    pub sink: EventSink,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AsyncResponse {
+    pub handle_id: u64,
+    pub label: Option<String>,
+    pub request_id: String,
+    pub result: AsyncResult,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", content = "value", rename_all = "camelCase")]
+pub enum AsyncResult {
+    Ok(Vec<u8>),
+    Err(SerialError),
+}
+
+/// Starts the worker for `handle_id`. It exits once every sender is dropped,
+/// which happens when the registry entry holding it is removed.
+pub fn spawn_worker(handle_id: u64) -> Sender<QueuedRequest> {
+    let (tx, rx) = mpsc::channel::<QueuedRequest>();
+    std::thread::spawn(move || {
+        for job in rx {
+            let result = with_entry(handle_id, "send_request_async", |entry| {
+                exchange(entry, &job.payload)
+            });
+            let response = AsyncResponse {
+                handle_id,
+                label: handle_label(handle_id),
+                request_id: job.request_id,
+                result: match result {
+                    Ok(payload) => AsyncResult::Ok(payload),
+                    Err(err) => AsyncResult::Err(err),
+                },
+            };
+            events::emit(&job.sink, RESPONSE_EVENT, &response);
+        }
+    });
+    tx
+}
+
+pub fn enqueue(handle_id: u64, job: QueuedRequest) -> Result<(), SerialError> {
+    with_entry(handle_id, "send_request_async", |entry| {
+        let queue = entry.queue.get_or_insert_with(|| spawn_worker(handle_id));
+        // A worker that died (e.g. panicked) is replaced rather than leaving
+        // the handle unable to queue work.
+        if let Err(mpsc::SendError(job)) = queue.send(job) {
+            let fresh = spawn_worker(handle_id);
+            fresh.send(job).map_err(|_| SerialError::IoError {
+                message: "request worker unavailable".to_string(),
+            })?;
+            entry.queue = Some(fresh);
+        }
+        Ok(())
+    })
+}
+
+/// Queues a framed request and returns immediately; the reply arrives as a
+/// `serial://response` event tagged with `request_id`.
+#[tauri::command]
+pub fn send_request_async(
+    app: tauri::AppHandle,
+    handle_id: u64,
+    payload: Vec<u8>,
+    request_id: String,
+) -> Result<(), SerialError> {
+    enqueue(
+        handle_id,
+        QueuedRequest {
+            payload,
+            request_id,
+            sink: events::app_sink(app),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::testing::{capture, wait_for};
+    use crate::frame::build_frame;
+    use crate::mock_port::MockPort;
+    use crate::serial::insert_mock;
+    use std::time::Duration;
+
+    #[test]
+    fn async_response_carries_request_id_and_payload() {
+        let port = MockPort::new();
+        port.queue_reply(&build_frame(&[0x04, 0x01]).unwrap());
+        let id = insert_mock(&port);
+        let (sink, captured) = capture();
+
+        enqueue(
+            id,
+            QueuedRequest {
+                payload: vec![0x04],
+                request_id: "req-7".to_string(),
+                sink,
+            },
+        )
+        .unwrap();
+
+        let events = wait_for(&captured, RESPONSE_EVENT, 1, Duration::from_secs(2));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["requestId"], "req-7");
+        assert_eq!(events[0]["handleId"], id);
+        assert_eq!(events[0]["result"]["status"], "ok");
+        assert_eq!(
+            events[0]["result"]["value"],
+            serde_json::json!([0x04, 0x01])
+        );
+    }
+
+    #[test]
+    fn async_errors_are_reported_in_event() {
+        let port = MockPort::new();
+        let id = insert_mock(&port);
+        let (sink, captured) = capture();
+
+        enqueue(
+            id,
+            QueuedRequest {
+                payload: vec![0x04],
+                request_id: "req-timeout".to_string(),
+                sink,
+            },
+        )
+        .unwrap();
+
+        let events = wait_for(&captured, RESPONSE_EVENT, 1, Duration::from_secs(2));
+        assert_eq!(events[0]["requestId"], "req-timeout");
+        assert_eq!(events[0]["result"]["status"], "err");
+        assert_eq!(events[0]["result"]["value"]["type"], "Timeout");
+    }
+}
@@ -0,0 +1,128 @@
+//! Per-handle token bucket for outgoing requests, so a runaway frontend loop
+//! cannot overrun a slow device's buffers.
+
+use crate::error::SerialError;
+use crate::serial::with_entry;
+use std::time::{Duration, Instant};
+
+pub(crate) struct RateLimiter {
+    /// Tokens added per second; also the bucket size, so up to one second's
+    /// worth of requests may go out back to back.
+    rate: f64,
+    tokens: f64,
+    refilled_at: Instant,
+    /// Fail with `RateLimited` instead of waiting for a token.
+    reject: bool,
+}
+
+impl RateLimiter {
+    fn new(max_per_second: u32, reject: bool) -> Self {
+        let rate = f64::from(max_per_second);
+        Self {
+            rate,
+            tokens: rate,
+            refilled_at: Instant::now(),
+            reject,
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.refilled_at = now;
+    }
+
+    /// Takes one token, sleeping until one is available unless the limiter
+    /// rejects. Called with the device lock held, so a waiting request also
+    /// holds back every other caller on the handle.
+    pub(crate) fn acquire(&mut self) -> Result<(), SerialError> {
+        self.refill();
+        if self.tokens < 1.0 {
+            let wait = Duration::from_secs_f64((1.0 - self.tokens) / self.rate);
+            if self.reject {
+                return Err(SerialError::RateLimited {
+                    retry_after_ms: wait.as_millis().max(1) as u64,
+                });
+            }
+            std::thread::sleep(wait);
+            self.refill();
+        }
+        self.tokens = (self.tokens - 1.0).max(0.0);
+        Ok(())
+    }
+}
+
+/// Limits `send_request` and `send_no_reply` on the handle to
+/// `max_per_second`. Over the limit a call waits for its turn, or with
+/// `reject` fails with `RateLimited`. `None` removes the limit.
+#[tauri::command]
+pub fn set_rate_limit(
+    handle_id: u64,
+    max_per_second: Option<u32>,
+    reject: bool,
+) -> Result<(), SerialError> {
+    if max_per_second == Some(0) {
+        return Err(SerialError::InvalidConfig {
+            message: "maxPerSecond must be at least 1".to_string(),
+        });
+    }
+    with_entry(handle_id, "set_rate_limit", |entry| {
+        entry.rate_limit = max_per_second.map(|rate| RateLimiter::new(rate, reject));
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_port::MockPort;
+    use crate::serial::{insert_mock, send_no_reply};
+
+    #[test]
+    fn burst_beyond_the_bucket_is_paced() {
+        let handle_id = insert_mock(&MockPort::new());
+        set_rate_limit(handle_id, Some(20), false).unwrap();
+
+        let started = Instant::now();
+        for _ in 0..25 {
+            send_no_reply(handle_id, vec![0x01]).unwrap();
+        }
+        // The first 20 drain the bucket; the other 5 wait 50ms each.
+        assert!(started.elapsed() >= Duration::from_millis(240));
+    }
+
+    #[test]
+    fn rejecting_limiter_fails_the_overflow_and_recovers() {
+        let port = MockPort::new();
+        let handle_id = insert_mock(&port);
+        set_rate_limit(handle_id, Some(10), true).unwrap();
+
+        for _ in 0..10 {
+            send_no_reply(handle_id, vec![0x01]).unwrap();
+        }
+        let written = port.written().len();
+        assert!(matches!(
+            send_no_reply(handle_id, vec![0x01]),
+            Err(SerialError::RateLimited { retry_after_ms }) if retry_after_ms <= 100
+        ));
+        assert_eq!(port.written().len(), written);
+
+        std::thread::sleep(Duration::from_millis(120));
+        send_no_reply(handle_id, vec![0x01]).unwrap();
+
+        set_rate_limit(handle_id, None, true).unwrap();
+        for _ in 0..20 {
+            send_no_reply(handle_id, vec![0x01]).unwrap();
+        }
+    }
+
+    #[test]
+    fn zero_rate_is_rejected() {
+        let handle_id = insert_mock(&MockPort::new());
+        assert!(matches!(
+            set_rate_limit(handle_id, Some(0), false),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+    }
+}
@@ -0,0 +1,281 @@
+//! Host-side read buffering.
+//!
+//! The frame reader asks for a byte at a time while hunting for sync, which
+//! against a raw port is one driver call per byte. `BufferedPort` reads up to
+//! its capacity in one call and serves small reads from memory instead.
+//! Buffered bytes count towards `bytes_to_read` and are dropped by an input
+//! `clear`, so callers cannot tell the buffer is there.
+
+use crate::error::SerialError;
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+use std::io::{self, Read, Write};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+pub(crate) const DEFAULT_READ_BUFFER_SIZE: usize = 4096;
+pub(crate) const MIN_READ_BUFFER_SIZE: usize = 16;
+pub(crate) const MAX_READ_BUFFER_SIZE: usize = 1 << 20;
+
+/// Resolves `SerialConfig.read_buffer_size`, defaulting when unset.
+pub(crate) fn read_buffer_size(size: Option<usize>) -> Result<usize, SerialError> {
+    let size = size.unwrap_or(DEFAULT_READ_BUFFER_SIZE);
+    if !(MIN_READ_BUFFER_SIZE..=MAX_READ_BUFFER_SIZE).contains(&size) {
+        return Err(SerialError::InvalidConfig {
+            message: format!(
+                "read buffer size {size} is outside {MIN_READ_BUFFER_SIZE}..={MAX_READ_BUFFER_SIZE}"
+            ),
+        });
+    }
+    Ok(size)
+}
+
+struct Buffer {
+    data: Box<[u8]>,
+    pos: usize,
+    filled: usize,
+}
+
+impl Buffer {
+    fn pending(&self) -> usize {
+        self.filled - self.pos
+    }
+
+    fn discard(&mut self) {
+        self.pos = 0;
+        self.filled = 0;
+    }
+}
+
+/// Delegates everything to the inner port, reading through a fixed-size
+/// buffer. The buffer sits behind a mutex only because `clear` takes `&self`.
+pub(crate) struct BufferedPort {
+    inner: Box<dyn SerialPort>,
+    buffer: Mutex<Buffer>,
+}
+
+impl BufferedPort {
+    pub(crate) fn new(inner: Box<dyn SerialPort>, capacity: usize) -> Self {
+        Self {
+            inner,
+            buffer: Mutex::new(Buffer {
+                data: vec![0; capacity].into_boxed_slice(),
+                pos: 0,
+                filled: 0,
+            }),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn capacity(&self) -> usize {
+        self.buffer.lock().unwrap().data.len()
+    }
+}
+
+impl Read for BufferedPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let buffer = self
+            .buffer
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        if buffer.pending() == 0 {
+            // Nothing to gain from copying a read at least as big as the buffer.
+            if buf.len() >= buffer.data.len() {
+                return self.inner.read(buf);
+            }
+            let n = self.inner.read(&mut buffer.data)?;
+            buffer.pos = 0;
+            buffer.filled = n;
+        }
+        let n = buf.len().min(buffer.pending());
+        buf[..n].copy_from_slice(&buffer.data[buffer.pos..buffer.pos + n]);
+        buffer.pos += n;
+        Ok(n)
+    }
+}
+
+impl Write for BufferedPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl SerialPort for BufferedPort {
+    fn name(&self) -> Option<String> {
+        self.inner.name()
+    }
+
+    fn baud_rate(&self) -> serialport::Result<u32> {
+        self.inner.baud_rate()
+    }
+
+    fn data_bits(&self) -> serialport::Result<DataBits> {
+        self.inner.data_bits()
+    }
+
+    fn flow_control(&self) -> serialport::Result<FlowControl> {
+        self.inner.flow_control()
+    }
+
+    fn parity(&self) -> serialport::Result<Parity> {
+        self.inner.parity()
+    }
+
+    fn stop_bits(&self) -> serialport::Result<StopBits> {
+        self.inner.stop_bits()
+    }
+
+    fn timeout(&self) -> Duration {
+        self.inner.timeout()
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> serialport::Result<()> {
+        self.inner.set_baud_rate(baud_rate)
+    }
+
+    fn set_data_bits(&mut self, data_bits: DataBits) -> serialport::Result<()> {
+        self.inner.set_data_bits(data_bits)
+    }
+
+    fn set_flow_control(&mut self, flow_control: FlowControl) -> serialport::Result<()> {
+        self.inner.set_flow_control(flow_control)
+    }
+
+    fn set_parity(&mut self, parity: Parity) -> serialport::Result<()> {
+        self.inner.set_parity(parity)
+    }
+
+    fn set_stop_bits(&mut self, stop_bits: StopBits) -> serialport::Result<()> {
+        self.inner.set_stop_bits(stop_bits)
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> serialport::Result<()> {
+        self.inner.set_timeout(timeout)
+    }
+
+    fn write_request_to_send(&mut self, level: bool) -> serialport::Result<()> {
+        self.inner.write_request_to_send(level)
+    }
+
+    fn write_data_terminal_ready(&mut self, level: bool) -> serialport::Result<()> {
+        self.inner.write_data_terminal_ready(level)
+    }
+
+    fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+        self.inner.read_clear_to_send()
+    }
+
+    fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+        self.inner.read_data_set_ready()
+    }
+
+    fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+        self.inner.read_ring_indicator()
+    }
+
+    fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+        self.inner.read_carrier_detect()
+    }
+
+    fn bytes_to_read(&self) -> serialport::Result<u32> {
+        let pending = self
+            .buffer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pending();
+        Ok(self.inner.bytes_to_read()? + pending as u32)
+    }
+
+    fn bytes_to_write(&self) -> serialport::Result<u32> {
+        self.inner.bytes_to_write()
+    }
+
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> serialport::Result<()> {
+        if matches!(buffer_to_clear, ClearBuffer::Input | ClearBuffer::All) {
+            self.buffer
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .discard();
+        }
+        self.inner.clear(buffer_to_clear)
+    }
+
+    /// The clone starts with an empty buffer; bytes already buffered here
+    /// stay here.
+    fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+        Ok(Box::new(BufferedPort::new(
+            self.inner.try_clone()?,
+            self.buffer
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .data
+                .len(),
+        )))
+    }
+
+    fn set_break(&self) -> serialport::Result<()> {
+        self.inner.set_break()
+    }
+
+    fn clear_break(&self) -> serialport::Result<()> {
+        self.inner.clear_break()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{build_frame, ProtocolConfig};
+    use crate::mock_port::MockPort;
+    use crate::serial::{registry, send_request, DeviceEntry};
+
+    #[test]
+    fn read_buffer_size_defaults_and_validates() {
+        assert_eq!(read_buffer_size(None).unwrap(), DEFAULT_READ_BUFFER_SIZE);
+        assert_eq!(read_buffer_size(Some(64)).unwrap(), 64);
+        assert!(matches!(
+            read_buffer_size(Some(MIN_READ_BUFFER_SIZE - 1)),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+        assert!(matches!(
+            read_buffer_size(Some(MAX_READ_BUFFER_SIZE + 1)),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+    }
+
+    #[test]
+    fn fills_at_most_capacity_per_underlying_read() {
+        let inner = MockPort::with_rx(&[0xAA; 40]);
+        let mut port = BufferedPort::new(Box::new(inner.clone()), 16);
+        assert_eq!(port.capacity(), 16);
+
+        let mut byte = [0u8; 1];
+        port.read_exact(&mut byte).unwrap();
+        assert_eq!(inner.state.lock().unwrap().rx.len(), 24);
+        assert_eq!(port.bytes_to_read().unwrap(), 39);
+
+        port.clear(ClearBuffer::Input).unwrap();
+        assert_eq!(port.bytes_to_read().unwrap(), 0);
+    }
+
+    #[test]
+    fn frames_spanning_several_fills_still_parse() {
+        let inner = MockPort::new();
+        let payload: Vec<u8> = (0..60).collect();
+        inner.queue_reply(&build_frame(&payload).unwrap());
+        let handle_id = registry().insert(DeviceEntry::new(
+            "mock".to_string(),
+            Box::new(BufferedPort::new(
+                Box::new(inner.clone()),
+                MIN_READ_BUFFER_SIZE,
+            )),
+            ProtocolConfig::default(),
+        ));
+
+        assert_eq!(send_request(handle_id, vec![0x01]).unwrap(), payload);
+        assert!(inner.state.lock().unwrap().rx.is_empty());
+    }
+}
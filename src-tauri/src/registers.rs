@@ -0,0 +1,215 @@
+//! Typed register access layered on `send_request` framing.
+//!
+//! READ_REGISTER is a host-side extension: the request carries a u16 address
+//! and the response a u32 value, both in the handle's configured endianness.
+//! WRITE_REGISTER carries the address and a u32 value and is acknowledged by
+//! an empty response of the same type.
+
+use crate::error::SerialError;
+use crate::frame::{Endianness, MSG_ERROR};
+use crate::serial::{exchange, with_entry, DeviceEntry};
+use serde::Serialize;
+
+pub const MSG_READ_REGISTER: u8 = 0x21;
+pub const MSG_WRITE_REGISTER: u8 = 0x23;
+
+fn encode_read_request(address: u16, endianness: Endianness) -> Vec<u8> {
+    let mut payload = vec![MSG_READ_REGISTER];
+    payload.extend_from_slice(&endianness.encode_u16(address));
+    payload
+}
+
+fn decode_register_value(response: &[u8], endianness: Endianness) -> Result<u32, SerialError> {
+    match response {
+        [MSG_READ_REGISTER, value @ ..] => {
+            let bytes: [u8; 4] = value.try_into().map_err(|_| SerialError::InvalidResponse {
+                message: format!("register value must be 4 bytes, got {}", value.len()),
+            })?;
+            Ok(endianness.decode_u32(bytes))
+        }
+        [other, ..] => Err(SerialError::InvalidResponse {
+            message: format!("expected register response, got type 0x{other:02X}"),
+        }),
+        [] => Err(SerialError::InvalidResponse {
+            message: "empty register response".to_string(),
+        }),
+    }
+}
+
+fn encode_write_request(address: u16, value: u32, endianness: Endianness) -> Vec<u8> {
+    let mut payload = vec![MSG_WRITE_REGISTER];
+    payload.extend_from_slice(&endianness.encode_u16(address));
+    payload.extend_from_slice(&endianness.encode_u32(value));
+    payload
+}
+
+fn check_write_ack(response: &[u8]) -> Result<(), SerialError> {
+    match response {
+        [MSG_WRITE_REGISTER] => Ok(()),
+        [MSG_ERROR, code, ..] => Err(SerialError::InvalidResponse {
+            message: format!("register write refused with error code {code}"),
+        }),
+        _ => Err(SerialError::InvalidResponse {
+            message: format!("expected register write ack, got {response:02X?}"),
+        }),
+    }
+}
+
+fn read_register_locked(entry: &mut DeviceEntry, address: u16) -> Result<u32, SerialError> {
+    let endianness = entry.protocol.endianness;
+    let response = exchange(entry, &encode_read_request(address, endianness))?;
+    decode_register_value(&response, endianness)
+}
+
+#[tauri::command]
+pub fn read_register(handle_id: u64, address: u16) -> Result<u32, SerialError> {
+    with_entry(handle_id, "read_register", |entry| {
+        read_register_locked(entry, address)
+    })
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterVerify {
+    pub verified: bool,
+    /// Value read back after the write; differs from the written value
+    /// exactly when `verified` is false.
+    pub read_back: u32,
+}
+
+/// Writes `value` to `address` and reads it back, all under one device lock
+/// hold so no other request can land between the write and the check.
+#[tauri::command]
+pub fn write_verify_register(
+    handle_id: u64,
+    address: u16,
+    value: u32,
+) -> Result<RegisterVerify, SerialError> {
+    with_entry(handle_id, "write_verify_register", |entry| {
+        let endianness = entry.protocol.endianness;
+        let ack = exchange(entry, &encode_write_request(address, value, endianness))?;
+        check_write_ack(&ack)?;
+        let read_back = read_register_locked(entry, address)?;
+        Ok(RegisterVerify {
+            verified: read_back == value,
+            read_back,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{build_frame, ProtocolConfig};
+    use crate::mock_port::MockPort;
+    use crate::serial::{insert_mock, insert_mock_with};
+
+    #[test]
+    fn read_request_encodes_address_per_endianness() {
+        assert_eq!(
+            encode_read_request(0x1234, Endianness::Little),
+            vec![MSG_READ_REGISTER, 0x34, 0x12]
+        );
+        assert_eq!(
+            encode_read_request(0x1234, Endianness::Big),
+            vec![MSG_READ_REGISTER, 0x12, 0x34]
+        );
+    }
+
+    #[test]
+    fn register_value_rejects_wrong_type_and_length() {
+        assert!(decode_register_value(&[0x01, 1, 2, 3, 4], Endianness::Little).is_err());
+        assert!(decode_register_value(&[MSG_READ_REGISTER, 1, 2, 3], Endianness::Little).is_err());
+        assert!(decode_register_value(&[], Endianness::Little).is_err());
+    }
+
+    fn read_from_mock(endianness: Endianness) -> (MockPort, u32) {
+        let port = MockPort::new();
+        port.queue_reply(&build_frame(&[MSG_READ_REGISTER, 0x78, 0x56, 0x34, 0x12]).unwrap());
+        let id = insert_mock_with(
+            &port,
+            ProtocolConfig {
+                endianness,
+                ..ProtocolConfig::default()
+            },
+        );
+        let value = read_register(id, 0x0010).unwrap();
+        (port, value)
+    }
+
+    #[test]
+    fn read_register_decodes_little_endian_value() {
+        let (port, value) = read_from_mock(Endianness::Little);
+        assert_eq!(value, 0x1234_5678);
+        assert_eq!(
+            port.written(),
+            build_frame(&[MSG_READ_REGISTER, 0x10, 0x00]).unwrap()
+        );
+    }
+
+    #[test]
+    fn read_register_decodes_big_endian_value() {
+        let (port, value) = read_from_mock(Endianness::Big);
+        assert_eq!(value, 0x7856_3412);
+        assert_eq!(
+            port.written(),
+            build_frame(&[MSG_READ_REGISTER, 0x00, 0x10]).unwrap()
+        );
+    }
+
+    fn verify_against(read_back: u32) -> (MockPort, RegisterVerify) {
+        let port = MockPort::new();
+        port.queue_reply(&build_frame(&[MSG_WRITE_REGISTER]).unwrap());
+        let mut reply = vec![MSG_READ_REGISTER];
+        reply.extend_from_slice(&read_back.to_le_bytes());
+        port.queue_reply(&build_frame(&reply).unwrap());
+        let id = insert_mock(&port);
+        let result = write_verify_register(id, 0x0010, 0xCAFE_F00D).unwrap();
+        (port, result)
+    }
+
+    #[test]
+    fn write_verify_register_confirms_matching_read_back() {
+        let (port, result) = verify_against(0xCAFE_F00D);
+        assert_eq!(
+            result,
+            RegisterVerify {
+                verified: true,
+                read_back: 0xCAFE_F00D
+            }
+        );
+        let expected = [
+            build_frame(&[MSG_WRITE_REGISTER, 0x10, 0x00, 0x0D, 0xF0, 0xFE, 0xCA]).unwrap(),
+            build_frame(&[MSG_READ_REGISTER, 0x10, 0x00]).unwrap(),
+        ]
+        .concat();
+        assert_eq!(port.written(), expected);
+    }
+
+    #[test]
+    fn write_verify_register_reports_differing_read_back() {
+        let (_port, result) = verify_against(0x0000_F00D);
+        assert_eq!(
+            result,
+            RegisterVerify {
+                verified: false,
+                read_back: 0x0000_F00D
+            }
+        );
+    }
+
+    #[test]
+    fn write_verify_register_fails_on_refused_write() {
+        let port = MockPort::new();
+        port.queue_reply(&build_frame(&[MSG_ERROR, 0x02]).unwrap());
+        let id = insert_mock(&port);
+        assert!(matches!(
+            write_verify_register(id, 0x0010, 1),
+            Err(SerialError::InvalidResponse { .. })
+        ));
+        assert_eq!(
+            port.written(),
+            build_frame(&[MSG_WRITE_REGISTER, 0x10, 0x00, 0x01, 0x00, 0x00, 0x00]).unwrap()
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Reopening a handle's port in place, so the frontend keeps its handle id
+//! across a disconnect.
+
+use crate::error::SerialError;
+use crate::line_errors::LineErrors;
+use crate::serial::{
+    apply_initial_control_lines, open_port_counted, send_init_frames, with_entry, SerialConfig,
+};
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+/// Stand-in held by an entry between dropping its dead port and getting a
+/// fresh one. Every operation fails, so a reopen that could not open the
+/// port leaves a handle that reports errors until reopened again.
+struct ClosedPort;
+
+fn closed_io() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotConnected,
+        "port closed; reopen the handle",
+    )
+}
+
+fn closed() -> serialport::Error {
+    closed_io().into()
+}
+
+impl Read for ClosedPort {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(closed_io())
+    }
+}
+
+impl Write for ClosedPort {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(closed_io())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Err(closed_io())
+    }
+}
+
+impl SerialPort for ClosedPort {
+    fn name(&self) -> Option<String> {
+        None
+    }
+
+    fn baud_rate(&self) -> serialport::Result<u32> {
+        Err(closed())
+    }
+
+    fn data_bits(&self) -> serialport::Result<DataBits> {
+        Err(closed())
+    }
+
+    fn flow_control(&self) -> serialport::Result<FlowControl> {
+        Err(closed())
+    }
+
+    fn parity(&self) -> serialport::Result<Parity> {
+        Err(closed())
+    }
+
+    fn stop_bits(&self) -> serialport::Result<StopBits> {
+        Err(closed())
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    fn set_baud_rate(&mut self, _baud_rate: u32) -> serialport::Result<()> {
+        Err(closed())
+    }
+
+    fn set_data_bits(&mut self, _data_bits: DataBits) -> serialport::Result<()> {
+        Err(closed())
+    }
+
+    fn set_flow_control(&mut self, _flow_control: FlowControl) -> serialport::Result<()> {
+        Err(closed())
+    }
+
+    fn set_parity(&mut self, _parity: Parity) -> serialport::Result<()> {
+        Err(closed())
+    }
+
+    fn set_stop_bits(&mut self, _stop_bits: StopBits) -> serialport::Result<()> {
+        Err(closed())
+    }
+
+    fn set_timeout(&mut self, _timeout: Duration) -> serialport::Result<()> {
+        Err(closed())
+    }
+
+    fn write_request_to_send(&mut self, _level: bool) -> serialport::Result<()> {
+        Err(closed())
+    }
+
+    fn write_data_terminal_ready(&mut self, _level: bool) -> serialport::Result<()> {
+        Err(closed())
+    }
+
+    fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+        Err(closed())
+    }
+
+    fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+        Err(closed())
+    }
+
+    fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+        Err(closed())
+    }
+
+    fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+        Err(closed())
+    }
+
+    fn bytes_to_read(&self) -> serialport::Result<u32> {
+        Err(closed())
+    }
+
+    fn bytes_to_write(&self) -> serialport::Result<u32> {
+        Err(closed())
+    }
+
+    fn clear(&self, _buffer_to_clear: ClearBuffer) -> serialport::Result<()> {
+        Err(closed())
+    }
+
+    fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+        Err(closed())
+    }
+
+    fn set_break(&self) -> serialport::Result<()> {
+        Err(closed())
+    }
+
+    fn clear_break(&self) -> serialport::Result<()> {
+        Err(closed())
+    }
+}
+
+/// Closes the handle's port and opens its path again with the config it was
+/// first opened with. The handle id, policy, stats and queued state survive;
+/// input already waiting on the new port predates the reopen and is dropped,
+/// along with the old port's read buffer.
+#[tauri::command]
+pub fn reopen_handle(handle_id: u64) -> Result<(), SerialError> {
+    reopen_with(handle_id, open_port_counted)
+}
+
+fn reopen_with(
+    handle_id: u64,
+    open: impl FnOnce(
+        &str,
+        &SerialConfig,
+    ) -> Result<(Box<dyn SerialPort>, Option<LineErrors>), SerialError>,
+) -> Result<(), SerialError> {
+    with_entry(handle_id, "reopen_handle", |entry| {
+        let config = entry
+            .open_config
+            .clone()
+            .ok_or_else(|| SerialError::InvalidConfig {
+                message: format!("handle {handle_id} has no stored open config"),
+            })?;
+        // Drop the dead port first: ports are opened exclusively, so the new
+        // open would otherwise fail against our own stale descriptor.
+        entry.port = Box::new(ClosedPort);
+        entry.line_errors = None;
+        let (mut port, line_errors) = open(&entry.path, &config)?;
+        apply_initial_control_lines(&mut *port, &config)?;
+        port.clear(ClearBuffer::Input)?;
+        entry.resolved_path = config.resolve_symlinks.then(|| port.name()).flatten();
+        entry.port = port;
+        entry.line_errors = line_errors;
+        entry.stats.record_reopen();
+        send_init_frames(entry, &config)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::diagnostics_report;
+    use crate::frame::build_frame;
+    use crate::mock_port::MockPort;
+    use crate::serial::{insert_mock, send_request, test_config};
+    use std::sync::Arc;
+
+    fn store_config(handle_id: u64) {
+        with_entry(handle_id, "test", |entry| {
+            entry.open_config = Some(test_config());
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn reopen_keeps_id_and_swaps_port() {
+        let dead = MockPort::new();
+        let handle_id = insert_mock(&dead);
+        store_config(handle_id);
+
+        let fresh = MockPort::new();
+        fresh.queue_reply(&build_frame(&[0x01, 0x07]).unwrap());
+        let opened = fresh.clone();
+        reopen_with(handle_id, move |path, _| {
+            assert_eq!(path, "mock");
+            Ok((Box::new(opened), None))
+        })
+        .unwrap();
+
+        assert_eq!(Arc::strong_count(&dead.state), 1);
+        assert_eq!(
+            send_request(handle_id, vec![0x01]).unwrap(),
+            vec![0x01, 0x07]
+        );
+        assert_eq!(fresh.written(), build_frame(&[0x01]).unwrap());
+    }
+
+    #[test]
+    fn reopen_keeps_stats_but_drops_stale_input() {
+        let dead = MockPort::new();
+        dead.queue_reply(&build_frame(&[0x01, 0x07]).unwrap());
+        let handle_id = insert_mock(&dead);
+        store_config(handle_id);
+        send_request(handle_id, vec![0x01]).unwrap();
+        assert!(send_request(handle_id, vec![0x01]).is_err());
+
+        let fresh = MockPort::with_rx(&[0xEE, 0xEE]);
+        let opened = fresh.clone();
+        reopen_with(handle_id, move |_, _| Ok((Box::new(opened), None))).unwrap();
+
+        assert!(fresh.state.lock().unwrap().rx.is_empty());
+        let report = diagnostics_report(handle_id).unwrap();
+        assert_eq!(report.counters.requests, 2);
+        assert_eq!(report.counters.responses, 1);
+        assert_eq!(report.counters.reopens, 1);
+        assert_eq!(report.recent_errors, vec!["timeout".to_string()]);
+    }
+
+    #[test]
+    fn failed_reopen_leaves_a_closed_handle() {
+        let handle_id = insert_mock(&MockPort::new());
+        store_config(handle_id);
+        let err = reopen_with(handle_id, |path, _| {
+            Err(SerialError::PortNotFound {
+                path: path.to_string(),
+            })
+        });
+        assert!(matches!(err, Err(SerialError::PortNotFound { .. })));
+        assert!(matches!(
+            send_request(handle_id, vec![0x01]),
+            Err(SerialError::Disconnected { .. })
+        ));
+    }
+
+    #[test]
+    fn reopen_rejects_unknown_handle() {
+        assert!(matches!(
+            reopen_handle(u64::MAX),
+            Err(SerialError::InvalidHandle { .. })
+        ));
+    }
+}
@@ -0,0 +1,205 @@
+//! Scripted request/response sequences for device test automation.
+
+use crate::error::SerialError;
+use crate::serial::{exchange, with_entry};
+use serde::{Deserialize, Serialize};
+
+/// Checks applied to a step's response; every field that is set must hold.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResponseMatcher {
+    pub min_len: Option<usize>,
+    pub max_len: Option<usize>,
+    /// Required first byte, usually the response TYPE.
+    pub leading_byte: Option<u8>,
+    pub exact: Option<Vec<u8>>,
+}
+
+impl ResponseMatcher {
+    fn check(&self, response: &[u8]) -> Result<(), String> {
+        if let Some(min) = self.min_len.filter(|&min| response.len() < min) {
+            return Err(format!(
+                "response is {} bytes, expected at least {min}",
+                response.len()
+            ));
+        }
+        if let Some(max) = self.max_len.filter(|&max| response.len() > max) {
+            return Err(format!(
+                "response is {} bytes, expected at most {max}",
+                response.len()
+            ));
+        }
+        if let Some(expected) = self.leading_byte {
+            match response.first() {
+                Some(&byte) if byte == expected => {}
+                Some(byte) => {
+                    return Err(format!(
+                        "expected leading 0x{expected:02X}, got 0x{byte:02X}"
+                    ))
+                }
+                None => return Err(format!("expected leading 0x{expected:02X}, got nothing")),
+            }
+        }
+        if let Some(exact) = self.exact.as_ref().filter(|exact| exact[..] != *response) {
+            return Err(format!("expected {exact:02X?}, got {response:02X?}"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequenceStep {
+    pub payload: Vec<u8>,
+    #[serde(default)]
+    pub expect: ResponseMatcher,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepFailure {
+    /// Zero-based index into the submitted steps.
+    pub step: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequenceReport {
+    /// Responses of the steps that ran, the failing one included.
+    pub responses: Vec<Vec<u8>>,
+    /// `None` when every step passed.
+    pub failure: Option<StepFailure>,
+}
+
+/// Runs `steps` in order under one hold of the device lock, so nothing else
+/// can interleave, and stops at the first step whose exchange fails or whose
+/// response does not match. A failed step is reported, not returned as an
+/// error; only an unusable handle is.
+#[tauri::command]
+pub fn run_sequence(
+    handle_id: u64,
+    steps: Vec<SequenceStep>,
+) -> Result<SequenceReport, SerialError> {
+    with_entry(handle_id, "run_sequence", |entry| {
+        let mut responses = Vec::new();
+        for (index, step) in steps.iter().enumerate() {
+            let checked = match exchange(entry, &step.payload) {
+                Ok(response) => {
+                    let checked = step.expect.check(&response);
+                    responses.push(response);
+                    checked
+                }
+                Err(err) => Err(err.to_string()),
+            };
+            if let Err(reason) = checked {
+                return Ok(SequenceReport {
+                    responses,
+                    failure: Some(StepFailure {
+                        step: index,
+                        reason,
+                    }),
+                });
+            }
+        }
+        Ok(SequenceReport {
+            responses,
+            failure: None,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::build_frame;
+    use crate::mock_port::MockPort;
+    use crate::serial::insert_mock;
+
+    fn step(payload: &[u8], expect: ResponseMatcher) -> SequenceStep {
+        SequenceStep {
+            payload: payload.to_vec(),
+            expect,
+        }
+    }
+
+    #[test]
+    fn matcher_checks_every_set_field() {
+        let matcher = ResponseMatcher {
+            min_len: Some(2),
+            max_len: Some(3),
+            leading_byte: Some(0x01),
+            exact: None,
+        };
+        assert!(matcher.check(&[0x01, 0x02]).is_ok());
+        assert!(matcher.check(&[0x01]).is_err());
+        assert!(matcher.check(&[0x01, 0, 0, 0]).is_err());
+        assert!(matcher.check(&[0x02, 0x02]).is_err());
+        assert!(ResponseMatcher::default().check(&[]).is_ok());
+    }
+
+    #[test]
+    fn sequence_stops_at_first_failed_expectation() {
+        let port = MockPort::new();
+        port.queue_reply(&build_frame(&[0x01, 0x10]).unwrap());
+        port.queue_reply(&build_frame(&[0x02, 0x20, 0x21]).unwrap());
+        port.queue_reply(&build_frame(&[0x03]).unwrap());
+        let handle_id = insert_mock(&port);
+
+        let report = run_sequence(
+            handle_id,
+            vec![
+                step(
+                    &[0x01],
+                    ResponseMatcher {
+                        exact: Some(vec![0x01, 0x10]),
+                        ..ResponseMatcher::default()
+                    },
+                ),
+                step(
+                    &[0x02],
+                    ResponseMatcher {
+                        leading_byte: Some(0x02),
+                        max_len: Some(2),
+                        ..ResponseMatcher::default()
+                    },
+                ),
+                step(&[0x03], ResponseMatcher::default()),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            report.responses,
+            vec![vec![0x01, 0x10], vec![0x02, 0x20, 0x21]]
+        );
+        assert_eq!(
+            report.failure,
+            Some(StepFailure {
+                step: 1,
+                reason: "response is 3 bytes, expected at most 2".to_string(),
+            })
+        );
+        let mut expected = build_frame(&[0x01]).unwrap();
+        expected.extend(build_frame(&[0x02]).unwrap());
+        assert_eq!(port.written(), expected);
+    }
+
+    #[test]
+    fn sequence_reports_a_timed_out_step() {
+        let port = MockPort::new();
+        port.queue_reply(&build_frame(&[0x01]).unwrap());
+        let handle_id = insert_mock(&port);
+
+        let report = run_sequence(
+            handle_id,
+            vec![
+                step(&[0x01], ResponseMatcher::default()),
+                step(&[0x02], ResponseMatcher::default()),
+            ],
+        )
+        .unwrap();
+        assert_eq!(report.responses, vec![vec![0x01]]);
+        assert_eq!(report.failure.unwrap().step, 1);
+    }
+}
@@ -1,20 +1,35 @@
+use crate::access::AccessMode;
+use crate::crc::{self, CrcCoverage, CrcVariant};
+use crate::diagnostics::HandleStats;
+use crate::disconnect::{is_disconnect, DisconnectKind};
 use crate::error::SerialError;
+use crate::events::{self, EventSink};
+use crate::frame::{
+    build_frame_with, frame_crc, read_frame_bounded, read_frame_unchecked, CrcPosition,
+    ProtocolConfig, MSG_ERROR,
+};
+use crate::line_errors::LineErrors;
+use crate::policy::{MessagePolicy, ResponsePolicy};
+use crate::queue::QueuedRequest;
+use crate::ratelimit::RateLimiter;
+use crate::read_buffer::{read_buffer_size, BufferedPort};
+use crate::stream::{
+    check_stream_conflict, StreamConflict, StreamStats, StreamWorker, UnsolicitedRing,
+    UNSOLICITED_CAPACITY,
+};
 use serde::{Deserialize, Serialize};
 use serialport::{
-    ClearBuffer, DataBits, FlowControl, Parity, SerialPort, SerialPortType, StopBits,
+    ClearBuffer, DataBits, FlowControl, Parity, SerialPort, SerialPortInfo, SerialPortType,
+    StopBits,
 };
 use std::collections::HashMap;
-use std::io::Write;
+use std::io::{self, ErrorKind, Read, Write};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, Mutex, OnceLock, RwLock};
-use std::time::{Duration, Instant};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-// Protocol constants matching the code in onboard/vscope.c
-const VSCOPE_SYNC_BYTE: u8 = 0xC8;
-const MAX_FRAME_LEN: usize = 254;
-const MAX_PAYLOAD_LEN: usize = 252;
-
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SerialConfig {
     pub baud_rate: u32,
@@ -22,9 +37,99 @@ pub struct SerialConfig {
     pub parity: Parity,
     pub stop_bits: StopBits,
     pub read_timeout_ms: u64,
+    /// DTR level to drive immediately after open; `None` keeps the driver default.
+    pub initial_dtr: Option<bool>,
+    /// RTS level to drive immediately after open; `None` keeps the driver default.
+    pub initial_rts: Option<bool>,
+    /// Zero port timeout: `read_raw` returns `WouldBlock` at once when no
+    /// data is waiting. Framed requests are refused on such handles.
+    #[serde(default)]
+    pub nonblocking: bool,
+    /// Discard waiting input before each framed request is written, so bytes
+    /// left over from an aborted exchange cannot be read as its response.
+    /// Turn off when unsolicited frames must survive a request.
+    #[serde(default = "default_flush_before_send")]
+    pub flush_before_send: bool,
+    /// Flush the port after each `send_no_reply` and `write_hex`. Turn off
+    /// to batch several writes and push them out with `flush_output`.
+    /// Framed requests always flush, since they wait for the reply.
+    #[serde(default = "default_auto_flush")]
+    pub auto_flush: bool,
+    /// Gap between written bytes for receivers that drop back-to-back bytes.
+    /// `None` writes each frame in one call.
+    pub inter_byte_delay_us: Option<u64>,
+    /// Extra attempts for a write that fails with `WouldBlock` or
+    /// `TimedOut` (unless those are configured as disconnects), a few
+    /// milliseconds apart. Counted per write call: per frame, or per byte
+    /// when writes are paced.
+    #[serde(default)]
+    pub write_retries: u32,
+    /// `"read-write"` (the default) or `"read-only"`. A read-only handle
+    /// refuses every data write; see `access` for platform caveats.
+    pub access_mode: Option<String>,
+    /// What opening a path that already has a handle does: `"allow"` (the
+    /// default) opens another handle, `"reuse"` returns the existing one and
+    /// `"error"` fails with `PortBusy`. `"reuse"` ignores the rest of this
+    /// config, keeping the settings the existing handle was opened with.
+    pub on_duplicate: Option<String>,
+    /// Size of the host-side read buffer, 16 bytes to 1 MiB; `None` uses
+    /// 4096. Larger suits high-rate streams, smaller constrained hosts.
+    pub read_buffer_size: Option<usize>,
+    /// Setup payloads sent in order right after open, before the handle is
+    /// registered; any failure fails the open. Sent again by `reopen_handle`.
+    #[serde(default)]
+    pub init_frames: Vec<Vec<u8>>,
+    /// Wait for a reply to each init frame. An error reply fails the open.
+    #[serde(default)]
+    pub init_await_ack: bool,
+    /// Open the target of a symlinked path (e.g. `/dev/serial/by-id/...`)
+    /// rather than the link. The handle keeps the link as its path, so
+    /// `reopen_handle` resolves it afresh after a re-enumeration.
+    #[serde(default)]
+    pub resolve_symlinks: bool,
+    /// Name carried in every event the handle emits, next to its id.
+    /// `None` uses the port path.
+    pub label: Option<String>,
+    /// After open, discard input until the line has been quiet this long,
+    /// e.g. to drop a boot banner. `None` skips the settle phase.
+    pub settle_quiet_ms: Option<u64>,
+    /// Longest the settle phase may run; the open then goes ahead anyway and
+    /// emits `SETTLE_TIMEOUT_EVENT`. `None` uses `DEFAULT_SETTLE_TIMEOUT_MS`.
+    pub settle_timeout_ms: Option<u64>,
+    #[serde(flatten)]
+    pub protocol: ProtocolConfig,
+    #[serde(flatten)]
+    pub retry: RetryConfig,
 }
 
-#[derive(Debug, Serialize)]
+fn default_flush_before_send() -> bool {
+    true
+}
+
+fn default_auto_flush() -> bool {
+    true
+}
+
+/// Retransmission settings for framed requests, kept per handle.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetryConfig {
+    /// Extra attempts after a timeout or CRC mismatch, shared between the
+    /// two unless either limit below is set.
+    pub max_retries: u32,
+    /// Extra attempts after a CRC mismatch, counted on their own. A bit flip
+    /// is usually worth retrying harder than a silent device. `None` uses
+    /// `max_retries`.
+    pub crc_retries: Option<u32>,
+    /// Extra attempts after a timeout, counted on their own. `None` uses
+    /// `max_retries`.
+    pub timeout_retries: Option<u32>,
+    /// Wall-clock budget for the whole request across all attempts,
+    /// independent of the per-attempt read timeout.
+    pub deadline_ms: Option<u64>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PortInfo {
     pub path: String,
@@ -34,21 +139,134 @@ pub struct PortInfo {
     pub product: Option<String>,
     pub serial_number: Option<String>,
     pub port_type: String,
+    /// Key for tracking a device across reconnects; see `stable_port_id`.
+    /// Empty in port history recorded before it existed.
+    #[serde(default)]
+    pub stable_id: String,
 }
 
-type PortHandle = Arc<Mutex<Box<dyn SerialPort + Send>>>;
+pub(crate) struct DeviceEntry {
+    /// Path the port was opened on, as passed to `open_device`.
+    pub(crate) path: String,
+    /// Device node actually opened when `resolve_symlinks` followed `path`.
+    pub(crate) resolved_path: Option<String>,
+    /// Included in the handle's event payloads; the path unless set.
+    pub(crate) label: String,
+    pub(crate) port: Box<dyn SerialPort + Send>,
+    pub(crate) protocol: ProtocolConfig,
+    pub(crate) retry: RetryConfig,
+    pub(crate) nonblocking: bool,
+    pub(crate) flush_before_send: bool,
+    pub(crate) auto_flush: bool,
+    pub(crate) inter_byte_delay: Option<Duration>,
+    pub(crate) write_retries: u32,
+    pub(crate) policy: MessagePolicy,
+    pub(crate) response_policy: ResponsePolicy,
+    pub(crate) rate_limit: Option<RateLimiter>,
+    /// Worker for `send_request_async`, started on first use.
+    pub(crate) queue: Option<Sender<QueuedRequest>>,
+    /// Frames collected by the stream reader, waiting to be drained.
+    pub(crate) unsolicited: UnsolicitedRing,
+    pub(crate) stream: Option<StreamWorker>,
+    pub(crate) stream_conflict: StreamConflict,
+    /// Running checksum over every frame the stream reader has decoded since
+    /// the stream was started.
+    pub(crate) stream_crc: u8,
+    pub(crate) stream_stats: StreamStats,
+    pub(crate) stats: HandleStats,
+    /// Most recent response or stream frame, for debug views.
+    pub(crate) last_frame: Option<TimedFrame>,
+    /// Write-to-parse time of the last successful framed transaction.
+    pub(crate) last_rtt: Option<Duration>,
+    /// Last frame built by the send path; raw frames are not included.
+    pub(crate) last_tx: Option<LastTx>,
+    /// Driver error counters for `line_status`; `None` where unsupported.
+    pub(crate) line_errors: Option<LineErrors>,
+    /// Config the port was opened with, kept for `reopen_handle`.
+    pub(crate) open_config: Option<SerialConfig>,
+}
 
-struct Registry {
-    next_id: AtomicU64,
-    ports: RwLock<HashMap<u64, PortHandle>>,
+impl DeviceEntry {
+    pub(crate) fn new(
+        path: String,
+        port: Box<dyn SerialPort + Send>,
+        protocol: ProtocolConfig,
+    ) -> Self {
+        Self {
+            resolved_path: None,
+            label: path.clone(),
+            path,
+            port,
+            stream_crc: protocol.crc_init,
+            stream_stats: StreamStats::default(),
+            protocol,
+            retry: RetryConfig::default(),
+            nonblocking: false,
+            flush_before_send: true,
+            auto_flush: true,
+            inter_byte_delay: None,
+            write_retries: 0,
+            policy: MessagePolicy::default(),
+            response_policy: ResponsePolicy::default(),
+            rate_limit: None,
+            queue: None,
+            unsolicited: UnsolicitedRing::new(UNSOLICITED_CAPACITY),
+            stream: None,
+            stream_conflict: StreamConflict::default(),
+            stats: HandleStats::new(),
+            last_frame: None,
+            last_rtt: None,
+            last_tx: None,
+            line_errors: None,
+            open_config: None,
+        }
+    }
 }
 
-fn poisoned_registry_lock(context: &str) -> SerialError {
-    SerialError::IoError {
-        message: format!("registry lock poisoned during {context}"),
+/// A received TYPE + PAYLOAD and when it was parsed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimedFrame {
+    pub payload: Vec<u8>,
+    /// Milliseconds since the Unix epoch.
+    pub received_at_ms: u64,
+}
+
+impl TimedFrame {
+    pub(crate) fn now(payload: &[u8]) -> Self {
+        Self {
+            payload: payload.to_vec(),
+            received_at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_millis() as u64),
+        }
     }
 }
 
+/// The last frame a handle built and wrote, for matching against device logs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LastTx {
+    /// Complete wire bytes, preamble to footer.
+    pub frame: Vec<u8>,
+    pub crc: u8,
+}
+
+type PortHandle = Arc<Mutex<DeviceEntry>>;
+
+/// A registry slot. The path is copied out of the entry so lookups by path
+/// need not lock every device.
+struct Registered {
+    /// `normalize_path` of the path the entry was opened on.
+    path: String,
+    port: PortHandle,
+}
+
+pub(crate) struct Registry {
+    next_id: AtomicU64,
+    ports: RwLock<HashMap<u64, Registered>>,
+}
+
 fn poisoned_device_lock(handle_id: u64, context: &str) -> SerialError {
     SerialError::IoError {
         message: format!(
@@ -65,278 +283,1369 @@ impl Registry {
         }
     }
 
-    fn insert(&self, port: Box<dyn SerialPort + Send>) -> Result<u64, SerialError> {
+    // The map only ever sees single insert/remove calls, so a panic while the
+    // lock was held cannot leave it half-updated; recovering the guard keeps
+    // one panicking command from locking everyone out of handle management.
+    fn read_ports(&self) -> RwLockReadGuard<'_, HashMap<u64, Registered>> {
+        self.ports.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write_ports(&self) -> RwLockWriteGuard<'_, HashMap<u64, Registered>> {
+        self.ports.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(crate) fn insert(&self, entry: DeviceEntry) -> u64 {
         let id = self.next_id.fetch_add(1, Ordering::Relaxed);
-        let mut ports = self
-            .ports
-            .write()
-            .map_err(|_| poisoned_registry_lock("insert"))?;
-        ports.insert(id, Arc::new(Mutex::new(port)));
-        Ok(id)
+        let registered = Registered {
+            path: normalize_path(&entry.path),
+            port: Arc::new(Mutex::new(entry)),
+        };
+        self.write_ports().insert(id, registered);
+        id
+    }
+
+    pub(crate) fn get(&self, id: u64) -> Option<PortHandle> {
+        self.read_ports()
+            .get(&id)
+            .map(|slot| Arc::clone(&slot.port))
     }
 
-    fn get(&self, id: u64) -> Result<Option<PortHandle>, SerialError> {
-        let ports = self
-            .ports
-            .read()
-            .map_err(|_| poisoned_registry_lock("get"))?;
-        Ok(ports.get(&id).cloned())
+    pub(crate) fn remove(&self, id: u64) -> Option<PortHandle> {
+        self.write_ports().remove(&id).map(|slot| slot.port)
     }
 
-    fn remove(&self, id: u64) -> Result<Option<PortHandle>, SerialError> {
-        let mut ports = self
-            .ports
-            .write()
-            .map_err(|_| poisoned_registry_lock("remove"))?;
-        Ok(ports.remove(&id))
+    /// Lowest handle id whose entry was opened on `path`.
+    fn find_by_path(&self, path: &str) -> Option<u64> {
+        let wanted = normalize_path(path);
+        self.read_ports()
+            .iter()
+            .filter(|(_, slot)| slot.path == wanted)
+            .map(|(&id, _)| id)
+            .min()
     }
 }
 
-fn registry() -> &'static Registry {
+/// Windows port names are case-insensitive and may carry the `\\.\` device
+/// namespace prefix, so `COM3`, `com3` and `\\.\COM3` all name one port.
+#[cfg(windows)]
+fn normalize_path(path: &str) -> String {
+    path.trim_start_matches(r"\\.\").to_ascii_uppercase()
+}
+
+#[cfg(not(windows))]
+fn normalize_path(path: &str) -> String {
+    path.to_string()
+}
+
+pub(crate) fn registry() -> &'static Registry {
     static REGISTRY: OnceLock<Registry> = OnceLock::new();
     REGISTRY.get_or_init(Registry::new)
 }
 
-#[tauri::command]
-pub fn list_ports() -> Result<Vec<PortInfo>, SerialError> {
-    let ports = serialport::available_ports()?;
-    let mut out = Vec::new();
-    for port in ports {
-        let (vid, pid, manufacturer, product, serial_number, port_type) = match &port.port_type {
-            SerialPortType::UsbPort(info) => (
-                Some(info.vid),
-                Some(info.pid),
-                info.manufacturer.clone(),
-                info.product.clone(),
-                info.serial_number.clone(),
-                "usb".to_string(),
-            ),
-            SerialPortType::BluetoothPort => {
-                (None, None, None, None, None, "bluetooth".to_string())
+/// Registers a mock port in the global registry with default protocol settings.
+#[cfg(test)]
+pub(crate) fn insert_mock(port: &crate::mock_port::MockPort) -> u64 {
+    insert_mock_with(port, ProtocolConfig::default())
+}
+
+#[cfg(test)]
+pub(crate) fn insert_mock_with(port: &crate::mock_port::MockPort, protocol: ProtocolConfig) -> u64 {
+    insert_mock_at(port, "mock", protocol)
+}
+
+#[cfg(test)]
+pub(crate) fn insert_mock_at(
+    port: &crate::mock_port::MockPort,
+    path: &str,
+    protocol: ProtocolConfig,
+) -> u64 {
+    registry().insert(DeviceEntry::new(
+        path.to_string(),
+        Box::new(port.clone()),
+        protocol,
+    ))
+}
+
+#[cfg(test)]
+pub(crate) fn test_config() -> SerialConfig {
+    SerialConfig {
+        baud_rate: 115_200,
+        data_bits: DataBits::Eight,
+        parity: Parity::None,
+        stop_bits: StopBits::One,
+        read_timeout_ms: 50,
+        initial_dtr: None,
+        initial_rts: None,
+        nonblocking: false,
+        flush_before_send: true,
+        auto_flush: true,
+        inter_byte_delay_us: None,
+        write_retries: 0,
+        access_mode: None,
+        on_duplicate: None,
+        read_buffer_size: None,
+        init_frames: Vec::new(),
+        init_await_ack: false,
+        resolve_symlinks: false,
+        label: None,
+        settle_quiet_ms: None,
+        settle_timeout_ms: None,
+        protocol: ProtocolConfig::default(),
+        retry: RetryConfig::default(),
+    }
+}
+
+/// Options for `list_ports`; omitted fields keep the default behavior.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PortFilter {
+    /// Report an enumeration failure as "no ports" instead of an error, for
+    /// locked-down systems where the OS refuses to list devices.
+    pub tolerate_errors: bool,
+    /// `"path"`, `"product"`, `"type"` or `"usb-first"`; `None` keeps the
+    /// OS enumeration order.
+    pub sort_by: Option<String>,
+}
+
+/// Orderings for `PortFilter::sort_by`. Every key falls back to the path so
+/// the result does not depend on enumeration order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PortSort {
+    Path,
+    /// Ports without a product name go last.
+    Product,
+    Type,
+    UsbFirst,
+}
+
+impl PortSort {
+    fn parse(key: &str) -> Result<Self, SerialError> {
+        match key {
+            "path" => Ok(PortSort::Path),
+            "product" => Ok(PortSort::Product),
+            "type" => Ok(PortSort::Type),
+            "usb-first" => Ok(PortSort::UsbFirst),
+            other => Err(SerialError::InvalidConfig {
+                message: format!(
+                    "unknown port sort key {other:?}; expected path, product, type or usb-first"
+                ),
+            }),
+        }
+    }
+
+    fn sort(self, ports: &mut [PortInfo]) {
+        match self {
+            PortSort::Path => ports.sort_by(|a, b| a.path.cmp(&b.path)),
+            PortSort::Product => ports.sort_by(|a, b| {
+                (a.product.is_none(), &a.product, &a.path).cmp(&(
+                    b.product.is_none(),
+                    &b.product,
+                    &b.path,
+                ))
+            }),
+            PortSort::Type => {
+                ports.sort_by(|a, b| (&a.port_type, &a.path).cmp(&(&b.port_type, &b.path)))
             }
-            SerialPortType::PciPort => (None, None, None, None, None, "pci".to_string()),
-            SerialPortType::Unknown => (None, None, None, None, None, "unknown".to_string()),
-        };
+            PortSort::UsbFirst => ports.sort_by(|a, b| {
+                (a.port_type != "usb", &a.path).cmp(&(b.port_type != "usb", &b.path))
+            }),
+        }
+    }
+}
 
-        out.push(PortInfo {
-            path: port.port_name,
-            vid,
-            pid,
-            manufacturer,
-            product,
-            serial_number,
-            port_type,
-        });
+#[tauri::command]
+pub fn list_ports(filter: Option<PortFilter>) -> Result<Vec<PortInfo>, SerialError> {
+    enumerate_ports(serialport::available_ports, &filter.unwrap_or_default())
+}
+
+/// A listed port and the handle already open on it, if any.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotatedPort {
+    #[serde(flatten)]
+    pub port: PortInfo,
+    /// Lowest open handle on this path, matched as `handle_for_path` does.
+    pub open_handle_id: Option<u64>,
+}
+
+/// `list_ports`, with each port marked with the handle open on it.
+#[tauri::command]
+pub fn list_ports_annotated(filter: Option<PortFilter>) -> Result<Vec<AnnotatedPort>, SerialError> {
+    annotate_ports(list_ports(filter)?)
+}
+
+fn annotate_ports(ports: Vec<PortInfo>) -> Result<Vec<AnnotatedPort>, SerialError> {
+    ports
+        .into_iter()
+        .map(|port| {
+            Ok(AnnotatedPort {
+                open_handle_id: registry().find_by_path(&port.path),
+                port,
+            })
+        })
+        .collect()
+}
+
+fn enumerate_ports(
+    enumerate: impl FnOnce() -> serialport::Result<Vec<SerialPortInfo>>,
+    filter: &PortFilter,
+) -> Result<Vec<PortInfo>, SerialError> {
+    let sort = filter.sort_by.as_deref().map(PortSort::parse).transpose()?;
+    let ports = match enumerate() {
+        Ok(ports) => ports,
+        Err(_) if filter.tolerate_errors => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let mut ports: Vec<PortInfo> = ports.into_iter().map(port_info).collect();
+    if let Some(sort) = sort {
+        sort.sort(&mut ports);
+    }
+    Ok(ports)
+}
+
+fn port_info(port: SerialPortInfo) -> PortInfo {
+    let (vid, pid, manufacturer, product, serial_number, port_type) = match &port.port_type {
+        SerialPortType::UsbPort(info) => (
+            Some(info.vid),
+            Some(info.pid),
+            info.manufacturer.clone(),
+            info.product.clone(),
+            info.serial_number.clone(),
+            "usb".to_string(),
+        ),
+        SerialPortType::BluetoothPort => (None, None, None, None, None, "bluetooth".to_string()),
+        SerialPortType::PciPort => (None, None, None, None, None, "pci".to_string()),
+        SerialPortType::Unknown => (None, None, None, None, None, "unknown".to_string()),
+    };
+
+    PortInfo {
+        stable_id: stable_port_id(&port.port_name, vid.zip(pid), serial_number.as_deref()),
+        path: port.port_name,
+        vid,
+        pid,
+        manufacturer,
+        product,
+        serial_number,
+        port_type,
+    }
+}
+
+/// Most stable identifier available for a port, in order of preference:
+///
+/// 1. `usb:VID:PID:SERIAL` when the USB device reports a serial number. This
+///    follows the device to any port, but two units flashed with the same
+///    serial collide.
+/// 2. `usb:VID:PID@PATH` for USB devices without one. `serialport` does not
+///    report the USB location, so the path stands in for it and the id only
+///    holds while the OS keeps assigning the same path.
+/// 3. `path:PATH` for everything else, which is as stable as the OS naming.
+fn stable_port_id(path: &str, usb_ids: Option<(u16, u16)>, serial_number: Option<&str>) -> String {
+    match (usb_ids, serial_number) {
+        (Some((vid, pid)), Some(serial)) if !serial.is_empty() => {
+            format!("usb:{vid:04x}:{pid:04x}:{serial}")
+        }
+        (Some((vid, pid)), _) => format!("usb:{vid:04x}:{pid:04x}@{path}"),
+        (None, _) => format!("path:{path}"),
     }
+}
+
+pub const SETTLE_TIMEOUT_EVENT: &str = "serial://settle-timeout";
+pub const DEFAULT_SETTLE_TIMEOUT_MS: u64 = 2_000;
+
+/// The settle phase of an open ran out before the line went quiet.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettleTimeout {
+    pub path: String,
+    pub label: String,
+    pub discarded_bytes: usize,
+    pub timeout_ms: u64,
+}
 
-    Ok(out)
+#[tauri::command]
+pub fn open_device(
+    app: tauri::AppHandle,
+    path: String,
+    config: SerialConfig,
+) -> Result<u64, SerialError> {
+    config.protocol.validate()?;
+    if let Some(existing) = check_duplicate(&path, &config)? {
+        return Ok(existing);
+    }
+    let (port, line_errors) = open_port_counted(&path, &config)?;
+    register_port(path, port, line_errors, config, &events::app_sink(app))
 }
 
+/// Like `open_device`, but retries transient failures (see
+/// `is_transient_open_error`) up to `attempts` times, `delay_ms` apart.
 #[tauri::command]
-pub fn open_device(path: String, config: SerialConfig) -> Result<u64, SerialError> {
-    let builder = serialport::new(&path, config.baud_rate)
+pub fn open_device_retry(
+    app: tauri::AppHandle,
+    path: String,
+    config: SerialConfig,
+    attempts: u32,
+    delay_ms: u64,
+) -> Result<u64, SerialError> {
+    config.protocol.validate()?;
+    if let Some(existing) = check_duplicate(&path, &config)? {
+        return Ok(existing);
+    }
+    let (port, line_errors) = retry_open(attempts, Duration::from_millis(delay_ms), || {
+        open_port_counted(&path, &config)
+    })?;
+    register_port(path, port, line_errors, config, &events::app_sink(app))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DuplicatePolicy {
+    Allow,
+    Reuse,
+    Error,
+}
+
+impl DuplicatePolicy {
+    pub(crate) fn parse(policy: Option<&str>) -> Result<Self, SerialError> {
+        match policy {
+            None | Some("allow") => Ok(DuplicatePolicy::Allow),
+            Some("reuse") => Ok(DuplicatePolicy::Reuse),
+            Some("error") => Ok(DuplicatePolicy::Error),
+            Some(other) => Err(SerialError::InvalidConfig {
+                message: format!(
+                    "unknown duplicate-open policy {other:?}; expected allow, reuse or error"
+                ),
+            }),
+        }
+    }
+}
+
+/// Applies `on_duplicate` before anything is opened: `Some(id)` is an existing
+/// handle to hand back instead, `None` means go ahead and open.
+fn check_duplicate(path: &str, config: &SerialConfig) -> Result<Option<u64>, SerialError> {
+    let policy = DuplicatePolicy::parse(config.on_duplicate.as_deref())?;
+    if policy == DuplicatePolicy::Allow {
+        return Ok(None);
+    }
+    match registry().find_by_path(path) {
+        Some(_) if policy == DuplicatePolicy::Error => Err(SerialError::PortBusy {
+            path: path.to_string(),
+        }),
+        existing => Ok(existing),
+    }
+}
+
+/// Whether an open failure may clear up on its own: the device node appearing
+/// late after enumeration, or another process briefly holding the port.
+/// Everything else (bad config, permissions, I/O faults) fails immediately.
+pub fn is_transient_open_error(err: &SerialError) -> bool {
+    matches!(
+        err,
+        SerialError::PortNotFound { .. } | SerialError::PortBusy { .. }
+    )
+}
+
+fn retry_open<T>(
+    attempts: u32,
+    delay: Duration,
+    mut open: impl FnMut() -> Result<T, SerialError>,
+) -> Result<T, SerialError> {
+    let mut remaining = attempts.max(1);
+    loop {
+        remaining -= 1;
+        match open() {
+            Err(err) if remaining > 0 && is_transient_open_error(&err) => {
+                std::thread::sleep(delay);
+            }
+            result => return result,
+        }
+    }
+}
+
+pub(crate) fn open_port(
+    path: &str,
+    config: &SerialConfig,
+) -> Result<Box<dyn SerialPort>, SerialError> {
+    open_port_counted(path, config).map(|(port, _)| port)
+}
+
+/// `open_port`, plus a reader for the driver's line-error counters where the
+/// platform and driver have them.
+pub(crate) fn open_port_counted(
+    path: &str,
+    config: &SerialConfig,
+) -> Result<(Box<dyn SerialPort>, Option<LineErrors>), SerialError> {
+    let access = AccessMode::parse(config.access_mode.as_deref())?;
+    let read_buffer = read_buffer_size(config.read_buffer_size)?;
+    let target = if config.resolve_symlinks {
+        resolve_port_path(path)?
+    } else {
+        path.to_string()
+    };
+    let timeout = if config.nonblocking {
+        Duration::ZERO
+    } else {
+        Duration::from_millis(config.read_timeout_ms)
+    };
+    let builder = serialport::new(&target, config.baud_rate)
         .data_bits(config.data_bits)
         .parity(config.parity)
         .stop_bits(config.stop_bits)
         .flow_control(FlowControl::None)
-        .timeout(Duration::from_millis(config.read_timeout_ms));
+        .timeout(timeout);
 
-    let port = builder.open().map_err(|err| match err.kind {
-        serialport::ErrorKind::NoDevice => SerialError::PortNotFound { path: path.clone() },
-        serialport::ErrorKind::Io(std::io::ErrorKind::PermissionDenied) => {
-            SerialError::PortBusy { path: path.clone() }
-        }
-        _ => SerialError::from(err),
-    })?;
-    registry().insert(port)
+    builder
+        .open_native()
+        .map_err(|err| match err.kind {
+            // serialport reports both a missing node and a port locked by another
+            // process as `NoDevice`; the port still enumerating means the latter.
+            serialport::ErrorKind::NoDevice if is_enumerated(&target) => SerialError::PortBusy {
+                path: path.to_string(),
+            },
+            serialport::ErrorKind::NoDevice
+            | serialport::ErrorKind::Io(std::io::ErrorKind::NotFound) => {
+                SerialError::PortNotFound {
+                    path: path.to_string(),
+                }
+            }
+            serialport::ErrorKind::Io(std::io::ErrorKind::PermissionDenied) => {
+                SerialError::PermissionDenied {
+                    path: path.to_string(),
+                }
+            }
+            _ => SerialError::from(err),
+        })
+        .map(|port| {
+            let line_errors = LineErrors::attach(&port);
+            let port = access.wrap(Box::new(BufferedPort::new(Box::new(port), read_buffer)));
+            (port, line_errors)
+        })
 }
 
-#[tauri::command]
-pub fn close_device(handle_id: u64) -> Result<(), SerialError> {
-    let _ = registry().remove(handle_id)?;
+/// Follows `path` to the device node when it is a symlink; any other path,
+/// including names that are not files at all (`COM3`), is returned as is. A
+/// link whose target is missing, as while udev re-creates it, is
+/// `PortNotFound` for the link.
+pub(crate) fn resolve_port_path(path: &str) -> Result<String, SerialError> {
+    let is_link = std::fs::symlink_metadata(path)
+        .map(|meta| meta.file_type().is_symlink())
+        .unwrap_or(false);
+    if !is_link {
+        return Ok(path.to_string());
+    }
+    std::fs::canonicalize(path)
+        .map(|target| target.to_string_lossy().into_owned())
+        .map_err(|_| SerialError::PortNotFound {
+            path: path.to_string(),
+        })
+}
+
+fn is_enumerated(path: &str) -> bool {
+    serialport::available_ports()
+        .map(|ports| ports.iter().any(|port| port.port_name == path))
+        .unwrap_or(false)
+}
+
+fn register_port(
+    path: String,
+    mut port: Box<dyn SerialPort>,
+    line_errors: Option<LineErrors>,
+    config: SerialConfig,
+    sink: &EventSink,
+) -> Result<u64, SerialError> {
+    // On failure the port is dropped here, which closes it before returning.
+    apply_initial_control_lines(&mut *port, &config)?;
+    settle(&mut *port, &path, &config, sink)?;
+    let resolved_path = config.resolve_symlinks.then(|| port.name()).flatten();
+    let mut entry = DeviceEntry::new(path, port, config.protocol.clone());
+    entry.resolved_path = resolved_path;
+    entry.line_errors = line_errors;
+    if let Some(label) = &config.label {
+        entry.label = label.clone();
+    }
+    entry.retry = config.retry.clone();
+    entry.nonblocking = config.nonblocking;
+    entry.flush_before_send = config.flush_before_send;
+    entry.auto_flush = config.auto_flush;
+    entry.inter_byte_delay = config.inter_byte_delay_us.map(Duration::from_micros);
+    entry.write_retries = config.write_retries;
+    send_init_frames(&mut entry, &config)?;
+    entry.open_config = Some(config);
+    Ok(registry().insert(entry))
+}
+
+/// Drains input until `settle_quiet_ms` of silence. Running out of time is
+/// only a warning: the device may just be chatty.
+fn settle(
+    port: &mut dyn SerialPort,
+    path: &str,
+    config: &SerialConfig,
+    sink: &EventSink,
+) -> Result<(), SerialError> {
+    let Some(quiet_ms) = config.settle_quiet_ms else {
+        return Ok(());
+    };
+    let timeout_ms = config
+        .settle_timeout_ms
+        .unwrap_or(DEFAULT_SETTLE_TIMEOUT_MS);
+    let drained = drain_port(
+        port,
+        Duration::from_millis(quiet_ms),
+        Duration::from_millis(timeout_ms),
+    )?;
+    if !drained.quiet {
+        events::emit(
+            sink,
+            SETTLE_TIMEOUT_EVENT,
+            &SettleTimeout {
+                path: path.to_string(),
+                label: config.label.clone().unwrap_or_else(|| path.to_string()),
+                discarded_bytes: drained.discarded,
+                timeout_ms,
+            },
+        );
+    }
     Ok(())
 }
 
+/// Label for event payloads emitted outside the device lock; `None` once the
+/// handle is gone.
+pub(crate) fn handle_label(handle_id: u64) -> Option<String> {
+    let handle = registry().get(handle_id)?;
+    let entry = handle.lock().unwrap_or_else(PoisonError::into_inner);
+    Some(entry.label.clone())
+}
+
+/// Sets the name the handle's events carry; `None` goes back to the path.
 #[tauri::command]
-pub fn flush_device(handle_id: u64) -> Result<(), SerialError> {
-    let port = registry()
-        .get(handle_id)?
-        .ok_or(SerialError::InvalidHandle { handle_id })?;
-    let port = port.lock().map_err(|_| {
-        let _ = registry().remove(handle_id);
-        poisoned_device_lock(handle_id, "flush_device")
-    })?;
-    port.clear(ClearBuffer::All)?;
+pub fn set_handle_label(handle_id: u64, label: Option<String>) -> Result<(), SerialError> {
+    with_entry(handle_id, "set_handle_label", |entry| {
+        entry.label = label.unwrap_or_else(|| entry.path.clone());
+        Ok(())
+    })
+}
+
+/// Device bring-up: sends `config.init_frames` in order, stopping at the
+/// first failure.
+pub(crate) fn send_init_frames(
+    entry: &mut DeviceEntry,
+    config: &SerialConfig,
+) -> Result<(), SerialError> {
+    for (index, payload) in config.init_frames.iter().enumerate() {
+        if !config.init_await_ack {
+            send_unanswered(entry, payload)?;
+            continue;
+        }
+        let response = exchange(entry, payload)?;
+        if response.first() == Some(&MSG_ERROR) {
+            return Err(SerialError::InvalidResponse {
+                message: format!(
+                    "device rejected init frame {index} (error code {:?})",
+                    response.get(1)
+                ),
+            });
+        }
+    }
     Ok(())
 }
 
+/// Looks up an existing handle by the path it was opened on, so a frontend that
+/// lost the id can reattach instead of opening the port twice.
 #[tauri::command]
-pub fn send_request(handle_id: u64, payload: Vec<u8>) -> Result<Vec<u8>, SerialError> {
-    if payload.is_empty() {
-        return Err(SerialError::InvalidConfig {
-            message: "payload must include message type".to_string(),
-        });
+pub fn handle_for_path(path: String) -> Result<Option<u64>, SerialError> {
+    Ok(registry().find_by_path(&path))
+}
+
+/// What was still buffered on a handle when it closed.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CloseResult {
+    /// Input bytes waiting unread in the driver.
+    pub leftover_bytes: Vec<u8>,
+    /// Stream frames never drained by the frontend.
+    pub unread_frames: Vec<Vec<u8>>,
+    /// Steps that failed without stopping the close, e.g. a control line
+    /// that could not be set.
+    pub warnings: Vec<String>,
+}
+
+/// Closes the handle, optionally driving DTR/RTS to a known-safe level first.
+/// Control-line failures are reported in `warnings` rather than returned so
+/// the port is always released. With `keep_leftover`, buffered input is read
+/// out and returned instead of being discarded with the port.
+#[tauri::command]
+pub fn close_device(
+    handle_id: u64,
+    final_dtr: Option<bool>,
+    final_rts: Option<bool>,
+    keep_leftover: Option<bool>,
+) -> Result<CloseResult, SerialError> {
+    let Some(port) = registry().remove(handle_id) else {
+        return Ok(CloseResult::default());
+    };
+    let keep_leftover = keep_leftover.unwrap_or(false);
+    if final_dtr.is_none() && final_rts.is_none() && !keep_leftover {
+        return Ok(CloseResult::default());
+    }
+    let mut result = CloseResult::default();
+    match port.lock() {
+        Ok(mut entry) => {
+            if keep_leftover {
+                result.leftover_bytes = take_leftover(&mut *entry.port, &mut result.warnings);
+                result.unread_frames = entry.unsolicited.drain();
+            }
+            if let Some(level) = final_dtr {
+                if let Err(err) = entry.port.write_data_terminal_ready(level) {
+                    result.warnings.push(format!("failed to set DTR: {err}"));
+                }
+            }
+            if let Some(level) = final_rts {
+                if let Err(err) = entry.port.write_request_to_send(level) {
+                    result.warnings.push(format!("failed to set RTS: {err}"));
+                }
+            }
+        }
+        Err(_) => result
+            .warnings
+            .push("device lock poisoned; closed without final steps".to_string()),
+    }
+    Ok(result)
+}
+
+/// Reads whatever input is already waiting, without waiting for more. A read
+/// failure is added to `warnings` and ends the salvage with what was
+/// collected so far.
+fn take_leftover(port: &mut dyn SerialPort, warnings: &mut Vec<String>) -> Vec<u8> {
+    let mut leftover = Vec::new();
+    loop {
+        let waiting = match port.bytes_to_read() {
+            Ok(0) => break,
+            Ok(waiting) => waiting as usize,
+            Err(err) => {
+                warnings.push(format!("cannot query leftover input: {err}"));
+                break;
+            }
+        };
+        let start = leftover.len();
+        leftover.resize(start + waiting, 0);
+        match port.read(&mut leftover[start..]) {
+            Ok(n) => leftover.truncate(start + n),
+            Err(err) => {
+                warnings.push(format!("failed to read leftover input: {err}"));
+                leftover.truncate(start);
+                break;
+            }
+        }
+    }
+    leftover
+}
+
+/// Drives DTR/RTS to their configured open-time levels before any data flows.
+pub(crate) fn apply_initial_control_lines(
+    port: &mut dyn SerialPort,
+    config: &SerialConfig,
+) -> Result<(), SerialError> {
+    if let Some(level) = config.initial_dtr {
+        port.write_data_terminal_ready(level)?;
     }
+    if let Some(level) = config.initial_rts {
+        port.write_request_to_send(level)?;
+    }
+    Ok(())
+}
 
+/// Locks the registry entry for `handle_id` and runs `f` against it. A poisoned
+/// device lock drops the handle so the frontend is forced to reconnect.
+pub(crate) fn with_entry<T>(
+    handle_id: u64,
+    context: &str,
+    f: impl FnOnce(&mut DeviceEntry) -> Result<T, SerialError>,
+) -> Result<T, SerialError> {
     let port = registry()
-        .get(handle_id)?
+        .get(handle_id)
         .ok_or(SerialError::InvalidHandle { handle_id })?;
-    let mut port = port.lock().map_err(|_| {
+    let mut entry = port.lock().map_err(|_| {
         let _ = registry().remove(handle_id);
-        poisoned_device_lock(handle_id, "send_request")
+        poisoned_device_lock(handle_id, context)
     })?;
+    f(&mut entry)
+}
 
-    // Clear any stale data from previous failed reads before sending
-    let _ = port.clear(ClearBuffer::Input);
+#[tauri::command]
+pub fn flush_device(handle_id: u64) -> Result<(), SerialError> {
+    with_entry(handle_id, "flush_device", |entry| {
+        entry.port.clear(ClearBuffer::All)?;
+        Ok(())
+    })
+}
 
-    let frame = build_frame(&payload)?;
-    port.write_all(&frame)?;
-    port.flush()?;
+/// Clears the input buffer only when more than `stale_threshold_bytes` are
+/// waiting, so a reply that is just starting to arrive is left alone.
+/// Returns the number of bytes discarded.
+#[tauri::command]
+pub fn flush_if_stale(handle_id: u64, stale_threshold_bytes: u32) -> Result<u32, SerialError> {
+    with_entry(handle_id, "flush_if_stale", |entry| {
+        let waiting = entry.port.bytes_to_read()?;
+        if waiting <= stale_threshold_bytes {
+            return Ok(0);
+        }
+        entry.port.clear(ClearBuffer::Input)?;
+        Ok(waiting)
+    })
+}
 
-    read_frame(&mut **port)
+/// Line errors counted by the driver since the previous `line_status` call.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineStatus {
+    /// The platform cannot report line errors; every count is zero.
+    pub unsupported: bool,
+    pub breaks: u32,
+    pub framing_errors: u32,
+    pub parity_errors: u32,
+    pub overruns: u32,
 }
 
-fn build_frame(payload: &[u8]) -> Result<Vec<u8>, SerialError> {
-    let payload_len = payload.len();
-    if payload_len > (1 + MAX_PAYLOAD_LEN) {
-        return Err(SerialError::PayloadTooLarge);
-    }
+/// Returns and clears the accumulated line-error counts for the handle.
+#[tauri::command]
+pub fn line_status(handle_id: u64) -> Result<LineStatus, SerialError> {
+    with_entry(handle_id, "line_status", |entry| {
+        match &mut entry.line_errors {
+            Some(counters) => Ok(counters.take()?),
+            None => Ok(LineStatus {
+                unsupported: true,
+                ..LineStatus::default()
+            }),
+        }
+    })
+}
 
-    let len_field = (payload_len + 1) as u8;
-    let mut frame = Vec::with_capacity(1 + 1 + payload_len + 1);
-    frame.push(VSCOPE_SYNC_BYTE);
-    frame.push(len_field);
-    frame.extend_from_slice(payload);
-    let crc = crc8(payload);
-    frame.push(crc);
-    Ok(frame)
+#[tauri::command]
+pub fn send_request(handle_id: u64, payload: Vec<u8>) -> Result<Vec<u8>, SerialError> {
+    with_entry(handle_id, "send_request", |entry| exchange(entry, &payload))
 }
 
-fn read_frame(port: &mut dyn SerialPort) -> Result<Vec<u8>, SerialError> {
-    let deadline = Instant::now() + port.timeout();
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimedResponse {
+    pub payload: Vec<u8>,
+    /// From the start of the write to the parsed response, for the attempt
+    /// that succeeded; retries before it are not included.
+    pub rtt_us: u64,
+}
+
+/// `send_request` that also reports the round-trip time.
+#[tauri::command]
+pub fn send_request_timed(handle_id: u64, payload: Vec<u8>) -> Result<TimedResponse, SerialError> {
+    with_entry(handle_id, "send_request_timed", |entry| {
+        let payload = exchange(entry, &payload)?;
+        let rtt = entry.last_rtt.unwrap_or_default();
+        Ok(TimedResponse {
+            payload,
+            rtt_us: rtt.as_micros() as u64,
+        })
+    })
+}
 
+/// Refuses an empty payload unless the protocol allows one, then applies the
+/// message policy to the TYPE byte when there is one.
+pub(crate) fn check_outgoing(entry: &DeviceEntry, payload: &[u8]) -> Result<(), SerialError> {
+    match payload.first() {
+        Some(&message_type) => entry.policy.check(message_type),
+        None if entry.protocol.allow_empty_payload => entry.policy.check_empty(),
+        None => Err(SerialError::InvalidConfig {
+            message: "payload must include message type".to_string(),
+        }),
+    }
+}
+
+/// One framed request/response round trip on an already-locked entry.
+pub(crate) fn exchange(entry: &mut DeviceEntry, payload: &[u8]) -> Result<Vec<u8>, SerialError> {
+    check_outgoing(entry, payload)?;
+    check_stream_conflict(entry)?;
+
+    let frame = build_frame_with(payload, &entry.protocol)?;
+    if let Some(limiter) = &mut entry.rate_limit {
+        limiter.acquire()?;
+    }
+    let crc = frame_crc(payload, &entry.protocol);
+    let deadline = entry
+        .retry
+        .deadline_ms
+        .map(|ms| Instant::now() + Duration::from_millis(ms));
+    entry.stats.record_request(payload);
+    let retry = &entry.retry;
+    let shared = retry.crc_retries.is_none() && retry.timeout_retries.is_none();
+    let mut crc_left = retry.crc_retries.unwrap_or(retry.max_retries);
+    let mut timeout_left = retry.timeout_retries.unwrap_or(retry.max_retries);
     loop {
-        if Instant::now() >= deadline {
-            return Err(SerialError::Timeout);
+        let result = attempt(entry, &frame, crc, deadline);
+        match &result {
+            Ok(response) => {
+                entry.stats.record_response(response);
+                entry.last_frame = Some(TimedFrame::now(response));
+            }
+            Err(err) => entry.stats.record_error(err),
         }
-
-        let mut sync = [0u8; 1];
-        port.read_exact(&mut sync)?;
-        if sync[0] != VSCOPE_SYNC_BYTE {
-            continue;
+        let timed_out = match &result {
+            Err(SerialError::Timeout) => true,
+            Err(SerialError::CrcMismatch) => false,
+            _ => return result,
+        };
+        let left = if timed_out { timeout_left } else { crc_left };
+        if left == 0 {
+            return result;
         }
-
-        let mut len_byte = [0u8; 1];
-        port.read_exact(&mut len_byte)?;
-        let len = len_byte[0] as usize;
-        if !(2..=MAX_FRAME_LEN).contains(&len) {
-            continue;
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(SerialError::Timeout);
         }
-
-        let mut buf = vec![0u8; len];
-        port.read_exact(&mut buf)?;
-
-        let payload_end = len - 1;
-        let crc = buf[payload_end];
-        let calc = crc8(&buf[..payload_end]);
-        if crc != calc {
-            return Err(SerialError::CrcMismatch);
+        if shared {
+            // One budget for both: the counters start equal and move together.
+            crc_left = left - 1;
+            timeout_left = left - 1;
+        } else if timed_out {
+            timeout_left -= 1;
+        } else {
+            crc_left -= 1;
         }
-
-        return Ok(buf[..payload_end].to_vec());
+        entry.stats.record_retry();
     }
 }
 
-const CRC8_LUT: [u8; 256] = [
-    0x00, 0xD5, 0x7F, 0xAA, 0xFE, 0x2B, 0x81, 0x54, 0x29, 0xFC, 0x56, 0x83, 0xD7, 0x02, 0xA8, 0x7D,
-    0x52, 0x87, 0x2D, 0xF8, 0xAC, 0x79, 0xD3, 0x06, 0x7B, 0xAE, 0x04, 0xD1, 0x85, 0x50, 0xFA, 0x2F,
-    0xA4, 0x71, 0xDB, 0x0E, 0x5A, 0x8F, 0x25, 0xF0, 0x8D, 0x58, 0xF2, 0x27, 0x73, 0xA6, 0x0C, 0xD9,
-    0xF6, 0x23, 0x89, 0x5C, 0x08, 0xDD, 0x77, 0xA2, 0xDF, 0x0A, 0xA0, 0x75, 0x21, 0xF4, 0x5E, 0x8B,
-    0x9D, 0x48, 0xE2, 0x37, 0x63, 0xB6, 0x1C, 0xC9, 0xB4, 0x61, 0xCB, 0x1E, 0x4A, 0x9F, 0x35, 0xE0,
-    0xCF, 0x1A, 0xB0, 0x65, 0x31, 0xE4, 0x4E, 0x9B, 0xE6, 0x33, 0x99, 0x4C, 0x18, 0xCD, 0x67, 0xB2,
-    0x39, 0xEC, 0x46, 0x93, 0xC7, 0x12, 0xB8, 0x6D, 0x10, 0xC5, 0x6F, 0xBA, 0xEE, 0x3B, 0x91, 0x44,
-    0x6B, 0xBE, 0x14, 0xC1, 0x95, 0x40, 0xEA, 0x3F, 0x42, 0x97, 0x3D, 0xE8, 0xBC, 0x69, 0xC3, 0x16,
-    0xEF, 0x3A, 0x90, 0x45, 0x11, 0xC4, 0x6E, 0xBB, 0xC6, 0x13, 0xB9, 0x6C, 0x38, 0xED, 0x47, 0x92,
-    0xBD, 0x68, 0xC2, 0x17, 0x43, 0x96, 0x3C, 0xE9, 0x94, 0x41, 0xEB, 0x3E, 0x6A, 0xBF, 0x15, 0xC0,
-    0x4B, 0x9E, 0x34, 0xE1, 0xB5, 0x60, 0xCA, 0x1F, 0x62, 0xB7, 0x1D, 0xC8, 0x9C, 0x49, 0xE3, 0x36,
-    0x19, 0xCC, 0x66, 0xB3, 0xE7, 0x32, 0x98, 0x4D, 0x30, 0xE5, 0x4F, 0x9A, 0xCE, 0x1B, 0xB1, 0x64,
-    0x72, 0xA7, 0x0D, 0xD8, 0x8C, 0x59, 0xF3, 0x26, 0x5B, 0x8E, 0x24, 0xF1, 0xA5, 0x70, 0xDA, 0x0F,
-    0x20, 0xF5, 0x5F, 0x8A, 0xDE, 0x0B, 0xA1, 0x74, 0x09, 0xDC, 0x76, 0xA3, 0xF7, 0x22, 0x88, 0x5D,
-    0xD6, 0x03, 0xA9, 0x7C, 0x28, 0xFD, 0x57, 0x82, 0xFF, 0x2A, 0x80, 0x55, 0x01, 0xD4, 0x7E, 0xAB,
-    0x84, 0x51, 0xFB, 0x2E, 0x7A, 0xAF, 0x05, 0xD0, 0xAD, 0x78, 0xD2, 0x07, 0x53, 0x86, 0x2C, 0xF9,
-];
-
-fn crc8(data: &[u8]) -> u8 {
-    let mut crc = 0u8;
-    for &byte in data {
-        crc = CRC8_LUT[(crc ^ byte) as usize];
+/// One transaction, with the read timeout shortened if the overall deadline
+/// would otherwise be overrun.
+fn attempt(
+    entry: &mut DeviceEntry,
+    frame: &[u8],
+    crc: u8,
+    deadline: Option<Instant>,
+) -> Result<Vec<u8>, SerialError> {
+    let Some(deadline) = deadline else {
+        return transact(entry, frame, Some(crc));
+    };
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    if remaining.is_zero() {
+        return Err(SerialError::Timeout);
     }
-    crc
+    let original_timeout = entry.port.timeout();
+    if remaining >= original_timeout {
+        return transact(entry, frame, Some(crc));
+    }
+    entry.port.set_timeout(remaining)?;
+    let result = transact(entry, frame, Some(crc));
+    let restored = entry.port.set_timeout(original_timeout);
+    let response = result?;
+    restored?;
+    Ok(response)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn crc8_empty() {
-        assert_eq!(crc8(&[]), 0x00);
+/// Writes `frame` as-is and parses the device's reply with the framing layer.
+/// With `crc`, the frame becomes `last_tx` once it has been written.
+fn transact(
+    entry: &mut DeviceEntry,
+    frame: &[u8],
+    crc: Option<u8>,
+) -> Result<Vec<u8>, SerialError> {
+    if entry.nonblocking {
+        return Err(SerialError::InvalidConfig {
+            message: "framed requests need a blocking handle; use read_raw on nonblocking handles"
+                .to_string(),
+        });
     }
+    let DeviceEntry {
+        port,
+        protocol,
+        flush_before_send,
+        inter_byte_delay,
+        write_retries,
+        response_policy,
+        last_rtt,
+        last_tx,
+        ..
+    } = entry;
 
-    #[test]
-    fn crc8_single_byte() {
-        assert_eq!(crc8(&[0x00]), 0x00);
-        assert_eq!(crc8(&[0x01]), 0xD5);
-        assert_eq!(crc8(&[0xFF]), 0xF9);
+    if *flush_before_send {
+        // Clear any stale data from previous failed reads before sending
+        let _ = port.clear(ClearBuffer::Input);
     }
 
-    #[test]
-    fn crc8_multi_byte() {
-        // Verify deterministic output for multi-byte input
-        assert_eq!(crc8(&[0x01, 0x02, 0x03]), 0x3F);
-        // Different input produces different CRC
-        assert_ne!(crc8(&[0x01, 0x02, 0x03]), crc8(&[0x03, 0x02, 0x01]));
+    let started = Instant::now();
+    write_paced(&mut **port, frame, *inter_byte_delay, *write_retries)?;
+    if let Some(crc) = crc {
+        *last_tx = Some(LastTx {
+            frame: frame.to_vec(),
+            crc,
+        });
     }
+    port.flush()?;
 
-    #[test]
-    fn build_frame_structure() {
-        let payload = vec![0x01, 0x02, 0x03];
-        let frame = build_frame(&payload).unwrap();
+    let deadline = Instant::now() + port.timeout();
+    // `leading_skip_max` bounds only the read straight after the write; once
+    // a frame has been skipped, bytes ahead of the next one are extra bytes.
+    let mut skip_max = protocol.leading_skip_max;
+    loop {
+        let response = read_frame_bounded(&mut **port, protocol, skip_max)?;
+        skip_max = protocol.extra_bytes.skip_max();
+        match response_policy.check(&response) {
+            Ok(()) => {
+                *last_rtt = Some(started.elapsed());
+                return Ok(response);
+            }
+            Err(_) if response_policy.skip_mismatched => {
+                if Instant::now() >= deadline {
+                    return Err(SerialError::Timeout);
+                }
+            }
+            Err(message) => return Err(SerialError::InvalidResponse { message }),
+        }
+    }
+}
 
-        assert_eq!(frame[0], VSCOPE_SYNC_BYTE);
-        // len field = payload_len + 1 (for crc) = 4
-        assert_eq!(frame[1], 0x04);
-        // payload
-        assert_eq!(&frame[2..5], &payload);
-        // crc
-        assert_eq!(frame[5], crc8(&payload));
+/// Writes `bytes` in one go, or one byte at a time with `delay` between bytes.
+/// Each paced byte is flushed so the gap is on the wire, not just between
+/// buffer writes.
+pub(crate) fn write_paced(
+    port: &mut dyn SerialPort,
+    bytes: &[u8],
+    delay: Option<Duration>,
+    retries: u32,
+) -> Result<(), SerialError> {
+    let Some(delay) = delay else {
+        return write_retrying(port, bytes, retries);
+    };
+    for (i, byte) in bytes.iter().enumerate() {
+        if i > 0 {
+            std::thread::sleep(delay);
+        }
+        write_retrying(port, std::slice::from_ref(byte), retries)?;
+        port.flush()?;
     }
+    Ok(())
+}
 
-    #[test]
-    fn build_frame_min_payload() {
-        let payload = vec![0x42]; // single byte (message type)
-        let frame = build_frame(&payload).unwrap();
+/// Pause before retrying a write that failed transiently.
+const WRITE_RETRY_DELAY: Duration = Duration::from_millis(2);
+
+fn is_transient_write_error(kind: ErrorKind) -> bool {
+    matches!(kind, ErrorKind::WouldBlock | ErrorKind::TimedOut)
+        && !is_disconnect(DisconnectKind::Io(kind))
+}
 
-        assert_eq!(frame.len(), 1 + 1 + 1 + 1); // sync + len(1) + payload + crc
-        assert_eq!(frame[0], VSCOPE_SYNC_BYTE);
-        assert_eq!(frame[1], 2); // payload + crc
+/// `write_all` that retries transient failures up to `retries` times,
+/// carrying on from wherever the failed attempt left off.
+fn write_retrying(
+    port: &mut dyn SerialPort,
+    mut bytes: &[u8],
+    retries: u32,
+) -> Result<(), SerialError> {
+    let mut retries_left = retries;
+    while !bytes.is_empty() {
+        match port.write(bytes) {
+            Ok(0) => return Err(io::Error::from(ErrorKind::WriteZero).into()),
+            Ok(n) => bytes = &bytes[n..],
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) if retries_left > 0 && is_transient_write_error(err.kind()) => {
+                retries_left -= 1;
+                std::thread::sleep(WRITE_RETRY_DELAY);
+            }
+            Err(err) => return Err(err.into()),
+        }
     }
+    Ok(())
+}
 
-    #[test]
-    fn build_frame_larger_payload() {
-        let payload = vec![0xAA; 253];
-        let frame = build_frame(&payload).unwrap();
+/// Frames and writes `payload` without waiting for a reply, for messages the
+/// device does not answer.
+#[tauri::command]
+pub fn send_no_reply(handle_id: u64, payload: Vec<u8>) -> Result<(), SerialError> {
+    with_entry(handle_id, "send_no_reply", |entry| {
+        send_unanswered(entry, &payload)
+    })
+}
 
-        assert_eq!(frame.len(), 1 + 1 + 253 + 1);
-        let len_field = frame[1];
-        assert_eq!(len_field, 254); // 253 + 1 for crc
+fn send_unanswered(entry: &mut DeviceEntry, payload: &[u8]) -> Result<(), SerialError> {
+    write_frame(entry, payload)?;
+    if entry.auto_flush {
+        entry.port.flush()?;
     }
+    Ok(())
+}
 
-    #[test]
-    fn build_frame_too_large() {
-        let payload = vec![0xAA; 254];
-        assert!(build_frame(&payload).is_err());
+/// Frames and writes `payload` without flushing, returning the frame bytes.
+fn write_frame(entry: &mut DeviceEntry, payload: &[u8]) -> Result<Vec<u8>, SerialError> {
+    check_outgoing(entry, payload)?;
+    let frame = build_frame_with(payload, &entry.protocol)?;
+    if let Some(limiter) = &mut entry.rate_limit {
+        limiter.acquire()?;
     }
+    write_paced(
+        &mut *entry.port,
+        &frame,
+        entry.inter_byte_delay,
+        entry.write_retries,
+    )?;
+    entry.last_tx = Some(LastTx {
+        frame: frame.clone(),
+        crc: frame_crc(payload, &entry.protocol),
+    });
+    Ok(frame)
+}
+
+/// `send_no_reply` for links that echo TX: reads back as many bytes as the
+/// frame had and fails with `InvalidResponse` unless they match it. A missing
+/// or short echo is a `Timeout`. The write is always flushed.
+#[tauri::command]
+pub fn send_confirm_echo(handle_id: u64, payload: Vec<u8>) -> Result<(), SerialError> {
+    with_entry(handle_id, "send_confirm_echo", |entry| {
+        if entry.flush_before_send {
+            let _ = entry.port.clear(ClearBuffer::Input);
+        }
+        let frame = write_frame(entry, &payload)?;
+        entry.port.flush()?;
+        let mut echo = vec![0u8; frame.len()];
+        entry.port.read_exact(&mut echo)?;
+        match frame
+            .iter()
+            .zip(&echo)
+            .position(|(sent, read)| sent != read)
+        {
+            None => Ok(()),
+            Some(i) => Err(SerialError::InvalidResponse {
+                message: format!(
+                    "echo mismatch at byte {i}: sent 0x{:02X}, read back 0x{:02X}",
+                    frame[i], echo[i]
+                ),
+            }),
+        }
+    })
+}
+
+/// Pushes out anything written since the last flush, for handles opened
+/// with `autoFlush` off.
+#[tauri::command]
+pub fn flush_output(handle_id: u64) -> Result<(), SerialError> {
+    with_entry(handle_id, "flush_output", |entry| {
+        entry.port.flush()?;
+        Ok(())
+    })
+}
+
+/// Sends caller-built frame bytes verbatim (no `build_frame`, so a bad CRC
+/// goes out untouched) and returns the parsed reply payload.
+#[tauri::command]
+pub fn send_raw_frame(handle_id: u64, frame: Vec<u8>) -> Result<Vec<u8>, SerialError> {
+    if frame.is_empty() {
+        return Err(SerialError::InvalidConfig {
+            message: "frame must not be empty".to_string(),
+        });
+    }
+    with_entry(handle_id, "send_raw_frame", |entry| {
+        check_raw_frame(entry, &frame)?;
+        transact(entry, &frame, None)
+    })
+}
+
+/// Applies the message policy to a caller-built frame. TYPE follows the
+/// preamble and LEN, and the CRC too when it is a prefix; a frame too short
+/// to reach it is refused while a policy is set.
+fn check_raw_frame(entry: &DeviceEntry, frame: &[u8]) -> Result<(), SerialError> {
+    let mut type_offset = usize::from(entry.protocol.preamble_count) + 1;
+    if entry.protocol.crc_position == CrcPosition::Prefix {
+        type_offset += 1;
+    }
+    match frame.get(type_offset) {
+        Some(&message_type) => entry.policy.check(message_type),
+        None if !entry.policy.is_restricted() => Ok(()),
+        None => Err(SerialError::InvalidConfig {
+            message: "frame has no message type; refused while a message policy is set".to_string(),
+        }),
+    }
+}
+
+/// The last frame the handle parsed from `send_request` (and the other framed
+/// exchanges) or its stream, without sending anything. `None` until one has
+/// arrived.
+#[tauri::command]
+pub fn last_frame(handle_id: u64) -> Result<Option<TimedFrame>, SerialError> {
+    with_entry(handle_id, "last_frame", |entry| {
+        Ok(entry.last_frame.clone())
+    })
+}
+
+/// The last frame `send_request`, `send_no_reply` or another framed send put
+/// on the wire, with its CRC. `None` until the first send; frames sent
+/// verbatim with `send_raw_frame` are not recorded.
+#[tauri::command]
+pub fn last_tx(handle_id: u64) -> Result<Option<LastTx>, SerialError> {
+    with_entry(handle_id, "last_tx", |entry| Ok(entry.last_tx.clone()))
+}
+
+/// One read of up to `max_len` raw bytes (at most `MAX_RAW_READ`). On a
+/// nonblocking handle "nothing waiting" is `WouldBlock`; on a blocking one it
+/// is `Timeout` after the handle's read timeout.
+#[tauri::command]
+pub fn read_raw(handle_id: u64, max_len: usize) -> Result<Vec<u8>, SerialError> {
+    crate::console::check_read_len(max_len)?;
+    with_entry(handle_id, "read_raw", |entry| {
+        let mut buf = vec![0u8; max_len];
+        loop {
+            match entry.port.read(&mut buf) {
+                Ok(n) => {
+                    buf.truncate(n);
+                    return Ok(buf);
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(err)
+                    if entry.nonblocking
+                        && matches!(
+                            err.kind(),
+                            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                        ) =>
+                {
+                    return Err(SerialError::WouldBlock);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    })
+}
+
+/// Longest single blocking read while draining, so quiet/overall deadlines
+/// are checked promptly regardless of the handle's configured timeout.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Reads and discards input until nothing has arrived for `quiet_ms`, or
+/// `overall_timeout_ms` elapses. Returns the number of bytes discarded.
+#[tauri::command]
+pub fn drain_until_quiet(
+    handle_id: u64,
+    quiet_ms: u64,
+    overall_timeout_ms: u64,
+) -> Result<usize, SerialError> {
+    with_entry(handle_id, "drain_until_quiet", |entry| {
+        drain_port(
+            &mut *entry.port,
+            Duration::from_millis(quiet_ms),
+            Duration::from_millis(overall_timeout_ms),
+        )
+        .map(|drained| drained.discarded)
+    })
+}
+
+pub(crate) struct Drained {
+    pub(crate) discarded: usize,
+    /// The quiet period was reached, rather than the overall timeout.
+    pub(crate) quiet: bool,
+}
+
+pub(crate) fn drain_port(
+    port: &mut dyn SerialPort,
+    quiet: Duration,
+    overall: Duration,
+) -> Result<Drained, SerialError> {
+    let original_timeout = port.timeout();
+    port.set_timeout(DRAIN_POLL_INTERVAL.min(quiet))?;
+    let result = drain_loop(port, quiet, overall);
+    let restored = port.set_timeout(original_timeout);
+    let discarded = result?;
+    restored?;
+    Ok(discarded)
+}
+
+fn drain_loop(
+    port: &mut dyn SerialPort,
+    quiet: Duration,
+    overall: Duration,
+) -> Result<Drained, SerialError> {
+    let start = Instant::now();
+    let deadline = start + overall;
+    let mut last_data = start;
+    let mut discarded = 0;
+    let mut buf = [0u8; 256];
+
+    loop {
+        let now = Instant::now();
+        if now.duration_since(last_data) >= quiet {
+            return Ok(Drained {
+                discarded,
+                quiet: true,
+            });
+        }
+        if now >= deadline {
+            return Ok(Drained {
+                discarded,
+                quiet: false,
+            });
+        }
+        match port.read(&mut buf) {
+            Ok(0) => {}
+            Ok(n) => {
+                discarded += n;
+                last_data = Instant::now();
+            }
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    std::io::ErrorKind::TimedOut
+                        | std::io::ErrorKind::WouldBlock
+                        | std::io::ErrorKind::Interrupted
+                ) => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// Returns the framing parameters currently in effect for a handle.
+#[tauri::command]
+pub fn protocol_config(handle_id: u64) -> Result<ProtocolConfig, SerialError> {
+    with_entry(handle_id, "protocol_config", |entry| {
+        Ok(entry.protocol.clone())
+    })
+}
+
+/// Replaces the framing parameters of an open handle; the next frame sent or
+/// read uses them.
+#[tauri::command]
+pub fn set_protocol_config(handle_id: u64, protocol: ProtocolConfig) -> Result<(), SerialError> {
+    protocol.validate()?;
+    with_entry(handle_id, "set_protocol_config", |entry| {
+        entry.protocol = protocol;
+        Ok(())
+    })
+}
+
+/// Bits one byte occupies on the wire: start bit, data, parity and stop bits.
+fn bits_per_byte(data_bits: DataBits, parity: Parity, stop_bits: StopBits) -> u32 {
+    let data = match data_bits {
+        DataBits::Five => 5,
+        DataBits::Six => 6,
+        DataBits::Seven => 7,
+        DataBits::Eight => 8,
+    };
+    let parity = u32::from(parity != Parity::None);
+    let stop = match stop_bits {
+        StopBits::One => 1,
+        StopBits::Two => 2,
+    };
+    1 + data + parity + stop
+}
+
+fn tx_time_ms(frame_len: usize, bits_per_byte: u32, baud_rate: u32) -> f64 {
+    (frame_len as f64 * f64::from(bits_per_byte) * 1000.0) / f64::from(baud_rate)
+}
+
+/// Time to put a frame carrying `payload_len` bytes (TYPE + PAYLOAD) on the
+/// wire at the handle's line settings, ignoring gaps between bytes other
+/// than a configured inter-byte delay.
+#[tauri::command]
+pub fn estimate_tx_time_ms(handle_id: u64, payload_len: usize) -> Result<f64, SerialError> {
+    with_entry(handle_id, "estimate_tx_time_ms", |entry| {
+        let baud_rate = entry.port.baud_rate()?;
+        if baud_rate == 0 {
+            return Err(SerialError::InvalidConfig {
+                message: "port reports a baud rate of 0".to_string(),
+            });
+        }
+        let bits = bits_per_byte(
+            entry.port.data_bits()?,
+            entry.port.parity()?,
+            entry.port.stop_bits()?,
+        );
+        let frame_len = entry.protocol.frame_len(payload_len);
+        let pacing = entry.inter_byte_delay.map_or(0.0, |delay| {
+            delay.as_secs_f64() * 1000.0 * frame_len.saturating_sub(1) as f64
+        });
+        Ok(tx_time_ms(frame_len, bits, baud_rate) + pacing)
+    })
+}
+
+/// Switches the handle's checksum in place, by the names `identify_checksum`
+/// reports (e.g. `"crc8-smbus"`, `"len-and-payload"`).
+#[tauri::command]
+pub fn set_crc_variant(
+    handle_id: u64,
+    variant: String,
+    coverage: String,
+    init: u8,
+) -> Result<(), SerialError> {
+    let crc_variant = CrcVariant::parse(&variant).ok_or_else(|| SerialError::InvalidConfig {
+        message: format!("unknown crc variant {variant:?}"),
+    })?;
+    let crc_coverage = CrcCoverage::parse(&coverage).ok_or_else(|| SerialError::InvalidConfig {
+        message: format!("unknown crc coverage {coverage:?}"),
+    })?;
+    with_entry(handle_id, "set_crc_variant", |entry| {
+        entry.protocol.crc_variant = crc_variant;
+        entry.protocol.crc_coverage = crc_coverage;
+        entry.protocol.crc_init = init;
+        Ok(())
+    })
+}
+
+/// Reports which checksum variants validate a captured `SYNC | LEN | body | CRC`
+/// frame, for bring-up of devices whose trailer is unknown.
+#[tauri::command]
+pub fn identify_checksum(frame: Vec<u8>) -> Result<Vec<String>, SerialError> {
+    if frame.len() < 4 {
+        return Err(SerialError::InvalidConfig {
+            message: "frame must include sync, len, type and crc".to_string(),
+        });
+    }
+    let len = frame[1];
+    if len as usize != frame.len() - 2 {
+        return Err(SerialError::InvalidConfig {
+            message: format!(
+                "len field {len} does not match {} bytes after it",
+                frame.len() - 2
+            ),
+        });
+    }
+
+    let body = &frame[2..frame.len() - 1];
+    let trailer = frame[frame.len() - 1];
+    Ok(crc::candidates()
+        .filter(|checksum| checksum.compute(frame[0], len, body) == trailer)
+        .map(|checksum| checksum.name())
+        .collect())
+}
+
+/// CRC the device sent on a response next to the one the host computes over
+/// the same bytes with the handle's checksum.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrcConformance {
+    /// TYPE + PAYLOAD of the response.
+    pub response: Vec<u8>,
+    pub device_crc: u8,
+    pub host_crc: u8,
+    pub matches: bool,
+}
+
+/// Sends `payload` and reports whether the response's CRC agrees with the
+/// host's; a disagreement is the result, not an error.
+#[tauri::command]
+pub fn crc_conformance(handle_id: u64, payload: Vec<u8>) -> Result<CrcConformance, SerialError> {
+    with_entry(handle_id, "crc_conformance", |entry| {
+        check_stream_conflict(entry)?;
+        if entry.flush_before_send {
+            let _ = entry.port.clear(ClearBuffer::Input);
+        }
+        write_frame(entry, &payload)?;
+        entry.port.flush()?;
+        let frame = read_frame_unchecked(&mut *entry.port, &entry.protocol)?;
+        Ok(CrcConformance {
+            response: frame.body,
+            device_crc: frame.received_crc,
+            host_crc: frame.computed_crc,
+            matches: frame.received_crc == frame.computed_crc,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::testing;
+    use crate::frame::{build_frame, Endianness, VSCOPE_SYNC_BYTE};
+    use crate::mock_port::MockPort;
 
     #[test]
     fn registry_id_generation() {
@@ -348,4 +1657,1282 @@ mod tests {
         assert_eq!(id1, 1);
         assert_eq!(id2, 2);
     }
+
+    #[test]
+    fn identify_checksum_finds_default_variant() {
+        let frame = build_frame(&[0x01, 0x02, 0x03]).unwrap();
+        assert_eq!(
+            identify_checksum(frame).unwrap(),
+            vec!["crc8-dvb-s2/payload/0x00".to_string()]
+        );
+    }
+
+    #[test]
+    fn identify_checksum_rejects_inconsistent_len() {
+        let mut frame = build_frame(&[0x01, 0x02, 0x03]).unwrap();
+        frame.pop();
+        assert!(matches!(
+            identify_checksum(frame),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+    }
+
+    #[test]
+    fn set_crc_variant_applies_to_next_request() {
+        let swapped = ProtocolConfig {
+            crc_variant: CrcVariant::Crc8Smbus,
+            crc_coverage: CrcCoverage::LenAndPayload,
+            crc_init: 0xFF,
+            ..ProtocolConfig::default()
+        };
+        let port = MockPort::new();
+        port.queue_reply(&build_frame(&[0x01, 0x0A]).unwrap());
+        port.queue_reply(&build_frame_with(&[0x01, 0x0B], &swapped).unwrap());
+        let handle_id = insert_mock(&port);
+
+        assert_eq!(
+            send_request(handle_id, vec![0x01]).unwrap(),
+            vec![0x01, 0x0A]
+        );
+        set_crc_variant(
+            handle_id,
+            "crc8-smbus".to_string(),
+            "len-and-payload".to_string(),
+            0xFF,
+        )
+        .unwrap();
+        assert_eq!(
+            send_request(handle_id, vec![0x01]).unwrap(),
+            vec![0x01, 0x0B]
+        );
+
+        let expected = [
+            build_frame(&[0x01]).unwrap(),
+            build_frame_with(&[0x01], &swapped).unwrap(),
+        ]
+        .concat();
+        assert_eq!(port.written(), expected);
+        assert_eq!(protocol_config(handle_id).unwrap(), swapped);
+    }
+
+    #[test]
+    fn set_crc_variant_rejects_unknown_names() {
+        let handle_id = insert_mock(&MockPort::new());
+        for (variant, coverage) in [("crc16", "payload"), ("crc8-smbus", "everything")] {
+            assert!(matches!(
+                set_crc_variant(handle_id, variant.to_string(), coverage.to_string(), 0),
+                Err(SerialError::InvalidConfig { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn initial_control_lines_applied_after_open() {
+        let mut port = MockPort::new();
+        let config = SerialConfig {
+            initial_dtr: Some(false),
+            initial_rts: Some(true),
+            ..test_config()
+        };
+        apply_initial_control_lines(&mut port, &config).unwrap();
+        let state = port.state.lock().unwrap();
+        assert_eq!(state.dtr, Some(false));
+        assert_eq!(state.rts, Some(true));
+    }
+
+    #[test]
+    fn initial_control_lines_untouched_by_default() {
+        let mut port = MockPort::new();
+        apply_initial_control_lines(&mut port, &test_config()).unwrap();
+        let state = port.state.lock().unwrap();
+        assert_eq!(state.dtr, None);
+        assert_eq!(state.rts, None);
+    }
+
+    #[test]
+    fn initial_control_line_failure_fails_open() {
+        let mut port = MockPort::new();
+        port.state.lock().unwrap().fail_control_lines = true;
+        let config = SerialConfig {
+            initial_dtr: Some(false),
+            ..test_config()
+        };
+        assert!(apply_initial_control_lines(&mut port, &config).is_err());
+    }
+
+    #[test]
+    fn transient_open_error_classification() {
+        let path = || "/dev/ttyUSB0".to_string();
+        let cases = [
+            (SerialError::PortNotFound { path: path() }, true),
+            (SerialError::PortBusy { path: path() }, true),
+            (SerialError::PermissionDenied { path: path() }, false),
+            (SerialError::InvalidHandle { handle_id: 1 }, false),
+            (SerialError::Timeout, false),
+            (SerialError::WouldBlock, false),
+            (SerialError::CrcMismatch, false),
+            (
+                SerialError::IoError {
+                    message: "boom".to_string(),
+                },
+                false,
+            ),
+            (
+                SerialError::InvalidConfig {
+                    message: "bad baud".to_string(),
+                },
+                false,
+            ),
+            (SerialError::PayloadTooLarge, false),
+            (
+                SerialError::InvalidResponse {
+                    message: "short".to_string(),
+                },
+                false,
+            ),
+            (SerialError::PolicyDenied { message_type: 0x40 }, false),
+        ];
+        for (err, transient) in cases {
+            assert_eq!(is_transient_open_error(&err), transient, "{err:?}");
+        }
+    }
+
+    #[test]
+    fn retry_open_retries_transient_errors_until_success() {
+        let mut calls = 0;
+        let result = retry_open(3, Duration::ZERO, || {
+            calls += 1;
+            if calls < 3 {
+                Err(SerialError::PortBusy {
+                    path: "p".to_string(),
+                })
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[test]
+    fn retry_open_stops_on_permanent_error_or_exhaustion() {
+        let mut calls = 0;
+        let result: Result<(), _> = retry_open(5, Duration::ZERO, || {
+            calls += 1;
+            Err(SerialError::PermissionDenied {
+                path: "p".to_string(),
+            })
+        });
+        assert!(matches!(result, Err(SerialError::PermissionDenied { .. })));
+        assert_eq!(calls, 1);
+
+        let mut calls = 0;
+        let result: Result<(), _> = retry_open(3, Duration::ZERO, || {
+            calls += 1;
+            Err(SerialError::PortNotFound {
+                path: "p".to_string(),
+            })
+        });
+        assert!(matches!(result, Err(SerialError::PortNotFound { .. })));
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn close_device_sets_final_lines_before_drop() {
+        let port = MockPort::new();
+        let handle_id = insert_mock(&port);
+        close_device(handle_id, Some(false), Some(true), None).unwrap();
+        assert_eq!(Arc::strong_count(&port.state), 1);
+        let state = port.state.lock().unwrap();
+        assert_eq!(state.dtr, Some(false));
+        assert_eq!(state.rts, Some(true));
+        drop(state);
+        assert!(registry().get(handle_id).is_none());
+    }
+
+    #[test]
+    fn close_device_closes_even_if_final_lines_fail() {
+        let port = MockPort::new();
+        port.state.lock().unwrap().fail_control_lines = true;
+        let handle_id = insert_mock(&port);
+        let result = close_device(handle_id, Some(false), None, None).unwrap();
+        assert_eq!(result.warnings.len(), 1, "{:?}", result.warnings);
+        assert!(result.warnings[0].contains("DTR"));
+        assert_eq!(Arc::strong_count(&port.state), 1);
+        assert!(port.state.lock().unwrap().dtr.is_none());
+    }
+
+    #[test]
+    fn close_device_returns_leftover_input_on_request() {
+        let port = MockPort::with_rx(&[0xC8, 0x05, 0x01]);
+        let handle_id = insert_mock(&port);
+        with_entry(handle_id, "test", |entry| {
+            entry.unsolicited.push(vec![0x07, 0x01]);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(
+            close_device(handle_id, None, None, Some(true)).unwrap(),
+            CloseResult {
+                leftover_bytes: vec![0xC8, 0x05, 0x01],
+                unread_frames: vec![vec![0x07, 0x01]],
+                warnings: Vec::new(),
+            }
+        );
+        assert_eq!(Arc::strong_count(&port.state), 1);
+
+        let port = MockPort::with_rx(&[0xAA]);
+        let handle_id = insert_mock(&port);
+        assert_eq!(
+            close_device(handle_id, None, None, None).unwrap(),
+            CloseResult::default()
+        );
+    }
+
+    #[test]
+    fn duplicate_open_follows_on_duplicate_policy() {
+        let path = "/dev/ttyDUPLICATE0";
+        let existing = insert_mock_at(&MockPort::new(), path, ProtocolConfig::default());
+        let with_policy = |policy: Option<&str>| SerialConfig {
+            on_duplicate: policy.map(str::to_string),
+            ..test_config()
+        };
+
+        assert_eq!(check_duplicate(path, &with_policy(None)).unwrap(), None);
+        assert_eq!(
+            check_duplicate(path, &with_policy(Some("allow"))).unwrap(),
+            None
+        );
+        assert_eq!(
+            check_duplicate(path, &with_policy(Some("reuse"))).unwrap(),
+            Some(existing)
+        );
+        assert!(matches!(
+            check_duplicate(path, &with_policy(Some("error"))),
+            Err(SerialError::PortBusy { path: busy }) if busy == path
+        ));
+        assert!(matches!(
+            check_duplicate(path, &with_policy(Some("replace"))),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+
+        // With nothing open on the path, every policy goes ahead and opens.
+        registry().remove(existing);
+        for policy in ["allow", "reuse", "error"] {
+            assert_eq!(
+                check_duplicate(path, &with_policy(Some(policy))).unwrap(),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn handle_for_path_finds_open_handle() {
+        let first = insert_mock_at(
+            &MockPort::new(),
+            "/dev/ttyLOOKUP0",
+            ProtocolConfig::default(),
+        );
+        let second = insert_mock_at(
+            &MockPort::new(),
+            "/dev/ttyLOOKUP1",
+            ProtocolConfig::default(),
+        );
+        assert_eq!(
+            handle_for_path("/dev/ttyLOOKUP0".to_string()).unwrap(),
+            Some(first)
+        );
+        assert_eq!(
+            handle_for_path("/dev/ttyLOOKUP1".to_string()).unwrap(),
+            Some(second)
+        );
+        assert_eq!(
+            handle_for_path("/dev/ttyLOOKUP2".to_string()).unwrap(),
+            None
+        );
+
+        // A busy device does not hold up the lookup.
+        let busy = registry().get(second).unwrap();
+        let _guard = busy.lock().unwrap();
+        assert_eq!(
+            handle_for_path("/dev/ttyLOOKUP1".to_string()).unwrap(),
+            Some(second)
+        );
+
+        close_device(first, None, None, None).unwrap();
+        assert_eq!(
+            handle_for_path("/dev/ttyLOOKUP0".to_string()).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn poisoned_registry_lock_is_recovered() {
+        // A private registry, so poisoning it cannot disturb other tests.
+        let registry = Registry::new();
+        let first = registry.insert(DeviceEntry::new(
+            "/dev/ttyPOISON0".to_string(),
+            Box::new(MockPort::new()),
+            ProtocolConfig::default(),
+        ));
+        std::thread::scope(|scope| {
+            let panicked = scope
+                .spawn(|| {
+                    let _guard = registry.ports.write().unwrap();
+                    panic!("poison the registry");
+                })
+                .join();
+            assert!(panicked.is_err());
+        });
+        assert!(registry.ports.is_poisoned());
+
+        assert!(registry.get(first).is_some());
+        let second = registry.insert(DeviceEntry::new(
+            "/dev/ttyPOISON1".to_string(),
+            Box::new(MockPort::new()),
+            ProtocolConfig::default(),
+        ));
+        assert_eq!(registry.find_by_path("/dev/ttyPOISON1"), Some(second));
+        assert!(registry.remove(first).is_some());
+        assert!(registry.get(first).is_none());
+    }
+
+    #[test]
+    fn flush_if_stale_only_clears_above_threshold() {
+        let port = MockPort::with_rx(&[0xAA; 4]);
+        let handle_id = insert_mock(&port);
+        assert_eq!(flush_if_stale(handle_id, 4).unwrap(), 0);
+        assert_eq!(port.state.lock().unwrap().rx.len(), 4);
+        assert!(port.state.lock().unwrap().clears.is_empty());
+
+        port.push_rx(&[0xBB; 6]);
+        assert_eq!(flush_if_stale(handle_id, 4).unwrap(), 10);
+        let state = port.state.lock().unwrap();
+        assert!(state.rx.is_empty());
+        assert_eq!(state.clears, vec![ClearBuffer::Input]);
+    }
+
+    #[test]
+    fn line_status_reports_unsupported_on_generic_path() {
+        let handle_id = insert_mock(&MockPort::new());
+        assert_eq!(
+            line_status(handle_id).unwrap(),
+            LineStatus {
+                unsupported: true,
+                ..LineStatus::default()
+            }
+        );
+        assert!(matches!(
+            line_status(u64::MAX),
+            Err(SerialError::InvalidHandle { .. })
+        ));
+    }
+
+    fn set_retry(handle_id: u64, retry: RetryConfig) {
+        with_entry(handle_id, "test", |entry| {
+            entry.retry = retry;
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn send_request_retries_after_timeout() {
+        let port = MockPort::new();
+        port.queue_reply(&[]);
+        port.queue_reply(&build_frame(&[0x04, 0x01]).unwrap());
+        let handle_id = insert_mock(&port);
+        set_retry(
+            handle_id,
+            RetryConfig {
+                max_retries: 2,
+                ..RetryConfig::default()
+            },
+        );
+        assert_eq!(
+            send_request(handle_id, vec![0x04]).unwrap(),
+            vec![0x04, 0x01]
+        );
+        assert_eq!(port.state.lock().unwrap().flush_calls, 2);
+    }
+
+    #[test]
+    fn crc_retries_are_counted_apart_from_timeouts() {
+        let mut bad = build_frame(&[0x04, 0x01]).unwrap();
+        *bad.last_mut().unwrap() ^= 0xFF;
+        let port = MockPort::new();
+        port.queue_reply(&bad);
+        port.queue_reply(&bad);
+        port.queue_reply(&build_frame(&[0x04, 0x01]).unwrap());
+        let handle_id = insert_mock(&port);
+        set_retry(
+            handle_id,
+            RetryConfig {
+                crc_retries: Some(3),
+                timeout_retries: Some(0),
+                ..RetryConfig::default()
+            },
+        );
+        assert_eq!(
+            send_request(handle_id, vec![0x04]).unwrap(),
+            vec![0x04, 0x01]
+        );
+        assert_eq!(port.state.lock().unwrap().flush_calls, 3);
+    }
+
+    #[test]
+    fn timeout_retries_give_up_despite_crc_budget() {
+        let mut port = MockPort::new();
+        port.set_timeout(Duration::from_millis(10)).unwrap();
+        let handle_id = insert_mock(&port);
+        set_retry(
+            handle_id,
+            RetryConfig {
+                crc_retries: Some(5),
+                timeout_retries: Some(2),
+                ..RetryConfig::default()
+            },
+        );
+        assert!(matches!(
+            send_request(handle_id, vec![0x04]),
+            Err(SerialError::Timeout)
+        ));
+        assert_eq!(port.state.lock().unwrap().flush_calls, 3);
+    }
+
+    #[test]
+    fn send_request_deadline_caps_total_retry_time() {
+        let mut port = MockPort::new();
+        port.set_timeout(Duration::from_millis(20)).unwrap();
+        let handle_id = insert_mock(&port);
+        set_retry(
+            handle_id,
+            RetryConfig {
+                max_retries: 20,
+                deadline_ms: Some(50),
+                ..RetryConfig::default()
+            },
+        );
+
+        let started = Instant::now();
+        let result = send_request(handle_id, vec![0x04]);
+        let elapsed = started.elapsed();
+        assert!(matches!(result, Err(SerialError::Timeout)));
+        // Without the cap this would take 21 attempts * 20 ms.
+        assert!(elapsed < Duration::from_millis(150), "took {elapsed:?}");
+        assert!(port.state.lock().unwrap().flush_calls <= 4);
+    }
+
+    fn insert_nonblocking_mock(port: &MockPort) -> u64 {
+        port.state.lock().unwrap().nonblocking = true;
+        let handle_id = insert_mock(port);
+        with_entry(handle_id, "test", |entry| {
+            entry.nonblocking = true;
+            Ok(())
+        })
+        .unwrap();
+        handle_id
+    }
+
+    #[test]
+    fn flush_before_send_controls_stale_input() {
+        let stale = build_frame(&[0x01, 0xAA]).unwrap();
+        let fresh = build_frame(&[0x01, 0xBB]).unwrap();
+
+        let port = MockPort::with_rx(&stale);
+        port.queue_reply(&fresh);
+        let handle_id = insert_mock(&port);
+        assert_eq!(
+            send_request(handle_id, vec![0x01]).unwrap(),
+            vec![0x01, 0xBB]
+        );
+        assert_eq!(port.state.lock().unwrap().clears, vec![ClearBuffer::Input]);
+
+        let port = MockPort::with_rx(&stale);
+        port.queue_reply(&fresh);
+        let handle_id = insert_mock(&port);
+        with_entry(handle_id, "test", |entry| {
+            entry.flush_before_send = false;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(
+            send_request(handle_id, vec![0x01]).unwrap(),
+            vec![0x01, 0xAA]
+        );
+        assert!(port.state.lock().unwrap().clears.is_empty());
+    }
+
+    #[test]
+    fn settle_discards_boot_banner_before_registration() {
+        let port = MockPort::new();
+        port.push_rx(b"boot v1.2\r\n");
+        port.schedule_rx(Duration::from_millis(20), b"ready\r\n");
+        let config = SerialConfig {
+            settle_quiet_ms: Some(60),
+            ..test_config()
+        };
+        let (sink, captured) = testing::capture();
+        let handle_id = register_port(
+            "mock-settle".to_string(),
+            Box::new(port.clone()),
+            None,
+            config,
+            &sink,
+        )
+        .unwrap();
+
+        assert!(captured.lock().unwrap().is_empty());
+        {
+            let state = port.state.lock().unwrap();
+            assert!(state.rx.is_empty() && state.scheduled.is_empty());
+        }
+        port.queue_reply(&build_frame(&[0x01, 0x2A]).unwrap());
+        assert_eq!(
+            send_request(handle_id, vec![0x01]).unwrap(),
+            vec![0x01, 0x2A]
+        );
+    }
+
+    #[test]
+    fn settle_timeout_still_opens_and_warns() {
+        let port = MockPort::new();
+        for i in 1..=10 {
+            port.schedule_rx(Duration::from_millis(i * 10), b"spam");
+        }
+        let config = SerialConfig {
+            settle_quiet_ms: Some(50),
+            settle_timeout_ms: Some(40),
+            ..test_config()
+        };
+        let (sink, captured) = testing::capture();
+        register_port(
+            "mock-chatty".to_string(),
+            Box::new(port.clone()),
+            None,
+            config,
+            &sink,
+        )
+        .unwrap();
+
+        let warnings = testing::wait_for(&captured, SETTLE_TIMEOUT_EVENT, 1, Duration::ZERO);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0]["path"], "mock-chatty");
+        assert_eq!(warnings[0]["timeoutMs"], 40);
+    }
+
+    #[test]
+    fn init_frames_are_sent_in_order_before_registration() {
+        let port = MockPort::new();
+        let config = SerialConfig {
+            init_frames: vec![vec![0x30, 0x01], vec![0x31]],
+            ..test_config()
+        };
+        let (sink, _) = testing::capture();
+        let handle_id = register_port(
+            "mock-init".to_string(),
+            Box::new(port.clone()),
+            None,
+            config,
+            &sink,
+        )
+        .unwrap();
+
+        let mut expected = build_frame(&[0x30, 0x01]).unwrap();
+        expected.extend(build_frame(&[0x31]).unwrap());
+        assert_eq!(port.written(), expected);
+
+        port.queue_reply(&build_frame(&[0x01, 0x2A]).unwrap());
+        assert_eq!(
+            send_request(handle_id, vec![0x01]).unwrap(),
+            vec![0x01, 0x2A]
+        );
+    }
+
+    #[test]
+    fn failed_init_ack_fails_the_open() {
+        let port = MockPort::new();
+        port.queue_reply(&build_frame(&[0x30]).unwrap());
+        port.queue_reply(&build_frame(&[MSG_ERROR, 0x02]).unwrap());
+        let config = SerialConfig {
+            init_frames: vec![vec![0x30], vec![0x31], vec![0x32]],
+            init_await_ack: true,
+            ..test_config()
+        };
+        let path = "mock-init-rejected".to_string();
+        let (sink, _) = testing::capture();
+        assert!(matches!(
+            register_port(path.clone(), Box::new(port.clone()), None, config, &sink),
+            Err(SerialError::InvalidResponse { .. })
+        ));
+        // The third frame never went out, and no handle was registered.
+        let mut expected = build_frame(&[0x30]).unwrap();
+        expected.extend(build_frame(&[0x31]).unwrap());
+        assert_eq!(port.written(), expected);
+        assert_eq!(handle_for_path(path).unwrap(), None);
+    }
+
+    #[test]
+    fn confirm_echo_accepts_matching_echo() {
+        let port = MockPort::new();
+        let frame = build_frame(&[0x05, 0x10, 0x20]).unwrap();
+        port.queue_reply(&frame);
+        let handle_id = insert_mock(&port);
+        send_confirm_echo(handle_id, vec![0x05, 0x10, 0x20]).unwrap();
+        assert_eq!(port.written(), frame);
+    }
+
+    #[test]
+    fn confirm_echo_rejects_corrupted_or_missing_echo() {
+        let port = MockPort::new();
+        let mut corrupted = build_frame(&[0x05, 0x10, 0x20]).unwrap();
+        corrupted[3] ^= 0x01;
+        port.queue_reply(&corrupted);
+        let handle_id = insert_mock(&port);
+        assert!(matches!(
+            send_confirm_echo(handle_id, vec![0x05, 0x10, 0x20]),
+            Err(SerialError::InvalidResponse { message }) if message.contains("byte 3")
+        ));
+        assert!(matches!(
+            send_confirm_echo(handle_id, vec![0x05]),
+            Err(SerialError::Timeout)
+        ));
+    }
+
+    #[test]
+    fn timed_request_reports_round_trip() {
+        let port = MockPort::new();
+        let handle_id = insert_mock(&port);
+        with_entry(handle_id, "test", |entry| {
+            entry.port.set_timeout(Duration::from_millis(500))?;
+            Ok(())
+        })
+        .unwrap();
+        port.schedule_rx(
+            Duration::from_millis(40),
+            &build_frame(&[0x01, 0x2A]).unwrap(),
+        );
+
+        let timed = send_request_timed(handle_id, vec![0x01]).unwrap();
+        assert_eq!(timed.payload, vec![0x01, 0x2A]);
+        assert!(
+            (35_000..300_000).contains(&timed.rtt_us),
+            "rtt {} us",
+            timed.rtt_us
+        );
+    }
+
+    #[test]
+    fn last_frame_tracks_the_latest_response() {
+        let port = MockPort::new();
+        port.queue_reply(&build_frame(&[0x01, 0x10]).unwrap());
+        port.queue_reply(&build_frame(&[0x02, 0x20]).unwrap());
+        let handle_id = insert_mock(&port);
+        assert_eq!(last_frame(handle_id).unwrap(), None);
+
+        send_request(handle_id, vec![0x01]).unwrap();
+        send_request(handle_id, vec![0x02]).unwrap();
+        let last = last_frame(handle_id).unwrap().unwrap();
+        assert_eq!(last.payload, vec![0x02, 0x20]);
+        assert!(last.received_at_ms > 0);
+
+        // A failed exchange leaves the last good frame in place.
+        assert!(send_request(handle_id, vec![0x03]).is_err());
+        assert_eq!(
+            last_frame(handle_id).unwrap().unwrap().payload,
+            vec![0x02, 0x20]
+        );
+    }
+
+    #[test]
+    fn last_tx_caches_the_latest_sent_frame() {
+        let port = MockPort::new();
+        port.queue_reply(&build_frame(&[0x01, 0x10]).unwrap());
+        let handle_id = insert_mock(&port);
+        assert_eq!(last_tx(handle_id).unwrap(), None);
+
+        send_request(handle_id, vec![0x01, 0x02]).unwrap();
+        let sent = last_tx(handle_id).unwrap().unwrap();
+        assert_eq!(sent.crc, crate::crc::crc8(&[0x01, 0x02]));
+        assert_eq!(sent.frame, port.written());
+
+        send_no_reply(handle_id, vec![0x05]).unwrap();
+        let sent = last_tx(handle_id).unwrap().unwrap();
+        assert_eq!(sent.crc, crate::crc::crc8(&[0x05]));
+        assert_eq!(sent.frame, build_frame(&[0x05]).unwrap());
+
+        // A request that never reached the wire is not recorded.
+        port.state
+            .lock()
+            .unwrap()
+            .write_errors
+            .push_back(std::io::ErrorKind::BrokenPipe);
+        assert!(send_request(handle_id, vec![0x07]).is_err());
+        assert_eq!(
+            last_tx(handle_id).unwrap().unwrap().frame,
+            build_frame(&[0x05]).unwrap()
+        );
+    }
+
+    #[test]
+    fn crc_conformance_reports_agreement_and_disagreement() {
+        let port = MockPort::new();
+        let good = build_frame(&[0x01, 0x2A]).unwrap();
+        let mut bad = good.clone();
+        *bad.last_mut().unwrap() ^= 0x5A;
+        port.queue_reply(&good);
+        port.queue_reply(&bad);
+        let handle_id = insert_mock(&port);
+        let expected_crc = *good.last().unwrap();
+
+        assert_eq!(
+            crc_conformance(handle_id, vec![0x01]).unwrap(),
+            CrcConformance {
+                response: vec![0x01, 0x2A],
+                device_crc: expected_crc,
+                host_crc: expected_crc,
+                matches: true,
+            }
+        );
+        assert_eq!(
+            crc_conformance(handle_id, vec![0x01]).unwrap(),
+            CrcConformance {
+                response: vec![0x01, 0x2A],
+                device_crc: expected_crc ^ 0x5A,
+                host_crc: expected_crc,
+                matches: false,
+            }
+        );
+    }
+
+    #[test]
+    fn transient_write_error_is_retried() {
+        let port = MockPort::new();
+        port.state
+            .lock()
+            .unwrap()
+            .write_errors
+            .push_back(ErrorKind::WouldBlock);
+        let handle_id = insert_mock(&port);
+        with_entry(handle_id, "test", |entry| {
+            entry.write_retries = 2;
+            Ok(())
+        })
+        .unwrap();
+        send_no_reply(handle_id, vec![0x05, 0x01]).unwrap();
+        assert_eq!(port.written(), build_frame(&[0x05, 0x01]).unwrap());
+        assert_eq!(port.state.lock().unwrap().write_calls, 2);
+    }
+
+    #[test]
+    fn write_errors_fail_without_retries_or_when_permanent() {
+        let port = MockPort::new();
+        let handle_id = insert_mock(&port);
+        port.state
+            .lock()
+            .unwrap()
+            .write_errors
+            .push_back(ErrorKind::WouldBlock);
+        assert!(send_no_reply(handle_id, vec![0x05]).is_err());
+
+        with_entry(handle_id, "test", |entry| {
+            entry.write_retries = 3;
+            Ok(())
+        })
+        .unwrap();
+        port.state
+            .lock()
+            .unwrap()
+            .write_errors
+            .push_back(ErrorKind::BrokenPipe);
+        assert!(matches!(
+            send_no_reply(handle_id, vec![0x05]),
+            Err(SerialError::Disconnected { .. })
+        ));
+        assert!(port.written().is_empty());
+    }
+
+    #[test]
+    fn writes_wait_for_flush_output_without_auto_flush() {
+        let port = MockPort::new();
+        let handle_id = insert_mock(&port);
+        with_entry(handle_id, "test", |entry| {
+            entry.auto_flush = false;
+            Ok(())
+        })
+        .unwrap();
+
+        send_no_reply(handle_id, vec![0x01]).unwrap();
+        send_no_reply(handle_id, vec![0x02]).unwrap();
+        assert_eq!(port.state.lock().unwrap().flush_calls, 0);
+        assert!(!port.written().is_empty());
+
+        flush_output(handle_id).unwrap();
+        assert_eq!(port.state.lock().unwrap().flush_calls, 1);
+    }
+
+    #[test]
+    fn inter_byte_delay_writes_one_byte_per_call() {
+        let port = MockPort::new();
+        let handle_id = insert_mock(&port);
+        send_no_reply(handle_id, vec![0x01, 0x02]).unwrap();
+        assert_eq!(port.state.lock().unwrap().write_calls, 1);
+
+        let paced = MockPort::new();
+        let handle_id = insert_mock(&paced);
+        with_entry(handle_id, "test", |entry| {
+            entry.inter_byte_delay = Some(Duration::from_micros(100));
+            Ok(())
+        })
+        .unwrap();
+        send_no_reply(handle_id, vec![0x01, 0x02]).unwrap();
+        let frame = build_frame(&[0x01, 0x02]).unwrap();
+        let state = paced.state.lock().unwrap();
+        assert_eq!(state.write_calls, frame.len());
+        assert_eq!(state.written, frame);
+    }
+
+    #[test]
+    fn nonblocking_read_raw_returns_would_block_promptly() {
+        let port = MockPort::new();
+        let handle_id = insert_nonblocking_mock(&port);
+        let started = Instant::now();
+        assert!(matches!(
+            read_raw(handle_id, 16),
+            Err(SerialError::WouldBlock)
+        ));
+        assert!(started.elapsed() < Duration::from_millis(20));
+
+        port.push_rx(&[0x01, 0x02, 0x03]);
+        assert_eq!(read_raw(handle_id, 2).unwrap(), vec![0x01, 0x02]);
+        assert_eq!(read_raw(handle_id, 16).unwrap(), vec![0x03]);
+    }
+
+    #[test]
+    fn blocking_read_raw_times_out() {
+        let handle_id = insert_mock(&MockPort::new());
+        assert!(matches!(read_raw(handle_id, 4), Err(SerialError::Timeout)));
+    }
+
+    #[test]
+    fn read_raw_refuses_oversized_reads() {
+        let port = MockPort::with_rx(&[0x01]);
+        let handle_id = insert_mock(&port);
+        assert!(matches!(
+            read_raw(handle_id, usize::MAX),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+        assert_eq!(read_raw(handle_id, 16).unwrap(), vec![0x01]);
+    }
+
+    #[test]
+    fn nonblocking_handle_rejects_framed_requests() {
+        let port = MockPort::new();
+        let handle_id = insert_nonblocking_mock(&port);
+        assert!(matches!(
+            send_request(handle_id, vec![0x04]),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+        assert!(port.written().is_empty());
+    }
+
+    fn failing_enumerator() -> serialport::Result<Vec<SerialPortInfo>> {
+        Err(serialport::Error::new(
+            serialport::ErrorKind::Unknown,
+            "enumeration denied",
+        ))
+    }
+
+    #[test]
+    fn list_ports_propagates_enumeration_error_by_default() {
+        let result = enumerate_ports(failing_enumerator, &PortFilter::default());
+        assert!(matches!(result, Err(SerialError::IoError { .. })));
+    }
+
+    #[test]
+    fn list_ports_tolerates_enumeration_error_when_asked() {
+        let filter = PortFilter {
+            tolerate_errors: true,
+            ..PortFilter::default()
+        };
+        assert!(enumerate_ports(failing_enumerator, &filter)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn annotated_ports_carry_open_handle_ids() {
+        let open = insert_mock_at(
+            &MockPort::new(),
+            "/dev/ttyANNOT0",
+            ProtocolConfig::default(),
+        );
+        let enumerated = || {
+            Ok(["/dev/ttyANNOT0", "/dev/ttyANNOT1"]
+                .map(|path| SerialPortInfo {
+                    port_name: path.to_string(),
+                    port_type: SerialPortType::PciPort,
+                })
+                .to_vec())
+        };
+        let ports = enumerate_ports(enumerated, &PortFilter::default()).unwrap();
+        let annotated = annotate_ports(ports).unwrap();
+        assert_eq!(annotated[0].port.path, "/dev/ttyANNOT0");
+        assert_eq!(annotated[0].open_handle_id, Some(open));
+        assert_eq!(annotated[1].open_handle_id, None);
+
+        close_device(open, None, None, None).unwrap();
+        let ports = enumerate_ports(enumerated, &PortFilter::default()).unwrap();
+        assert!(annotate_ports(ports)
+            .unwrap()
+            .iter()
+            .all(|port| port.open_handle_id.is_none()));
+    }
+
+    #[test]
+    fn list_ports_maps_enumerated_ports() {
+        let ports = enumerate_ports(
+            || {
+                Ok(vec![SerialPortInfo {
+                    port_name: "/dev/ttyS0".to_string(),
+                    port_type: SerialPortType::PciPort,
+                }])
+            },
+            &PortFilter::default(),
+        )
+        .unwrap();
+        assert_eq!(ports.len(), 1);
+        assert_eq!(ports[0].path, "/dev/ttyS0");
+        assert_eq!(ports[0].port_type, "pci");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_port_path_follows_symlinks() {
+        let dir = std::env::temp_dir().join(format!("vscope-links-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("ttyFAKE0");
+        let link = dir.join("usb-Vendor_Scope_1234-if00");
+        let _ = std::fs::remove_file(&link);
+        std::fs::write(&target, b"").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let link = link.to_str().unwrap();
+
+        assert_eq!(
+            resolve_port_path(link).unwrap(),
+            std::fs::canonicalize(&target).unwrap().to_str().unwrap()
+        );
+        let plain = target.to_str().unwrap();
+        assert_eq!(resolve_port_path(plain).unwrap(), plain);
+        assert_eq!(resolve_port_path("COM3").unwrap(), "COM3");
+
+        std::fs::remove_file(&target).unwrap();
+        assert!(matches!(
+            resolve_port_path(link),
+            Err(SerialError::PortNotFound { path }) if path == link
+        ));
+    }
+
+    #[test]
+    fn stable_id_prefers_serial_then_usb_ids_then_path() {
+        let usb = |serial_number: Option<&str>| SerialPortInfo {
+            port_name: "/dev/ttyACM0".to_string(),
+            port_type: SerialPortType::UsbPort(serialport::UsbPortInfo {
+                vid: 0x0483,
+                pid: 0x5740,
+                serial_number: serial_number.map(String::from),
+                manufacturer: None,
+                product: None,
+            }),
+        };
+        assert_eq!(port_info(usb(Some("A1B2"))).stable_id, "usb:0483:5740:A1B2");
+        assert_eq!(port_info(usb(None)).stable_id, "usb:0483:5740@/dev/ttyACM0");
+        assert_eq!(
+            port_info(usb(Some(""))).stable_id,
+            "usb:0483:5740@/dev/ttyACM0"
+        );
+        let pci = SerialPortInfo {
+            port_name: "/dev/ttyS0".to_string(),
+            port_type: SerialPortType::PciPort,
+        };
+        assert_eq!(port_info(pci).stable_id, "path:/dev/ttyS0");
+    }
+
+    fn mixed_enumeration() -> serialport::Result<Vec<SerialPortInfo>> {
+        let usb = |product: Option<&str>| {
+            SerialPortType::UsbPort(serialport::UsbPortInfo {
+                vid: 0x0483,
+                pid: 0x5740,
+                serial_number: None,
+                manufacturer: None,
+                product: product.map(String::from),
+            })
+        };
+        Ok(vec![
+            SerialPortInfo {
+                port_name: "/dev/ttyS1".to_string(),
+                port_type: SerialPortType::PciPort,
+            },
+            SerialPortInfo {
+                port_name: "/dev/ttyACM1".to_string(),
+                port_type: usb(Some("Scope")),
+            },
+            SerialPortInfo {
+                port_name: "/dev/rfcomm0".to_string(),
+                port_type: SerialPortType::BluetoothPort,
+            },
+            SerialPortInfo {
+                port_name: "/dev/ttyACM0".to_string(),
+                port_type: usb(Some("Bridge")),
+            },
+            SerialPortInfo {
+                port_name: "/dev/ttyUSB0".to_string(),
+                port_type: usb(None),
+            },
+        ])
+    }
+
+    fn sorted_paths(key: &str) -> Vec<String> {
+        let filter = PortFilter {
+            sort_by: Some(key.to_string()),
+            ..PortFilter::default()
+        };
+        enumerate_ports(mixed_enumeration, &filter)
+            .unwrap()
+            .into_iter()
+            .map(|port| port.path)
+            .collect()
+    }
+
+    #[test]
+    fn list_ports_sorts_by_each_key() {
+        assert_eq!(
+            sorted_paths("path"),
+            [
+                "/dev/rfcomm0",
+                "/dev/ttyACM0",
+                "/dev/ttyACM1",
+                "/dev/ttyS1",
+                "/dev/ttyUSB0"
+            ]
+        );
+        assert_eq!(
+            sorted_paths("product"),
+            [
+                "/dev/ttyACM0",
+                "/dev/ttyACM1",
+                "/dev/rfcomm0",
+                "/dev/ttyS1",
+                "/dev/ttyUSB0"
+            ]
+        );
+        assert_eq!(
+            sorted_paths("type"),
+            [
+                "/dev/rfcomm0",
+                "/dev/ttyS1",
+                "/dev/ttyACM0",
+                "/dev/ttyACM1",
+                "/dev/ttyUSB0"
+            ]
+        );
+        assert_eq!(
+            sorted_paths("usb-first"),
+            [
+                "/dev/ttyACM0",
+                "/dev/ttyACM1",
+                "/dev/ttyUSB0",
+                "/dev/rfcomm0",
+                "/dev/ttyS1"
+            ]
+        );
+    }
+
+    #[test]
+    fn list_ports_rejects_unknown_sort_key() {
+        let filter = PortFilter {
+            sort_by: Some("vendor".to_string()),
+            ..PortFilter::default()
+        };
+        assert!(matches!(
+            enumerate_ports(mixed_enumeration, &filter),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+    }
+
+    #[test]
+    fn empty_payload_is_a_heartbeat_only_when_allowed() {
+        let port = MockPort::new();
+        let handle_id = insert_mock(&port);
+        assert!(matches!(
+            send_request(handle_id, Vec::new()),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+        assert!(port.written().is_empty());
+
+        let protocol = ProtocolConfig {
+            allow_empty_payload: true,
+            ..ProtocolConfig::default()
+        };
+        let heartbeat = build_frame_with(&[], &protocol).unwrap();
+        let port = MockPort::new();
+        port.queue_reply(&heartbeat);
+        let handle_id = insert_mock_with(&port, protocol);
+        assert_eq!(
+            send_request(handle_id, Vec::new()).unwrap(),
+            Vec::<u8>::new()
+        );
+        send_no_reply(handle_id, Vec::new()).unwrap();
+        assert_eq!(port.written(), [heartbeat.clone(), heartbeat].concat());
+    }
+
+    #[test]
+    fn tx_time_matches_hand_computed_values() {
+        // 8N1 is 10 bits a byte; a default frame adds SYNC, LEN and CRC.
+        assert_eq!(
+            bits_per_byte(DataBits::Eight, Parity::None, StopBits::One),
+            10
+        );
+        assert_eq!(
+            bits_per_byte(DataBits::Seven, Parity::Even, StopBits::Two),
+            11
+        );
+        assert_eq!(
+            bits_per_byte(DataBits::Eight, Parity::Odd, StopBits::One),
+            11
+        );
+        // 100 bytes of 8N1 at 9600 baud: 1000 bits, 104.1666 ms.
+        assert!((tx_time_ms(100, 10, 9_600) - 1000.0 * 1000.0 / 9_600.0).abs() < 1e-9);
+        assert!((tx_time_ms(12, 11, 19_200) - 6.875).abs() < 1e-9);
+
+        // The mock reports 115200 8N1: (1 + 1 + 9 + 1) bytes * 10 bits.
+        let handle_id = insert_mock(&MockPort::new());
+        let estimate = estimate_tx_time_ms(handle_id, 9).unwrap();
+        assert!((estimate - 120.0 * 1000.0 / 115_200.0).abs() < 1e-9);
+
+        let footed = ProtocolConfig {
+            preamble_count: 3,
+            footer: Some(0x0A),
+            ..ProtocolConfig::default()
+        };
+        let handle_id = insert_mock_with(&MockPort::new(), footed);
+        let estimate = estimate_tx_time_ms(handle_id, 9).unwrap();
+        assert!((estimate - 150.0 * 1000.0 / 115_200.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn send_raw_frame_writes_bytes_verbatim() {
+        let port = MockPort::new();
+        port.queue_reply(&build_frame(&[0x42, 0x01]).unwrap());
+        let id = insert_mock(&port);
+
+        // Deliberately wrong CRC: the frame must still go out untouched.
+        let raw = vec![VSCOPE_SYNC_BYTE, 0x02, 0x42, 0x00];
+        let reply = send_raw_frame(id, raw.clone()).unwrap();
+        assert_eq!(port.written(), raw);
+        assert_eq!(reply, vec![0x42, 0x01]);
+    }
+
+    #[test]
+    fn send_raw_frame_applies_the_message_policy() {
+        let port = MockPort::new();
+        port.queue_reply(&build_frame(&[0x04, 0x01]).unwrap());
+        let id = insert_mock(&port);
+        crate::policy::set_message_policy(id, None, Some(vec![0x40]), None).unwrap();
+
+        assert!(matches!(
+            send_raw_frame(id, vec![VSCOPE_SYNC_BYTE, 0x02, 0x40, 0x00]),
+            Err(SerialError::PolicyDenied { message_type: 0x40 })
+        ));
+        assert!(matches!(
+            send_raw_frame(id, vec![VSCOPE_SYNC_BYTE, 0x02]),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+        assert!(port.written().is_empty());
+
+        let allowed = build_frame(&[0x04]).unwrap();
+        assert_eq!(
+            send_raw_frame(id, allowed.clone()).unwrap(),
+            vec![0x04, 0x01]
+        );
+        assert_eq!(port.written(), allowed);
+    }
+
+    #[test]
+    fn send_raw_frame_rejects_empty_frame() {
+        let id = insert_mock(&MockPort::new());
+        assert!(matches!(
+            send_raw_frame(id, Vec::new()),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+    }
+
+    #[test]
+    fn protocol_config_reflects_open_and_reconfigure() {
+        let opened = ProtocolConfig {
+            sync_byte: 0xAA,
+            max_read_frame_len: 64,
+            ..ProtocolConfig::default()
+        };
+        let id = insert_mock_with(&MockPort::new(), opened.clone());
+        assert_eq!(protocol_config(id).unwrap(), opened);
+
+        let reconfigured = ProtocolConfig {
+            crc_variant: CrcVariant::Crc8Maxim,
+            endianness: Endianness::Big,
+            ..opened
+        };
+        set_protocol_config(id, reconfigured.clone()).unwrap();
+        assert_eq!(protocol_config(id).unwrap(), reconfigured);
+    }
+
+    #[test]
+    fn protocol_config_rejects_unknown_handle_and_invalid_config() {
+        assert!(matches!(
+            protocol_config(u64::MAX),
+            Err(SerialError::InvalidHandle { .. })
+        ));
+        let id = insert_mock(&MockPort::new());
+        let invalid = ProtocolConfig {
+            max_payload_len: 1000,
+            ..ProtocolConfig::default()
+        };
+        assert!(set_protocol_config(id, invalid).is_err());
+    }
+
+    #[test]
+    fn drain_until_quiet_discards_burst_then_returns() {
+        let port = MockPort::new();
+        for i in 0..4 {
+            port.schedule_rx(Duration::from_millis(i * 10), &[0x55; 16]);
+        }
+        let id = insert_mock(&port);
+
+        let start = Instant::now();
+        let discarded = drain_until_quiet(id, 40, 1000).unwrap();
+        let elapsed = start.elapsed();
+
+        assert_eq!(discarded, 64);
+        assert!(elapsed >= Duration::from_millis(70), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(500), "{elapsed:?}");
+        // The handle's own read timeout is restored afterwards.
+        with_entry(id, "test", |entry| {
+            assert_eq!(entry.port.timeout(), Duration::from_millis(50));
+            Ok(())
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn drain_until_quiet_stops_at_overall_timeout() {
+        let port = MockPort::new();
+        for i in 0..40 {
+            port.schedule_rx(Duration::from_millis(i * 5), &[0xAA]);
+        }
+        let id = insert_mock(&port);
+
+        let start = Instant::now();
+        let discarded = drain_until_quiet(id, 50, 60).unwrap();
+        assert!(start.elapsed() < Duration::from_millis(150));
+        assert!(discarded > 0 && discarded < 40, "{discarded}");
+    }
 }
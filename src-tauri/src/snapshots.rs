@@ -0,0 +1,355 @@
+use crate::db;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+
+const MAX_PAGE_LIMIT: u32 = 500;
+/// Differing offsets reported by `diff_snapshots`; the count covers the rest.
+const MAX_DIFF_OFFSETS: usize = 16;
+
+const META_COLUMNS: &str = "id, name, device_names_json, channel_count, sample_count, divider, pre_trig, channel_map_json, trigger_threshold, trigger_channel, trigger_mode, rt_values_json, created_at";
+
+/// Decoded `snapshot_meta` row, shaped like the frontend `SnapshotMeta`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotMeta {
+    pub id: i64,
+    pub name: String,
+    pub device_names: Vec<String>,
+    pub channel_count: u32,
+    pub sample_count: u32,
+    pub divider: u32,
+    pub pre_trig: u32,
+    pub channel_map: Vec<u32>,
+    pub trigger_threshold: f64,
+    pub trigger_channel: u32,
+    pub trigger_mode: u32,
+    pub rt_values: Vec<f64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotPage {
+    pub items: Vec<SnapshotMeta>,
+    pub total: u64,
+    pub offset: u32,
+    pub limit: u32,
+}
+
+/// Maps a client-supplied order key to a fixed ORDER BY clause so nothing
+/// user-controlled is ever interpolated into SQL.
+fn order_clause(order: &str) -> Result<&'static str, String> {
+    match order {
+        "created_at_desc" => Ok("created_at DESC, id DESC"),
+        "created_at_asc" => Ok("created_at ASC, id ASC"),
+        "name_asc" => Ok("name ASC, id ASC"),
+        "name_desc" => Ok("name DESC, id DESC"),
+        _ => Err(format!("unsupported snapshot order: {order}")),
+    }
+}
+
+fn parse_json_column<T: serde::de::DeserializeOwned>(row: &Row, idx: usize) -> rusqlite::Result<T> {
+    let raw: String = row.get(idx)?;
+    serde_json::from_str(&raw).map_err(|err| {
+        rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(err))
+    })
+}
+
+fn decode_meta(row: &Row) -> rusqlite::Result<SnapshotMeta> {
+    Ok(SnapshotMeta {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        device_names: parse_json_column(row, 2)?,
+        channel_count: row.get(3)?,
+        sample_count: row.get(4)?,
+        divider: row.get(5)?,
+        pre_trig: row.get(6)?,
+        channel_map: parse_json_column(row, 7)?,
+        trigger_threshold: row.get(8)?,
+        trigger_channel: row.get(9)?,
+        trigger_mode: row.get(10)?,
+        rt_values: parse_json_column(row, 11)?,
+        created_at: row.get(12)?,
+    })
+}
+
+fn query_snapshot_page(
+    conn: &Connection,
+    offset: u32,
+    limit: u32,
+    order: &str,
+) -> Result<SnapshotPage, String> {
+    let order_by = order_clause(order)?;
+    if limit == 0 || limit > MAX_PAGE_LIMIT {
+        return Err(format!("limit must be within 1..={MAX_PAGE_LIMIT}"));
+    }
+
+    let total: u64 = conn
+        .query_row("SELECT COUNT(*) FROM snapshot_meta", [], |row| row.get(0))
+        .map_err(|err| err.to_string())?;
+
+    let sql =
+        format!("SELECT {META_COLUMNS} FROM snapshot_meta ORDER BY {order_by} LIMIT ?1 OFFSET ?2");
+    let mut stmt = conn.prepare(&sql).map_err(|err| err.to_string())?;
+    let items = stmt
+        .query_map(params![limit, offset], decode_meta)
+        .map_err(|err| err.to_string())?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|err| err.to_string())?;
+
+    Ok(SnapshotPage {
+        items,
+        total,
+        offset,
+        limit,
+    })
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotDiff {
+    pub len_a: usize,
+    pub len_b: usize,
+    pub lengths_differ: bool,
+    /// Differing bytes within the common prefix; bytes past the shorter
+    /// payload are covered by `lengths_differ`.
+    pub differing_bytes: usize,
+    pub first_differences: Vec<usize>,
+}
+
+fn load_snapshot_data(conn: &Connection, id: i64) -> Result<Vec<u8>, String> {
+    conn.query_row(
+        "SELECT data FROM snapshot_data WHERE snapshot_id = ?1",
+        params![id],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|err| err.to_string())?
+    .ok_or_else(|| format!("snapshot {id} not found"))
+}
+
+fn diff_bytes(a: &[u8], b: &[u8]) -> SnapshotDiff {
+    let mut differing_bytes = 0;
+    let mut first_differences = Vec::new();
+    for (offset, (x, y)) in a.iter().zip(b).enumerate() {
+        if x != y {
+            differing_bytes += 1;
+            if first_differences.len() < MAX_DIFF_OFFSETS {
+                first_differences.push(offset);
+            }
+        }
+    }
+    SnapshotDiff {
+        len_a: a.len(),
+        len_b: b.len(),
+        lengths_differ: a.len() != b.len(),
+        differing_bytes,
+        first_differences,
+    }
+}
+
+fn query_snapshot_diff(conn: &Connection, id_a: i64, id_b: i64) -> Result<SnapshotDiff, String> {
+    let a = load_snapshot_data(conn, id_a)?;
+    let b = load_snapshot_data(conn, id_b)?;
+    Ok(diff_bytes(&a, &b))
+}
+
+/// Newest-first by `created_at` (then id), matching `created_at_desc`.
+const KEPT_SNAPSHOTS: &str =
+    "SELECT id FROM snapshot_meta ORDER BY created_at DESC, id DESC LIMIT ?1";
+
+fn prune_oldest(conn: &mut Connection, keep: usize) -> Result<usize, String> {
+    let keep = i64::try_from(keep).unwrap_or(i64::MAX);
+    let tx = conn.transaction().map_err(|err| err.to_string())?;
+    // Data rows go explicitly so pruning does not depend on the connection
+    // having foreign keys (and so the cascade) switched on.
+    tx.execute(
+        &format!("DELETE FROM snapshot_data WHERE snapshot_id NOT IN ({KEPT_SNAPSHOTS})"),
+        params![keep],
+    )
+    .map_err(|err| err.to_string())?;
+    let removed = tx
+        .execute(
+            &format!("DELETE FROM snapshot_meta WHERE id NOT IN ({KEPT_SNAPSHOTS})"),
+            params![keep],
+        )
+        .map_err(|err| err.to_string())?;
+    tx.commit().map_err(|err| err.to_string())?;
+    Ok(removed)
+}
+
+/// Deletes all but the newest `keep` snapshots in one transaction and
+/// returns how many were removed.
+#[tauri::command]
+pub fn prune_snapshots(app: tauri::AppHandle, keep: usize) -> Result<usize, String> {
+    let mut conn = db::open_connection(&app).map_err(|err| err.to_string())?;
+    prune_oldest(&mut conn, keep)
+}
+
+#[tauri::command]
+pub fn diff_snapshots(app: tauri::AppHandle, id_a: i64, id_b: i64) -> Result<SnapshotDiff, String> {
+    let conn = db::open_connection(&app).map_err(|err| err.to_string())?;
+    query_snapshot_diff(&conn, id_a, id_b)
+}
+
+#[tauri::command]
+pub fn list_snapshots_page(
+    app: tauri::AppHandle,
+    offset: u32,
+    limit: u32,
+    order: String,
+) -> Result<SnapshotPage, String> {
+    let conn = db::open_connection(&app).map_err(|err| err.to_string())?;
+    query_snapshot_page(&conn, offset, limit, &order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_conn() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        db::run_migrations_once(&mut conn, None).unwrap();
+        conn
+    }
+
+    fn insert_meta(conn: &Connection, id: i64, name: &str, created_at: &str) {
+        conn.execute(
+            &format!(
+                "INSERT INTO snapshot_meta ({META_COLUMNS}) VALUES (?1, ?2, '[\"dev\"]', 5, 100, 1, 10, '[0,1,2,3,4]', 0.5, 0, 1, '[1.5]', ?3)"
+            ),
+            params![id, name, created_at],
+        )
+        .unwrap();
+    }
+
+    fn seeded_conn() -> Connection {
+        let conn = test_conn();
+        insert_meta(&conn, 1, "bravo", "2026-01-01T00:00:00Z");
+        insert_meta(&conn, 2, "alpha", "2026-01-03T00:00:00Z");
+        insert_meta(&conn, 3, "charlie", "2026-01-02T00:00:00Z");
+        conn
+    }
+
+    fn ids(page: &SnapshotPage) -> Vec<i64> {
+        page.items.iter().map(|meta| meta.id).collect()
+    }
+
+    #[test]
+    fn page_orders_by_requested_key() {
+        let conn = seeded_conn();
+        let newest = query_snapshot_page(&conn, 0, 10, "created_at_desc").unwrap();
+        assert_eq!(ids(&newest), vec![2, 3, 1]);
+        let oldest = query_snapshot_page(&conn, 0, 10, "created_at_asc").unwrap();
+        assert_eq!(ids(&oldest), vec![1, 3, 2]);
+        let by_name = query_snapshot_page(&conn, 0, 10, "name_asc").unwrap();
+        assert_eq!(ids(&by_name), vec![2, 1, 3]);
+    }
+
+    #[test]
+    fn page_reports_total_independent_of_slice() {
+        let conn = seeded_conn();
+        let page = query_snapshot_page(&conn, 1, 1, "created_at_desc").unwrap();
+        assert_eq!(ids(&page), vec![3]);
+        assert_eq!(page.total, 3);
+        assert_eq!(page.items[0].device_names, vec!["dev".to_string()]);
+        assert_eq!(page.items[0].channel_map, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn page_past_end_is_empty() {
+        let conn = seeded_conn();
+        let page = query_snapshot_page(&conn, 10, 5, "name_desc").unwrap();
+        assert!(page.items.is_empty());
+        assert_eq!(page.total, 3);
+    }
+
+    #[test]
+    fn page_rejects_unknown_order_and_bad_limit() {
+        let conn = seeded_conn();
+        assert!(query_snapshot_page(&conn, 0, 10, "name; DROP TABLE snapshot_meta").is_err());
+        assert!(query_snapshot_page(&conn, 0, 0, "name_asc").is_err());
+        assert!(query_snapshot_page(&conn, 0, MAX_PAGE_LIMIT + 1, "name_asc").is_err());
+    }
+
+    fn insert_data(conn: &Connection, id: i64, data: &[u8]) {
+        insert_meta(conn, id, &format!("snap {id}"), "2026-01-01T00:00:00Z");
+        conn.execute(
+            "INSERT INTO snapshot_data (snapshot_id, data, byte_len) VALUES (?1, ?2, ?3)",
+            params![id, data, data.len()],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn prune_keeps_only_the_newest() {
+        let mut conn = test_conn();
+        for (id, day) in [(1, 3), (2, 1), (3, 5), (4, 2), (5, 4)] {
+            insert_meta(&conn, id, "snap", &format!("2026-01-0{day}T00:00:00Z"));
+            conn.execute(
+                "INSERT INTO snapshot_data (snapshot_id, data, byte_len) VALUES (?1, x'00', 1)",
+                params![id],
+            )
+            .unwrap();
+        }
+
+        assert_eq!(prune_oldest(&mut conn, 2).unwrap(), 3);
+        let page = query_snapshot_page(&conn, 0, 10, "created_at_desc").unwrap();
+        assert_eq!(ids(&page), vec![3, 5]);
+        let data_rows: i64 = conn
+            .query_row("SELECT COUNT(*) FROM snapshot_data", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(data_rows, 2);
+
+        assert_eq!(prune_oldest(&mut conn, 10).unwrap(), 0);
+        assert_eq!(prune_oldest(&mut conn, 0).unwrap(), 2);
+    }
+
+    #[test]
+    fn diff_of_identical_snapshots_is_empty() {
+        let conn = test_conn();
+        insert_data(&conn, 1, &[1, 2, 3, 4]);
+        insert_data(&conn, 2, &[1, 2, 3, 4]);
+        let diff = query_snapshot_diff(&conn, 1, 2).unwrap();
+        assert!(!diff.lengths_differ);
+        assert_eq!(diff.differing_bytes, 0);
+        assert!(diff.first_differences.is_empty());
+    }
+
+    #[test]
+    fn diff_reports_differing_offsets() {
+        let conn = test_conn();
+        let a = vec![0u8; 40];
+        let mut b = a.clone();
+        for offset in (0..40).step_by(2) {
+            b[offset] = 0xFF;
+        }
+        insert_data(&conn, 1, &a);
+        insert_data(&conn, 2, &b);
+        let diff = query_snapshot_diff(&conn, 1, 2).unwrap();
+        assert!(!diff.lengths_differ);
+        assert_eq!(diff.differing_bytes, 20);
+        assert_eq!(diff.first_differences.len(), MAX_DIFF_OFFSETS);
+        assert_eq!(&diff.first_differences[..3], &[0, 2, 4]);
+    }
+
+    #[test]
+    fn diff_reports_length_mismatch() {
+        let conn = test_conn();
+        insert_data(&conn, 1, &[1, 2, 3]);
+        insert_data(&conn, 2, &[1, 9, 3, 4, 5]);
+        let diff = query_snapshot_diff(&conn, 1, 2).unwrap();
+        assert!(diff.lengths_differ);
+        assert_eq!((diff.len_a, diff.len_b), (3, 5));
+        assert_eq!(diff.differing_bytes, 1);
+        assert_eq!(diff.first_differences, vec![1]);
+    }
+
+    #[test]
+    fn diff_rejects_missing_snapshot() {
+        let conn = test_conn();
+        insert_data(&conn, 1, &[1]);
+        let err = query_snapshot_diff(&conn, 1, 42).unwrap_err();
+        assert!(err.contains("42"));
+    }
+}
@@ -0,0 +1,834 @@
+//! Background stream reader for unsolicited device frames (telemetry).
+//!
+//! The reader only takes the device lock once bytes are waiting, so framed
+//! requests on the same handle still get through between frames. Decoded
+//! frames (TYPE + PAYLOAD) accumulate in a bounded per-handle ring that the
+//! frontend polls and drains.
+
+use crate::disconnect::{Disconnected, DISCONNECTED_EVENT};
+use crate::error::SerialError;
+use crate::events::{self, EventSink};
+use crate::frame::read_frame_bounded;
+use crate::serial::{handle_label, with_entry, DeviceEntry, TimedFrame};
+use crate::stream_file::FileSink;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Frames kept before the overflow policy applies, unless changed with
+/// `set_unsolicited_capacity`.
+pub const UNSOLICITED_CAPACITY: usize = 1024;
+
+pub const BUFFER_OVERFLOW_EVENT: &str = "serial://buffer-overflow";
+pub const STREAM_VIOLATION_EVENT: &str = "serial://stream-violation";
+
+/// Frame sizes (TYPE + PAYLOAD) covered by each `stream_frame_stats`
+/// histogram bucket.
+pub const SIZE_BUCKET_WIDTH: usize = 8;
+/// Enough buckets for the largest body a one-byte LEN allows.
+const SIZE_BUCKETS: usize = 256 / SIZE_BUCKET_WIDTH;
+
+/// Shortest sleep between idle stream polls. Each poll takes the device lock,
+/// so the protocol's sub-millisecond read poll would keep it busy between
+/// frames.
+const STREAM_IDLE_POLL: Duration = Duration::from_millis(5);
+
+/// What a framed request does on a handle whose stream reader is running.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum StreamConflict {
+    /// Let the request through. The reader only touches the port under the
+    /// device lock, which the request holds from write to parsed reply, so
+    /// the request already has the line to itself.
+    #[default]
+    Pause,
+    /// Fail with `StreamActive`, for pure-telemetry devices.
+    Reject,
+}
+
+impl StreamConflict {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "pause" => Some(StreamConflict::Pause),
+            "reject" => Some(StreamConflict::Reject),
+            _ => None,
+        }
+    }
+}
+
+/// What the ring does with a frame that arrives when it is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum OverflowPolicy {
+    #[default]
+    DropOldest,
+    DropNewest,
+    /// Drop the oldest and emit `serial://buffer-overflow`, once per
+    /// overflow until the ring is next drained.
+    Notify,
+}
+
+impl OverflowPolicy {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "drop-oldest" => Some(OverflowPolicy::DropOldest),
+            "drop-newest" => Some(OverflowPolicy::DropNewest),
+            "notify" => Some(OverflowPolicy::Notify),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferOverflow {
+    handle_id: u64,
+    label: String,
+    capacity: usize,
+}
+
+/// Input the reader refused, such as padding after a frame under
+/// `ExtraBytesPolicy::Strict`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StreamViolation<'a> {
+    handle_id: u64,
+    label: String,
+    message: &'a str,
+}
+
+pub(crate) struct UnsolicitedRing {
+    frames: VecDeque<Vec<u8>>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    /// An overflow event has gone out since the last drain.
+    overflow_reported: bool,
+}
+
+impl UnsolicitedRing {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            frames: VecDeque::new(),
+            capacity,
+            policy: OverflowPolicy::default(),
+            overflow_reported: false,
+        }
+    }
+
+    /// Adds `frame`, applying the overflow policy when full. Returns `true`
+    /// when the caller should emit an overflow event.
+    pub(crate) fn push(&mut self, frame: Vec<u8>) -> bool {
+        let mut notify = false;
+        if self.frames.len() >= self.capacity {
+            match self.policy {
+                OverflowPolicy::DropNewest => return false,
+                OverflowPolicy::DropOldest => {}
+                OverflowPolicy::Notify => notify = !self.overflow_reported,
+            }
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+        self.overflow_reported |= notify;
+        notify
+    }
+
+    /// Changes the limits in place; shrinking below the current length
+    /// discards the oldest frames.
+    fn configure(&mut self, capacity: usize, policy: OverflowPolicy) {
+        let excess = self.frames.len().saturating_sub(capacity);
+        self.frames.drain(..excess);
+        self.capacity = capacity;
+        self.policy = policy;
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub(crate) fn drain(&mut self) -> Vec<Vec<u8>> {
+        self.overflow_reported = false;
+        self.frames.drain(..).collect()
+    }
+}
+
+/// Sizes and arrival gaps of the frames decoded in one stream session.
+#[derive(Default)]
+pub(crate) struct StreamStats {
+    total_frames: u64,
+    size_histogram: [u64; SIZE_BUCKETS],
+    last_frame_at: Option<Instant>,
+    intervals: u32,
+    interval_sum: Duration,
+    min_interval: Option<Duration>,
+    max_interval: Option<Duration>,
+}
+
+impl StreamStats {
+    fn record(&mut self, frame_len: usize, at: Instant) {
+        self.total_frames += 1;
+        self.size_histogram[(frame_len / SIZE_BUCKET_WIDTH).min(SIZE_BUCKETS - 1)] += 1;
+        if let Some(last) = self.last_frame_at.replace(at) {
+            let gap = at.duration_since(last);
+            self.intervals += 1;
+            self.interval_sum += gap;
+            self.min_interval = Some(self.min_interval.map_or(gap, |min| min.min(gap)));
+            self.max_interval = Some(self.max_interval.map_or(gap, |max| max.max(gap)));
+        }
+    }
+
+    fn report(&self) -> FrameStats {
+        let ms = |gap: Duration| gap.as_secs_f64() * 1000.0;
+        FrameStats {
+            total_frames: self.total_frames,
+            size_histogram: self.size_histogram.to_vec(),
+            min_interval_ms: self.min_interval.map(ms),
+            avg_interval_ms: (self.intervals > 0).then(|| ms(self.interval_sum / self.intervals)),
+            max_interval_ms: self.max_interval.map(ms),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrameStats {
+    pub total_frames: u64,
+    /// Frame counts by size; bucket `i` holds frames of
+    /// `i * SIZE_BUCKET_WIDTH` up to `(i + 1) * SIZE_BUCKET_WIDTH - 1` bytes.
+    pub size_histogram: Vec<u64>,
+    /// Gaps between consecutive frames as the reader decoded them; `None`
+    /// until two frames have arrived.
+    pub min_interval_ms: Option<f64>,
+    pub avg_interval_ms: Option<f64>,
+    pub max_interval_ms: Option<f64>,
+}
+
+/// Where the stream reader delivers decoded frames.
+pub(crate) enum StreamTarget {
+    /// The per-handle ring the frontend drains.
+    Ring,
+    File(FileSink),
+}
+
+pub(crate) struct StreamWorker {
+    stop: Arc<AtomicBool>,
+    to_file: bool,
+    thread: JoinHandle<StreamTarget>,
+}
+
+impl StreamWorker {
+    fn spawn(handle_id: u64, target: StreamTarget, events: EventSink) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = stop.clone();
+        let to_file = matches!(target, StreamTarget::File(_));
+        let thread =
+            std::thread::spawn(move || stream_loop(handle_id, &stop_flag, target, &events));
+        Self {
+            stop,
+            to_file,
+            thread,
+        }
+    }
+
+    /// Starts a new stream session on `entry`, resetting its running checksum
+    /// and frame stats.
+    pub(crate) fn spawn_for(
+        entry: &mut DeviceEntry,
+        handle_id: u64,
+        target: StreamTarget,
+        events: EventSink,
+    ) -> Self {
+        entry.stream_crc = entry.protocol.crc_init;
+        entry.stream_stats = StreamStats::default();
+        Self::spawn(handle_id, target, events)
+    }
+
+    pub(crate) fn to_file(&self) -> bool {
+        self.to_file
+    }
+
+    /// Stops and joins the reader, handing back its target; `None` if the
+    /// reader panicked.
+    pub(crate) fn stop(self) -> Option<StreamTarget> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.thread.join().ok()
+    }
+}
+
+/// Runs until stopped or the handle disappears. Timeouts and CRC failures on
+/// a single frame are line noise, not a reason to end the stream; input the
+/// protocol refuses (`InvalidResponse`) is reported with
+/// `serial://stream-violation` and the reader resyncs. A file
+/// write failure ends it, and so does a disconnect, which is announced with
+/// `serial://disconnected`.
+fn stream_loop(
+    handle_id: u64,
+    stop: &AtomicBool,
+    mut target: StreamTarget,
+    events: &EventSink,
+) -> StreamTarget {
+    // Set once a frame has been decoded and nothing since has needed a
+    // resync, so the next byte should be a sync byte.
+    let mut after_frame = false;
+    while !stop.load(Ordering::Relaxed) {
+        let polled = with_entry(handle_id, "stream", |entry| {
+            let Some(frame) = poll_frame(entry, after_frame)? else {
+                return Ok(Some(
+                    entry.protocol.read_poll_interval().max(STREAM_IDLE_POLL),
+                ));
+            };
+            entry.stream_crc = entry.protocol.crc_variant.update(entry.stream_crc, &frame);
+            entry.stream_stats.record(frame.len(), Instant::now());
+            entry.last_frame = Some(TimedFrame::now(&frame));
+            match &mut target {
+                StreamTarget::Ring => {
+                    if entry.unsolicited.push(frame) {
+                        let overflow = BufferOverflow {
+                            handle_id,
+                            label: entry.label.clone(),
+                            capacity: entry.unsolicited.capacity,
+                        };
+                        events::emit(events, BUFFER_OVERFLOW_EVENT, &overflow);
+                    }
+                }
+                StreamTarget::File(sink) => sink.write_frame(&frame)?,
+            }
+            Ok(None)
+        });
+        match polled {
+            Ok(None) => after_frame = true,
+            Ok(Some(idle)) => {
+                // Bytes after a quiet spell start a new burst, not padding.
+                after_frame = false;
+                std::thread::sleep(idle);
+            }
+            Err(SerialError::Timeout | SerialError::CrcMismatch) => after_frame = false,
+            Err(err @ SerialError::InvalidResponse { .. }) => {
+                after_frame = false;
+                report_violation(handle_id, &err, events);
+            }
+            Err(SerialError::Disconnected { message }) => {
+                events::emit(
+                    events,
+                    DISCONNECTED_EVENT,
+                    &Disconnected {
+                        handle_id,
+                        label: handle_label(handle_id),
+                        message: &message,
+                    },
+                );
+                break;
+            }
+            Err(_) => break,
+        }
+        if let StreamTarget::File(sink) = &mut target {
+            sink.maybe_report(handle_id);
+        }
+    }
+    target
+}
+
+/// Records `err` in the handle's diagnostics and emits it as a violation.
+fn report_violation(handle_id: u64, err: &SerialError, events: &EventSink) {
+    let SerialError::InvalidResponse { message } = err else {
+        return;
+    };
+    let recorded = with_entry(handle_id, "stream", |entry| {
+        entry.stats.record_error(err);
+        Ok(entry.label.clone())
+    });
+    if let Ok(label) = recorded {
+        events::emit(
+            events,
+            STREAM_VIOLATION_EVENT,
+            &StreamViolation {
+                handle_id,
+                label,
+                message,
+            },
+        );
+    }
+}
+
+/// Applies the handle's `StreamConflict` ahead of a framed request, which
+/// must already hold the device lock.
+pub(crate) fn check_stream_conflict(entry: &mut DeviceEntry) -> Result<(), SerialError> {
+    if entry.stream.is_none() {
+        return Ok(());
+    }
+    match entry.stream_conflict {
+        StreamConflict::Reject => Err(SerialError::StreamActive),
+        StreamConflict::Pause => {
+            entry.stats.record_stream_pause();
+            Ok(())
+        }
+    }
+}
+
+/// Reads one frame if any input is waiting.
+fn poll_frame(entry: &mut DeviceEntry, after_frame: bool) -> Result<Option<Vec<u8>>, SerialError> {
+    if entry.port.bytes_to_read()? == 0 {
+        return Ok(None);
+    }
+    let skip_max = if after_frame {
+        entry.protocol.extra_bytes.skip_max()
+    } else {
+        None
+    };
+    read_frame_bounded(&mut *entry.port, &entry.protocol, skip_max).map(Some)
+}
+
+/// Starts the background reader; a no-op if it is already running.
+#[tauri::command]
+pub fn start_stream(app: tauri::AppHandle, handle_id: u64) -> Result<(), SerialError> {
+    start_stream_with(handle_id, events::app_sink(app))
+}
+
+pub(crate) fn start_stream_with(handle_id: u64, sink: EventSink) -> Result<(), SerialError> {
+    with_entry(handle_id, "start_stream", |entry| {
+        if entry.stream.is_none() {
+            entry.stream = Some(StreamWorker::spawn_for(
+                entry,
+                handle_id,
+                StreamTarget::Ring,
+                sink,
+            ));
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+pub fn stop_stream(handle_id: u64) -> Result<(), SerialError> {
+    // The reader needs the device lock to finish, so join outside it.
+    let worker = with_entry(handle_id, "stop_stream", |entry| Ok(entry.stream.take()))?;
+    if let Some(worker) = worker {
+        let _ = worker.stop();
+    }
+    Ok(())
+}
+
+/// Chooses what framed requests do while the stream reader runs: `"pause"`
+/// (the default) lets them through, with the device lock keeping the reader
+/// off the port for the exchange; `"reject"` fails them with `StreamActive`.
+#[tauri::command]
+pub fn set_stream_conflict(handle_id: u64, mode: String) -> Result<(), SerialError> {
+    let conflict = StreamConflict::parse(&mode).ok_or_else(|| SerialError::InvalidConfig {
+        message: format!("unknown stream conflict mode {mode:?}; expected pause or reject"),
+    })?;
+    with_entry(handle_id, "set_stream_conflict", |entry| {
+        entry.stream_conflict = conflict;
+        Ok(())
+    })
+}
+
+/// Sets how many unsolicited frames the handle buffers and what happens to
+/// a frame that arrives when the buffer is full: `"drop-oldest"` (the
+/// default), `"drop-newest"`, or `"notify"`, which drops the oldest and
+/// emits `serial://buffer-overflow`.
+#[tauri::command]
+pub fn set_unsolicited_capacity(
+    handle_id: u64,
+    capacity: usize,
+    policy: String,
+) -> Result<(), SerialError> {
+    if capacity == 0 {
+        return Err(SerialError::InvalidConfig {
+            message: "capacity must be at least 1".to_string(),
+        });
+    }
+    let policy = OverflowPolicy::parse(&policy).ok_or_else(|| SerialError::InvalidConfig {
+        message: format!(
+            "unknown overflow policy {policy:?}; expected drop-oldest, drop-newest or notify"
+        ),
+    })?;
+    with_entry(handle_id, "set_unsolicited_capacity", |entry| {
+        entry.unsolicited.configure(capacity, policy);
+        Ok(())
+    })
+}
+
+/// Number of buffered unsolicited frames, without removing them.
+#[tauri::command]
+pub fn pending_frame_count(handle_id: u64) -> Result<usize, SerialError> {
+    with_entry(handle_id, "pending_frame_count", |entry| {
+        Ok(entry.unsolicited.len())
+    })
+}
+
+/// Removes and returns every buffered unsolicited frame, oldest first.
+#[tauri::command]
+pub fn drain_unsolicited(handle_id: u64) -> Result<Vec<Vec<u8>>, SerialError> {
+    with_entry(handle_id, "drain_unsolicited", |entry| {
+        Ok(entry.unsolicited.drain())
+    })
+}
+
+/// Running checksum (the handle's CRC variant and init) over every frame,
+/// TYPE + PAYLOAD, decoded since the stream was last started. Widened to
+/// u32 so the frontend can compare against wider device-side checksums.
+#[tauri::command]
+pub fn stream_checksum(handle_id: u64) -> Result<u32, SerialError> {
+    with_entry(handle_id, "stream_checksum", |entry| {
+        Ok(u32::from(entry.stream_crc))
+    })
+}
+
+/// Frame size histogram and inter-frame intervals for the current (or last)
+/// stream session.
+#[tauri::command]
+pub fn stream_frame_stats(handle_id: u64) -> Result<FrameStats, SerialError> {
+    with_entry(handle_id, "stream_frame_stats", |entry| {
+        Ok(entry.stream_stats.report())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crc::crc8;
+    use crate::diagnostics::diagnostics_report;
+    use crate::disconnect::set_disconnect_error_kinds;
+    use crate::events::testing;
+    use crate::frame::{build_frame, ExtraBytesPolicy, ProtocolConfig};
+    use crate::mock_port::MockPort;
+    use crate::serial::{insert_mock, insert_mock_with, send_request, set_handle_label};
+
+    fn wait_for_count(handle_id: u64, count: usize) -> usize {
+        let deadline = Instant::now() + Duration::from_secs(1);
+        loop {
+            let pending = pending_frame_count(handle_id).unwrap();
+            if pending >= count || Instant::now() >= deadline {
+                return pending;
+            }
+            std::thread::sleep(Duration::from_millis(2));
+        }
+    }
+
+    #[test]
+    fn ring_discards_oldest_at_capacity() {
+        let mut ring = UnsolicitedRing::new(2);
+        ring.push(vec![1]);
+        ring.push(vec![2]);
+        assert!(!ring.push(vec![3]));
+        assert_eq!(ring.len(), 2);
+        assert_eq!(ring.drain(), vec![vec![2], vec![3]]);
+        assert_eq!(ring.len(), 0);
+    }
+
+    #[test]
+    fn ring_drop_newest_keeps_first_frames() {
+        let mut ring = UnsolicitedRing::new(2);
+        ring.configure(2, OverflowPolicy::DropNewest);
+        for frame in 1..=3 {
+            assert!(!ring.push(vec![frame]));
+        }
+        assert_eq!(ring.drain(), vec![vec![1], vec![2]]);
+    }
+
+    #[test]
+    fn ring_notify_reports_once_per_drain() {
+        let mut ring = UnsolicitedRing::new(2);
+        ring.configure(2, OverflowPolicy::Notify);
+        let notified: Vec<bool> = (1..=4).map(|frame| ring.push(vec![frame])).collect();
+        assert_eq!(notified, vec![false, false, true, false]);
+        assert_eq!(ring.drain(), vec![vec![3], vec![4]]);
+
+        ring.push(vec![5]);
+        ring.push(vec![6]);
+        assert!(ring.push(vec![7]));
+    }
+
+    #[test]
+    fn shrinking_the_ring_drops_oldest() {
+        let mut ring = UnsolicitedRing::new(4);
+        for frame in 1..=4 {
+            ring.push(vec![frame]);
+        }
+        ring.configure(2, OverflowPolicy::DropOldest);
+        assert_eq!(ring.drain(), vec![vec![3], vec![4]]);
+    }
+
+    #[test]
+    fn stream_emits_overflow_event_under_notify_only() {
+        for (policy, events_expected) in [("notify", 1), ("drop-oldest", 0)] {
+            let mut frames = Vec::new();
+            for i in 0..3 {
+                frames.extend(build_frame(&[0x07, i]).unwrap());
+            }
+            let handle_id = insert_mock(&MockPort::with_rx(&frames));
+            set_unsolicited_capacity(handle_id, 2, policy.to_string()).unwrap();
+            let (sink, captured) = testing::capture();
+            start_stream_with(handle_id, sink).unwrap();
+            assert_eq!(wait_for_count(handle_id, 2), 2);
+            std::thread::sleep(Duration::from_millis(20));
+            stop_stream(handle_id).unwrap();
+
+            let events = testing::wait_for(
+                &captured,
+                BUFFER_OVERFLOW_EVENT,
+                1,
+                Duration::from_millis(50),
+            );
+            assert_eq!(events.len(), events_expected, "{policy}");
+            if let Some(event) = events.first() {
+                assert_eq!(event["handleId"], handle_id);
+                assert_eq!(event["capacity"], 2);
+            }
+            assert_eq!(
+                drain_unsolicited(handle_id).unwrap(),
+                vec![vec![0x07, 1], vec![0x07, 2]]
+            );
+        }
+    }
+
+    #[test]
+    fn stream_events_carry_the_handle_label() {
+        for (label, expected) in [(None, "mock"), (Some("left motor"), "left motor")] {
+            let mut frames = Vec::new();
+            for i in 0..2 {
+                frames.extend(build_frame(&[0x07, i]).unwrap());
+            }
+            let handle_id = insert_mock(&MockPort::with_rx(&frames));
+            set_handle_label(handle_id, label.map(str::to_string)).unwrap();
+            set_unsolicited_capacity(handle_id, 1, "notify".to_string()).unwrap();
+            let (sink, captured) = testing::capture();
+            start_stream_with(handle_id, sink).unwrap();
+
+            let events =
+                testing::wait_for(&captured, BUFFER_OVERFLOW_EVENT, 1, Duration::from_secs(1));
+            stop_stream(handle_id).unwrap();
+            assert_eq!(events[0]["handleId"], handle_id);
+            assert_eq!(events[0]["label"], expected);
+        }
+    }
+
+    #[test]
+    fn unsolicited_capacity_rejects_bad_settings() {
+        let handle_id = insert_mock(&MockPort::new());
+        assert!(set_unsolicited_capacity(handle_id, 0, "notify".to_string()).is_err());
+        assert!(set_unsolicited_capacity(handle_id, 8, "spill".to_string()).is_err());
+    }
+
+    #[test]
+    fn pending_count_tracks_stream_until_drained() {
+        let port = MockPort::new();
+        for (i, delay) in [5u64, 15, 25].into_iter().enumerate() {
+            port.schedule_rx(
+                Duration::from_millis(delay),
+                &build_frame(&[0x07, i as u8]).unwrap(),
+            );
+        }
+        let handle_id = insert_mock(&port);
+        assert_eq!(pending_frame_count(handle_id).unwrap(), 0);
+
+        start_stream_with(handle_id, testing::capture().0).unwrap();
+        assert_eq!(wait_for_count(handle_id, 3), 3);
+        stop_stream(handle_id).unwrap();
+
+        assert_eq!(pending_frame_count(handle_id).unwrap(), 3);
+        assert_eq!(
+            drain_unsolicited(handle_id).unwrap(),
+            vec![vec![0x07, 0], vec![0x07, 1], vec![0x07, 2]]
+        );
+        assert_eq!(pending_frame_count(handle_id).unwrap(), 0);
+    }
+
+    #[test]
+    fn stream_checksum_covers_received_frames_and_resets() {
+        let payloads = [[0x07, 0x10], [0x07, 0x20], [0x07, 0x30]];
+        let port = MockPort::new();
+        for (i, payload) in payloads.iter().enumerate() {
+            port.schedule_rx(
+                Duration::from_millis(5 + 5 * i as u64),
+                &build_frame(payload).unwrap(),
+            );
+        }
+        let handle_id = insert_mock(&port);
+        start_stream_with(handle_id, testing::capture().0).unwrap();
+        assert_eq!(wait_for_count(handle_id, 3), 3);
+        stop_stream(handle_id).unwrap();
+
+        let expected = crc8(&payloads.concat());
+        assert_eq!(stream_checksum(handle_id).unwrap(), u32::from(expected));
+
+        start_stream_with(handle_id, testing::capture().0).unwrap();
+        assert_eq!(stream_checksum(handle_id).unwrap(), 0);
+        stop_stream(handle_id).unwrap();
+    }
+
+    #[test]
+    fn configured_error_kind_ends_stream_as_disconnect() {
+        // Widen the global set rather than replace it, so tests running in
+        // parallel keep the default classification.
+        let kinds = ["NotConnected", "BrokenPipe", "NoDevice", "ConnectionReset"];
+        set_disconnect_error_kinds(kinds.map(String::from).to_vec()).unwrap();
+
+        let port = MockPort::new();
+        port.state.lock().unwrap().read_error = Some(std::io::ErrorKind::ConnectionReset);
+        let handle_id = insert_mock(&port);
+        let (sink, captured) = testing::capture();
+        start_stream_with(handle_id, sink).unwrap();
+
+        let events = testing::wait_for(&captured, DISCONNECTED_EVENT, 1, Duration::from_secs(1));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["handleId"], handle_id);
+        stop_stream(handle_id).unwrap();
+
+        assert!(matches!(
+            set_disconnect_error_kinds(vec!["Sideways".to_string()]),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+    }
+
+    #[test]
+    fn request_gets_the_line_while_streaming() {
+        let port = MockPort::new();
+        port.queue_reply(&build_frame(&[0x01, 0x2A]).unwrap());
+        let handle_id = insert_mock(&port);
+        start_stream_with(handle_id, testing::capture().0).unwrap();
+
+        assert_eq!(
+            send_request(handle_id, vec![0x01]).unwrap(),
+            vec![0x01, 0x2A]
+        );
+        let counters = diagnostics_report(handle_id).unwrap().counters;
+        assert_eq!(counters.stream_pauses, 1);
+
+        port.push_rx(&build_frame(&[0x07, 0x01]).unwrap());
+        assert_eq!(wait_for_count(handle_id, 1), 1);
+        stop_stream(handle_id).unwrap();
+        assert_eq!(
+            drain_unsolicited(handle_id).unwrap(),
+            vec![vec![0x07, 0x01]]
+        );
+    }
+
+    #[test]
+    fn rejecting_handle_refuses_requests_while_streaming() {
+        let port = MockPort::new();
+        let handle_id = insert_mock(&port);
+        set_stream_conflict(handle_id, "reject".to_string()).unwrap();
+        start_stream_with(handle_id, testing::capture().0).unwrap();
+
+        assert!(matches!(
+            send_request(handle_id, vec![0x01]),
+            Err(SerialError::StreamActive)
+        ));
+        assert!(port.written().is_empty());
+
+        stop_stream(handle_id).unwrap();
+        assert!(matches!(
+            send_request(handle_id, vec![0x01]),
+            Err(SerialError::Timeout)
+        ));
+        assert!(matches!(
+            set_stream_conflict(handle_id, "ignore".to_string()),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+    }
+
+    #[test]
+    fn frame_stats_track_sizes_and_intervals() {
+        let port = MockPort::new();
+        for (i, delay) in [10u64, 30, 50, 70].into_iter().enumerate() {
+            let mut payload = vec![0x07];
+            payload.resize(1 + i * 4, 0xAB);
+            port.schedule_rx(
+                Duration::from_millis(delay),
+                &build_frame(&payload).unwrap(),
+            );
+        }
+        let handle_id = insert_mock(&port);
+        start_stream_with(handle_id, testing::capture().0).unwrap();
+        assert_eq!(wait_for_count(handle_id, 4), 4);
+        stop_stream(handle_id).unwrap();
+
+        let stats = stream_frame_stats(handle_id).unwrap();
+        assert_eq!(stats.total_frames, 4);
+        // Sizes 1, 5, 9 and 13.
+        assert_eq!(&stats.size_histogram[..3], &[2, 2, 0]);
+        assert_eq!(stats.size_histogram.len(), 256 / SIZE_BUCKET_WIDTH);
+        for interval in [
+            stats.min_interval_ms,
+            stats.avg_interval_ms,
+            stats.max_interval_ms,
+        ] {
+            let interval = interval.unwrap();
+            assert!((12.0..=28.0).contains(&interval), "{interval}");
+        }
+
+        start_stream_with(handle_id, testing::capture().0).unwrap();
+        let stats = stream_frame_stats(handle_id).unwrap();
+        assert_eq!(stats.total_frames, 0);
+        assert_eq!(stats.avg_interval_ms, None);
+        stop_stream(handle_id).unwrap();
+    }
+
+    fn padded_frames() -> Vec<u8> {
+        let mut bytes = build_frame(&[0x07, 0x01]).unwrap();
+        bytes.extend([0x00, 0x00]);
+        bytes.extend(build_frame(&[0x07, 0x02]).unwrap());
+        bytes
+    }
+
+    #[test]
+    fn lenient_stream_skips_padding_after_a_frame() {
+        let port = MockPort::with_rx(&padded_frames());
+        let handle_id = insert_mock(&port);
+        start_stream_with(handle_id, testing::capture().0).unwrap();
+        assert_eq!(wait_for_count(handle_id, 2), 2);
+        stop_stream(handle_id).unwrap();
+        assert_eq!(
+            drain_unsolicited(handle_id).unwrap(),
+            vec![vec![0x07, 0x01], vec![0x07, 0x02]]
+        );
+    }
+
+    #[test]
+    fn strict_stream_reports_padding_after_a_frame_and_resyncs() {
+        let port = MockPort::with_rx(&padded_frames());
+        let protocol = ProtocolConfig {
+            extra_bytes: ExtraBytesPolicy::Strict,
+            ..ProtocolConfig::default()
+        };
+        let handle_id = insert_mock_with(&port, protocol);
+        let (sink, captured) = testing::capture();
+        start_stream_with(handle_id, sink).unwrap();
+        let events =
+            testing::wait_for(&captured, STREAM_VIOLATION_EVENT, 1, Duration::from_secs(1));
+        assert_eq!(wait_for_count(handle_id, 2), 2);
+        stop_stream(handle_id).unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["handleId"], handle_id);
+        assert!(events[0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("non-sync bytes"));
+        assert_eq!(
+            drain_unsolicited(handle_id).unwrap(),
+            vec![vec![0x07, 0x01], vec![0x07, 0x02]]
+        );
+        let report = crate::diagnostics::diagnostics_report(handle_id).unwrap();
+        assert_eq!(report.recent_errors.len(), 1);
+    }
+
+    #[test]
+    fn strict_stream_accepts_noise_after_a_quiet_spell() {
+        let port = MockPort::with_rx(&build_frame(&[0x07, 0x01]).unwrap());
+        let mut later = vec![0x00, 0x00];
+        later.extend(build_frame(&[0x07, 0x02]).unwrap());
+        port.schedule_rx(Duration::from_millis(30), &later);
+        let protocol = ProtocolConfig {
+            extra_bytes: ExtraBytesPolicy::Strict,
+            ..ProtocolConfig::default()
+        };
+        let handle_id = insert_mock_with(&port, protocol);
+        let (sink, captured) = testing::capture();
+        start_stream_with(handle_id, sink).unwrap();
+        assert_eq!(wait_for_count(handle_id, 2), 2);
+        stop_stream(handle_id).unwrap();
+        assert!(testing::wait_for(&captured, STREAM_VIOLATION_EVENT, 1, Duration::ZERO).is_empty());
+    }
+}
@@ -0,0 +1,260 @@
+//! High-rate logging: the stream reader writes decoded frames straight to a
+//! file instead of the ring, and the frontend only sees periodic counts.
+
+use crate::console::format_hex;
+use crate::error::SerialError;
+use crate::events::{self, EventSink};
+use crate::serial::{handle_label, with_entry};
+use crate::stream::{StreamTarget, StreamWorker};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::{Duration, Instant};
+
+pub const STREAM_FILE_PROGRESS_EVENT: &str = "serial://stream-file-progress";
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FileFormat {
+    /// Each frame (TYPE + PAYLOAD) preceded by its length as a little-endian u16.
+    Binary,
+    /// One frame per line as space-separated hex.
+    Hex,
+}
+
+impl FileFormat {
+    fn parse(format: &str) -> Result<Self, SerialError> {
+        match format {
+            "binary" => Ok(FileFormat::Binary),
+            "hex" => Ok(FileFormat::Hex),
+            other => Err(SerialError::InvalidConfig {
+                message: format!("unknown stream file format {other:?}; expected binary or hex"),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamFileTotals {
+    pub frames: u64,
+    /// Bytes written to the file, framing of the chosen format included.
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StreamFileProgress {
+    handle_id: u64,
+    label: Option<String>,
+    #[serde(flatten)]
+    totals: StreamFileTotals,
+}
+
+pub(crate) struct FileSink {
+    writer: BufWriter<File>,
+    format: FileFormat,
+    totals: StreamFileTotals,
+    events: EventSink,
+    last_report: Instant,
+}
+
+impl FileSink {
+    pub(crate) fn write_frame(&mut self, frame: &[u8]) -> Result<(), SerialError> {
+        let written = match self.format {
+            FileFormat::Binary => {
+                self.writer.write_all(&(frame.len() as u16).to_le_bytes())?;
+                self.writer.write_all(frame)?;
+                2 + frame.len()
+            }
+            FileFormat::Hex => {
+                let line = format_hex(frame);
+                writeln!(self.writer, "{line}")?;
+                line.len() + 1
+            }
+        };
+        self.totals.frames += 1;
+        self.totals.bytes += written as u64;
+        Ok(())
+    }
+
+    /// Emits a progress event if the last one is older than the interval.
+    pub(crate) fn maybe_report(&mut self, handle_id: u64) {
+        if self.last_report.elapsed() < PROGRESS_INTERVAL {
+            return;
+        }
+        self.last_report = Instant::now();
+        events::emit(
+            &self.events,
+            STREAM_FILE_PROGRESS_EVENT,
+            &StreamFileProgress {
+                handle_id,
+                label: handle_label(handle_id),
+                totals: self.totals,
+            },
+        );
+    }
+
+    fn finish(mut self) -> Result<StreamFileTotals, SerialError> {
+        self.writer.flush()?;
+        Ok(self.totals)
+    }
+}
+
+/// Starts the stream reader writing frames to `path` (created or truncated)
+/// in `format`, `"binary"` or `"hex"`. Fails if a stream is already running.
+#[tauri::command]
+pub fn start_stream_to_file(
+    app: tauri::AppHandle,
+    handle_id: u64,
+    path: String,
+    format: String,
+) -> Result<(), SerialError> {
+    start_stream_to_file_with(handle_id, &path, &format, events::app_sink(app))
+}
+
+fn start_stream_to_file_with(
+    handle_id: u64,
+    path: &str,
+    format: &str,
+    sink: EventSink,
+) -> Result<(), SerialError> {
+    let format = FileFormat::parse(format)?;
+    with_entry(handle_id, "start_stream_to_file", |entry| {
+        if entry.stream.is_some() {
+            return Err(SerialError::InvalidConfig {
+                message: "a stream is already running on this handle".to_string(),
+            });
+        }
+        let file = File::create(path).map_err(|err| SerialError::IoError {
+            message: format!("cannot create {path}: {err}"),
+        })?;
+        let target = StreamTarget::File(FileSink {
+            writer: BufWriter::new(file),
+            format,
+            totals: StreamFileTotals::default(),
+            events: sink.clone(),
+            last_report: Instant::now(),
+        });
+        entry.stream = Some(StreamWorker::spawn_for(entry, handle_id, target, sink));
+        Ok(())
+    })
+}
+
+/// Stops a file stream, flushes and closes the file, and returns the totals.
+#[tauri::command]
+pub fn stop_stream_to_file(handle_id: u64) -> Result<StreamFileTotals, SerialError> {
+    // As with `stop_stream`, the reader needs the device lock to finish.
+    let worker = with_entry(handle_id, "stop_stream_to_file", |entry| {
+        match entry.stream.take_if(|worker| worker.to_file()) {
+            Some(worker) => Ok(worker),
+            None => Err(SerialError::InvalidConfig {
+                message: "no file stream is running on this handle".to_string(),
+            }),
+        }
+    })?;
+    match worker.stop() {
+        Some(StreamTarget::File(sink)) => sink.finish(),
+        _ => Err(SerialError::IoError {
+            message: "stream reader ended abnormally".to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::testing;
+    use crate::frame::build_frame;
+    use crate::mock_port::MockPort;
+    use crate::serial::insert_mock;
+    use crate::stream::stop_stream;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("vscope-stream-{}-{name}", std::process::id()))
+    }
+
+    fn scheduled_port(count: u8) -> MockPort {
+        let port = MockPort::new();
+        for i in 0..count {
+            port.schedule_rx(
+                Duration::from_millis(2 + u64::from(i)),
+                &build_frame(&[0x07, i]).unwrap(),
+            );
+        }
+        port
+    }
+
+    fn wait_for_drain(port: &MockPort) {
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while Instant::now() < deadline {
+            let state = port.state.lock().unwrap();
+            if state.scheduled.is_empty() && state.rx.is_empty() {
+                return;
+            }
+            drop(state);
+            std::thread::sleep(Duration::from_millis(2));
+        }
+    }
+
+    #[test]
+    fn binary_stream_writes_and_counts_each_frame() {
+        let port = scheduled_port(5);
+        let handle_id = insert_mock(&port);
+        let path = temp_path("binary.bin");
+        let (sink, _captured) = testing::capture();
+
+        start_stream_to_file_with(handle_id, path.to_str().unwrap(), "binary", sink).unwrap();
+        wait_for_drain(&port);
+        let totals = stop_stream_to_file(handle_id).unwrap();
+
+        assert_eq!(
+            totals,
+            StreamFileTotals {
+                frames: 5,
+                bytes: 20
+            }
+        );
+        let written = std::fs::read(&path).unwrap();
+        let expected: Vec<u8> = (0..5u8).flat_map(|i| [2, 0, 0x07, i]).collect();
+        assert_eq!(written, expected);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn hex_stream_writes_one_line_per_frame() {
+        let port = scheduled_port(3);
+        let handle_id = insert_mock(&port);
+        let path = temp_path("frames.hex");
+        let (sink, _captured) = testing::capture();
+
+        start_stream_to_file_with(handle_id, path.to_str().unwrap(), "hex", sink).unwrap();
+        wait_for_drain(&port);
+        assert_eq!(stop_stream_to_file(handle_id).unwrap().frames, 3);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "07 00\n07 01\n07 02\n"
+        );
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn file_stream_rejects_bad_format_and_running_stream() {
+        let handle_id = insert_mock(&MockPort::new());
+        let path = temp_path("rejected.bin");
+        let path = path.to_str().unwrap();
+        let (sink, _captured) = testing::capture();
+        assert!(matches!(
+            start_stream_to_file_with(handle_id, path, "csv", sink.clone()),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+        crate::stream::start_stream_with(handle_id, sink.clone()).unwrap();
+        assert!(matches!(
+            start_stream_to_file_with(handle_id, path, "hex", sink),
+            Err(SerialError::InvalidConfig { .. })
+        ));
+        assert!(stop_stream_to_file(handle_id).is_err());
+        stop_stream(handle_id).unwrap();
+    }
+}
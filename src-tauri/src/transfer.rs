@@ -0,0 +1,204 @@
+//! Bulk transfers: a blob split into framed chunks, each acknowledged by the
+//! device echoing the chunk's TYPE before the next one goes out.
+
+use crate::error::SerialError;
+use crate::events::{self, EventSink};
+use crate::frame::MSG_ERROR;
+use crate::serial::{exchange, handle_label, with_entry};
+use serde::Serialize;
+use std::time::Instant;
+
+pub const TRANSFER_PROGRESS_EVENT: &str = "serial://transfer-progress";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferReport {
+    pub chunks_sent: usize,
+    pub bytes_sent: usize,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferProgress {
+    pub handle_id: u64,
+    pub label: Option<String>,
+    pub chunks_sent: usize,
+    pub total_chunks: usize,
+    pub bytes_sent: usize,
+    pub total_bytes: usize,
+}
+
+fn check_ack(response: &[u8], message_type: u8) -> Result<(), SerialError> {
+    match response.split_first() {
+        Some((&kind, _)) if kind == message_type => Ok(()),
+        Some((&MSG_ERROR, data)) => Err(SerialError::InvalidResponse {
+            message: format!("device rejected chunk (error code {:?})", data.first()),
+        }),
+        Some((other, _)) => Err(SerialError::InvalidResponse {
+            message: format!("expected chunk ack 0x{message_type:02X}, got 0x{other:02X}"),
+        }),
+        None => Err(SerialError::InvalidResponse {
+            message: "empty chunk ack".to_string(),
+        }),
+    }
+}
+
+/// Sends `data` as `message_type` frames of at most `chunk_size` payload
+/// bytes (clamped to the handle's max payload), stopping at the first
+/// unacknowledged chunk. The device lock is released between chunks.
+pub(crate) fn send_chunked_with(
+    handle_id: u64,
+    message_type: u8,
+    data: &[u8],
+    chunk_size: usize,
+    sink: &EventSink,
+) -> Result<TransferReport, SerialError> {
+    if chunk_size == 0 {
+        return Err(SerialError::InvalidConfig {
+            message: "chunk size must be at least 1".to_string(),
+        });
+    }
+    let max_payload = with_entry(handle_id, "send_chunked", |entry| {
+        Ok(entry.protocol.max_payload_len)
+    })?;
+    let chunk_size = chunk_size.min(max_payload);
+    let total_chunks = data.len().div_ceil(chunk_size);
+
+    let started = Instant::now();
+    let mut bytes_sent = 0;
+    for (index, chunk) in data.chunks(chunk_size).enumerate() {
+        let mut payload = Vec::with_capacity(1 + chunk.len());
+        payload.push(message_type);
+        payload.extend_from_slice(chunk);
+        let response = with_entry(handle_id, "send_chunked", |entry| exchange(entry, &payload))?;
+        check_ack(&response, message_type)?;
+
+        bytes_sent += chunk.len();
+        events::emit(
+            sink,
+            TRANSFER_PROGRESS_EVENT,
+            &TransferProgress {
+                handle_id,
+                label: handle_label(handle_id),
+                chunks_sent: index + 1,
+                total_chunks,
+                bytes_sent,
+                total_bytes: data.len(),
+            },
+        );
+    }
+
+    Ok(TransferReport {
+        chunks_sent: total_chunks,
+        bytes_sent,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+#[tauri::command]
+pub fn send_chunked(
+    app: tauri::AppHandle,
+    handle_id: u64,
+    message_type: u8,
+    data: Vec<u8>,
+    chunk_size: usize,
+) -> Result<TransferReport, SerialError> {
+    send_chunked_with(
+        handle_id,
+        message_type,
+        &data,
+        chunk_size,
+        &events::app_sink(app),
+    )
+}
+
+fn read_source(path: &str) -> Result<Vec<u8>, SerialError> {
+    std::fs::read(path).map_err(|err| SerialError::IoError {
+        message: format!("cannot read {path}: {err}"),
+    })
+}
+
+#[tauri::command]
+pub fn send_file(
+    app: tauri::AppHandle,
+    handle_id: u64,
+    path: String,
+    message_type: u8,
+    chunk_size: usize,
+) -> Result<TransferReport, SerialError> {
+    let data = read_source(&path)?;
+    send_chunked_with(
+        handle_id,
+        message_type,
+        &data,
+        chunk_size,
+        &events::app_sink(app),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::testing;
+    use crate::frame::build_frame;
+    use crate::mock_port::MockPort;
+    use crate::serial::insert_mock;
+    use std::time::Duration;
+
+    const MSG_WRITE_BLOB: u8 = 0x30;
+
+    #[test]
+    fn file_is_sent_in_acknowledged_chunks() {
+        let path = std::env::temp_dir().join(format!("vscope-blob-{}.bin", std::process::id()));
+        let blob: Vec<u8> = (0..10).collect();
+        std::fs::write(&path, &blob).unwrap();
+
+        let port = MockPort::new();
+        for _ in 0..3 {
+            port.queue_reply(&build_frame(&[MSG_WRITE_BLOB]).unwrap());
+        }
+        let handle_id = insert_mock(&port);
+        let (sink, captured) = testing::capture();
+
+        let data = read_source(path.to_str().unwrap()).unwrap();
+        let report = send_chunked_with(handle_id, MSG_WRITE_BLOB, &data, 4, &sink).unwrap();
+        assert_eq!(report.chunks_sent, 3);
+        assert_eq!(report.bytes_sent, 10);
+
+        let mut expected = Vec::new();
+        for chunk in blob.chunks(4) {
+            let mut payload = vec![MSG_WRITE_BLOB];
+            payload.extend_from_slice(chunk);
+            expected.extend(build_frame(&payload).unwrap());
+        }
+        assert_eq!(port.written(), expected);
+
+        let progress = testing::wait_for(&captured, TRANSFER_PROGRESS_EVENT, 3, Duration::ZERO);
+        assert_eq!(progress.len(), 3);
+        assert_eq!(progress[2]["bytesSent"], 10);
+        assert_eq!(progress[2]["totalChunks"], 3);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn error_ack_stops_transfer() {
+        let port = MockPort::new();
+        port.queue_reply(&build_frame(&[MSG_WRITE_BLOB]).unwrap());
+        port.queue_reply(&build_frame(&[MSG_ERROR, 0x02]).unwrap());
+        let handle_id = insert_mock(&port);
+        let (sink, _) = testing::capture();
+        let result = send_chunked_with(handle_id, MSG_WRITE_BLOB, &[0u8; 12], 4, &sink);
+        assert!(matches!(result, Err(SerialError::InvalidResponse { .. })));
+        assert_eq!(port.state.lock().unwrap().flush_calls, 2);
+    }
+
+    #[test]
+    fn missing_file_is_a_clear_error() {
+        let err = read_source("/nonexistent/vscope-blob.bin").unwrap_err();
+        match err {
+            SerialError::IoError { message } => assert!(message.contains("vscope-blob.bin")),
+            other => panic!("unexpected error {other:?}"),
+        }
+    }
+}
@@ -1,4 +1,5 @@
 import Database from "@tauri-apps/plugin-sql";
+import { invoke } from "@tauri-apps/api/core";
 import { appDataDir, join } from "@tauri-apps/api/path";
 import { z } from "zod";
 
@@ -51,3 +52,20 @@ export async function selectOne<T>(
   const rows = await selectAll(sql, schema, params);
   return rows[0] ?? null;
 }
+
+/**
+ * Deletes and re-migrates the database. The plugin pool is closed first so
+ * the files can be removed; the next `getDb` call opens a fresh one.
+ */
+export async function resetDatabase(): Promise<void> {
+  if (dbPromise) {
+    const pending = dbPromise;
+    dbPromise = null;
+    try {
+      await (await pending).close();
+    } catch {
+      // A pool that never opened has nothing to close.
+    }
+  }
+  await invoke("reset_database");
+}
@@ -0,0 +1,27 @@
+import { describe, expect, test } from "bun:test";
+import { InsufficientSpaceError, parseSpaceError } from "./snapshots";
+
+describe("parseSpaceError", () => {
+  test("maps InsufficientSpace to InsufficientSpaceError", () => {
+    const error = parseSpaceError({
+      type: "InsufficientSpace",
+      data: { needed: 20_000_000, available: 1_000 },
+    });
+    expect(error).toBeInstanceOf(InsufficientSpaceError);
+    expect((error as InsufficientSpaceError).needed).toBe(20_000_000);
+    expect((error as InsufficientSpaceError).available).toBe(1_000);
+  });
+
+  test("keeps a failed space query distinct from a full disk", () => {
+    const error = parseSpaceError({
+      type: "SpaceUnknown",
+      data: { message: "no existing directory" },
+    });
+    expect(error).not.toBeInstanceOf(InsufficientSpaceError);
+    expect(error.message).toContain("no existing directory");
+  });
+
+  test("wraps anything else as a plain Error", () => {
+    expect(parseSpaceError("boom").message).toBe("boom");
+  });
+});
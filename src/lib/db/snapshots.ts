@@ -1,3 +1,4 @@
+import { invoke } from "@tauri-apps/api/core";
 import { z } from "zod";
 import { getDb, selectAll, selectOne } from "./index";
 import { SnapshotMetaSchema, type SnapshotMeta } from "../snapshots/schema";
@@ -104,10 +105,61 @@ export async function listSnapshotMeta(): Promise<SnapshotMeta[]> {
   return rows.map((row) => decodeSnapshotMeta(row));
 }
 
+/** The data volume lacks room for a snapshot; `needed` includes the margin. */
+export class InsufficientSpaceError extends Error {
+  readonly needed: number;
+  readonly available: number;
+
+  constructor(needed: number, available: number) {
+    super(
+      `insufficient disk space: ${needed} bytes needed, ${available} bytes free`,
+    );
+    this.name = "InsufficientSpaceError";
+    this.needed = needed;
+    this.available = available;
+  }
+}
+
+const SpaceErrorSchema = z.discriminatedUnion("type", [
+  z.object({
+    type: z.literal("InsufficientSpace"),
+    data: z.object({ needed: z.number(), available: z.number() }),
+  }),
+  z.object({
+    type: z.literal("SpaceUnknown"),
+    data: z.object({ message: z.string() }),
+  }),
+]);
+
+/** Maps a rejected `check_snapshot_space` invoke to an `Error`. */
+export const parseSpaceError = (e: unknown): Error => {
+  const parsed = SpaceErrorSchema.safeParse(e);
+  if (!parsed.success) {
+    return e instanceof Error ? e : new Error(String(e));
+  }
+  const { type, data } = parsed.data;
+  return type === "InsufficientSpace"
+    ? new InsufficientSpaceError(data.needed, data.available)
+    : new Error(`cannot check disk space: ${data.message}`);
+};
+
+export type SaveSnapshotOptions = {
+  /** Refuse the save up front when the data volume lacks room for it. */
+  checkSpace?: boolean;
+};
+
 export async function saveSnapshot(
   meta: SnapshotMeta,
   data: Float32Array,
+  options: SaveSnapshotOptions = {},
 ): Promise<void> {
+  if (options.checkSpace) {
+    await invoke("check_snapshot_space", { byteLen: data.byteLength }).catch(
+      (e: unknown) => {
+        throw parseSpaceError(e);
+      },
+    );
+  }
   const db = await getDb();
   await db.execute("BEGIN");
   try {
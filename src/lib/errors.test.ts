@@ -20,6 +20,15 @@ describe("parseSerialError", () => {
     expect((err as { path: string }).path).toBe("/dev/ttyUSB0");
   });
 
+  it("parses PermissionDenied from Rust JSON", () => {
+    const err = parseSerialError({
+      type: "PermissionDenied",
+      data: { path: "/dev/ttyUSB0" },
+    });
+    expect(err._tag).toBe("PermissionDenied");
+    expect((err as { path: string }).path).toBe("/dev/ttyUSB0");
+  });
+
   it("parses InvalidHandle from Rust JSON", () => {
     const err = parseSerialError({
       type: "InvalidHandle",
@@ -57,6 +66,17 @@ describe("parseSerialError", () => {
     expect(err._tag).toBe("PayloadTooLarge");
   });
 
+  it("parses InvalidResponse from Rust JSON", () => {
+    const err = parseSerialError({
+      type: "InvalidResponse",
+      data: { message: "field runs past the payload" },
+    });
+    expect(err._tag).toBe("InvalidResponse");
+    expect((err as { message: string }).message).toBe(
+      "field runs past the payload",
+    );
+  });
+
   it("falls back to IoError for plain string", () => {
     const err = parseSerialError("something went wrong");
     expect(err._tag).toBe("IoError");
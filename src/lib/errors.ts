@@ -5,12 +5,19 @@ import { DeviceError as CodecDeviceError, ErrorCode } from "./protocol";
 export type SerialError = Data.TaggedEnum<{
   PortNotFound: { readonly path: string };
   PortBusy: { readonly path: string };
+  PermissionDenied: { readonly path: string };
   InvalidHandle: { readonly handleId: number };
   Timeout: {};
+  WouldBlock: {};
   CrcMismatch: {};
   IoError: { readonly message: string };
   InvalidConfig: { readonly message: string };
   PayloadTooLarge: {};
+  InvalidResponse: { readonly message: string };
+  PolicyDenied: { readonly messageType: number };
+  Disconnected: { readonly message: string };
+  RateLimited: { readonly retryAfterMs: number };
+  StreamActive: {};
 }>;
 
 export const SerialError = Data.taggedEnum<SerialError>();
@@ -77,12 +84,18 @@ export const parseSerialError = (e: unknown): SerialError => {
         return SerialError.PortBusy({
           path: String(rustErr.data?.path ?? "unknown"),
         });
+      case "PermissionDenied":
+        return SerialError.PermissionDenied({
+          path: String(rustErr.data?.path ?? "unknown"),
+        });
       case "InvalidHandle":
         return SerialError.InvalidHandle({
           handleId: Number(rustErr.data?.handle_id ?? 0),
         });
       case "Timeout":
         return SerialError.Timeout();
+      case "WouldBlock":
+        return SerialError.WouldBlock();
       case "CrcMismatch":
         return SerialError.CrcMismatch();
       case "IoError":
@@ -95,6 +108,24 @@ export const parseSerialError = (e: unknown): SerialError => {
         });
       case "PayloadTooLarge":
         return SerialError.PayloadTooLarge();
+      case "InvalidResponse":
+        return SerialError.InvalidResponse({
+          message: String(rustErr.data?.message ?? "unknown response error"),
+        });
+      case "PolicyDenied":
+        return SerialError.PolicyDenied({
+          messageType: Number(rustErr.data?.message_type ?? 0),
+        });
+      case "Disconnected":
+        return SerialError.Disconnected({
+          message: String(rustErr.data?.message ?? "device disconnected"),
+        });
+      case "RateLimited":
+        return SerialError.RateLimited({
+          retryAfterMs: Number(rustErr.data?.retry_after_ms ?? 0),
+        });
+      case "StreamActive":
+        return SerialError.StreamActive();
     }
   }
 
@@ -17,9 +17,15 @@ export const PortInfoSchema = z.object({
   product: z.string().nullable(),
   serialNumber: z.string().nullable(),
   portType: z.enum(["usb", "bluetooth", "pci", "unknown"]),
+  stableId: z.string(),
 });
 export type PortInfo = z.infer<typeof PortInfoSchema>;
 
+export const AnnotatedPortSchema = PortInfoSchema.extend({
+  openHandleId: z.number().int().nullable(),
+});
+export type AnnotatedPort = z.infer<typeof AnnotatedPortSchema>;
+
 export const OpenDeviceInputSchema = z.object({
   path: z.string().min(1),
   config: SerialConfigSchema,